@@ -0,0 +1,148 @@
+//! This module contains admin interface.
+//!
+//! Admin interface speaks Redis protocol, so `redis-cli` can be used:
+//! `redis-cli -p 6579 METRICS`.
+//!
+use crate::app::messages::MainLoopEvent;
+use crate::config::Config;
+use crate::metrics;
+use crate::redis::parser::parse_command;
+use crate::redis::sentinel::events::SENTINEL_STATE;
+use crate::redis::types::RedisError;
+use log::{debug, error, info};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Max time to wait main loop reloads config.
+const RELOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Encode bulk string reply.
+fn bulk_string_reply(data: &str) -> Vec<u8> {
+    format!("${}\r\n{}\r\n", data.len(), data).into_bytes()
+}
+
+/// State of group seen by sentinels.
+fn render_sentinel_state() -> String {
+    match SENTINEL_STATE.lock() {
+        Ok(state) => state.render(),
+        Err(_) => String::from("# Sentinel\r\n"),
+    }
+}
+
+/// Ask main loop to reload config and wait report.
+fn reload_config(tx_main_loop_message: &Sender<MainLoopEvent>) -> Vec<u8> {
+    let (tx_reply, rx_reply) = mpsc::channel();
+
+    if tx_main_loop_message
+        .send(MainLoopEvent::reload(Some(tx_reply)))
+        .is_err()
+    {
+        return b"-ERR main loop is stopped\r\n".to_vec();
+    }
+
+    match rx_reply.recv_timeout(RELOAD_TIMEOUT) {
+        Ok(Ok(report)) => bulk_string_reply(&report),
+        Ok(Err(e)) => format!("-ERR {}\r\n", e.replace(['\r', '\n'], " ")).into_bytes(),
+        Err(_) => b"-ERR reload timeout\r\n".to_vec(),
+    }
+}
+
+/// Execute an admin command.
+fn execute_admin_command(
+    args: &[Vec<u8>],
+    tx_main_loop_message: &Sender<MainLoopEvent>,
+) -> Vec<u8> {
+    let name = String::from_utf8_lossy(&args[0]).to_ascii_uppercase();
+
+    match name.as_str() {
+        "PING" => b"+PONG\r\n".to_vec(),
+        "METRICS" => bulk_string_reply(&metrics::render()),
+        "SENTINEL" => bulk_string_reply(&render_sentinel_state()),
+        "RELOAD" => reload_config(tx_main_loop_message),
+        "INFO" => bulk_string_reply(&format!(
+            "{}\r\n{}",
+            metrics::render(),
+            render_sentinel_state()
+        )),
+        e => format!("-ERR unknown admin command '{}'\r\n", e).into_bytes(),
+    }
+}
+
+/// Read commands of admin client until connection is closed.
+fn manage_admin_client(
+    mut stream: TcpStream,
+    tx_main_loop_message: Sender<MainLoopEvent>,
+) -> std::io::Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut data = [0; 2048];
+
+    loop {
+        let len = stream.read(&mut data)?;
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        buffer.extend_from_slice(&data[..len]);
+
+        while let Some((args, size)) = parse_command(&buffer)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?
+        {
+            buffer.drain(..size);
+
+            if args.is_empty() {
+                continue;
+            }
+
+            if args[0].eq_ignore_ascii_case(b"QUIT") {
+                stream.write_all(b"+OK\r\n")?;
+                return Ok(());
+            }
+
+            stream.write_all(&execute_admin_command(&args, &tx_main_loop_message))?;
+        }
+    }
+}
+
+/// Start admin interface if configured.
+/// Create a new thread for do this.
+pub fn watch_admin_connection(
+    config: &Config,
+    tx_main_loop_message: Sender<MainLoopEvent>,
+) -> Result<(), RedisError> {
+    let admin = match config.admin.as_ref() {
+        Some(a) => a,
+        None => return Ok(()),
+    };
+
+    info!("Listen admin connection to {}", &admin.bind);
+
+    let listener = match TcpListener::bind(&admin.bind) {
+        Ok(l) => l,
+        Err(e) => return Err(RedisError::from_io_error(e)),
+    };
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    debug!("watch_admin_connection(): New admin client");
+
+                    let tx_main_loop_message = tx_main_loop_message.clone();
+
+                    thread::spawn(move || {
+                        if let Err(e) = manage_admin_client(stream, tx_main_loop_message) {
+                            debug!("Admin client error: {:?}", e);
+                        }
+                    });
+                }
+                Err(e) => error!("Error when establish admin connection {:?}.", e),
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -1,8 +1,10 @@
 //! Main messages.
 //!
 use std::net::{SocketAddr, TcpStream};
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc::Sender;
 
-use crate::{redis::{sentinel::MasterChangeNotification, stream::network::NetworkStream}, workers::WorkerEventReceiver};
+use crate::{client::session::ClientSession, metrics, redis::{node::create_redis_stream_connection, sentinel::{events::SentinelEvent, MasterChangeNotification}, stream::{network::NetworkStream, RedisStream}, types::RedisError}, workers::WorkerEventReceiver};
 
 /// Message to communicate with main loop
 #[derive(Debug)]
@@ -13,6 +15,16 @@ pub struct MainLoopEvent {
     pub master_change: Option<MasterChangeNotification>,
     /// Worker get and release client
     pub worker_message: Option<GetAndReleaseClient>,
+    /// Client leaves push worker (subscribe mode or blocking command ended) and goes back to workers
+    pub released_client: Option<ClientConnectionParameter>,
+    /// Event published by sentinel (+sdown, +odown...)
+    pub sentinel_event: Option<SentinelEvent>,
+    /// Reload config file (SIGHUP or admin command)
+    pub reload: Option<ReloadRequest>,
+    /// Stop gracefully (SIGTERM or SIGINT)
+    pub shutdown: bool,
+    /// New process asks listening socket (zero-downtime restart)
+    pub upgrade: Option<UnixStream>,
 }
 
 impl MainLoopEvent {
@@ -22,6 +34,11 @@ impl MainLoopEvent {
             new_client: Some((tcp_stream, socket_addr)),
             master_change: None,
             worker_message: None,
+            released_client: None,
+            sentinel_event: None,
+            reload: None,
+            shutdown: false,
+            upgrade: None,
         }
     }
 
@@ -31,6 +48,11 @@ impl MainLoopEvent {
             new_client: None,
             master_change: Some(new_master),
             worker_message: None,
+            released_client: None,
+            sentinel_event: None,
+            reload: None,
+            shutdown: false,
+            upgrade: None,
         }
     }
 
@@ -44,6 +66,11 @@ impl MainLoopEvent {
                 client_to_release: None,
                 tx_worker_message
             }),
+            released_client: None,
+            sentinel_event: None,
+            reload: None,
+            shutdown: false,
+            upgrade: None,
         }
     }
 
@@ -57,8 +84,90 @@ impl MainLoopEvent {
                 client_to_release: Some(client),
                 tx_worker_message
             }),
+            released_client: None,
+            sentinel_event: None,
+            reload: None,
+            shutdown: false,
+            upgrade: None,
         }
-    }    
+    }
+
+    /// Create message to notify an event published by sentinel
+    pub fn sentinel_event(event: SentinelEvent) -> Self {
+        Self {
+            new_client: None,
+            master_change: None,
+            worker_message: None,
+            released_client: None,
+            sentinel_event: Some(event),
+            reload: None,
+            shutdown: false,
+            upgrade: None,
+        }
+    }
+
+    /// Create message to reload config file
+    pub fn reload(reply: Option<Sender<Result<String, String>>>) -> Self {
+        Self {
+            new_client: None,
+            master_change: None,
+            worker_message: None,
+            released_client: None,
+            sentinel_event: None,
+            reload: Some(ReloadRequest { reply }),
+            shutdown: false,
+            upgrade: None,
+        }
+    }
+
+    /// Create message to stop gracefully
+    pub fn shutdown() -> Self {
+        Self {
+            new_client: None,
+            master_change: None,
+            worker_message: None,
+            released_client: None,
+            sentinel_event: None,
+            reload: None,
+            shutdown: true,
+            upgrade: None,
+        }
+    }
+
+    /// Create message to hand sockets to new process
+    pub fn upgrade(stream: UnixStream) -> Self {
+        Self {
+            new_client: None,
+            master_change: None,
+            worker_message: None,
+            released_client: None,
+            sentinel_event: None,
+            reload: None,
+            shutdown: false,
+            upgrade: Some(stream),
+        }
+    }
+
+    /// Release a client that no more waits data from Redis
+    pub fn push_release_client(client: ClientConnectionParameter) -> Self {
+        Self {
+            new_client: None,
+            master_change: None,
+            worker_message: None,
+            released_client: Some(client),
+            sentinel_event: None,
+            reload: None,
+            shutdown: false,
+            upgrade: None,
+        }
+    }
+}
+
+/// Reload config file
+#[derive(Debug)]
+pub struct ReloadRequest {
+    /// Channel to send report of reload (admin command)
+    pub reply: Option<Sender<Result<String, String>>>
 }
 
 /// Get and release client
@@ -81,11 +190,45 @@ pub struct ClientConnectionParameter {
     /// Client stream
     pub client_stream: NetworkStream,
     /// Redis stream
-    pub redis_stream: NetworkStream
+    pub redis_stream: NetworkStream,
+    /// Address of Redis of redis_stream
+    pub redis_addr: String,
+    /// Protocol state
+    pub session: ClientSession
+}
+
+impl ClientConnectionParameter {
+    /// Replace connection to Redis by a connection to new master.
+    /// State of client (authentication, database, subscriptions...) is sent again.
+    pub fn reconnect(&mut self, redis_addr: &str) -> Result<(), RedisError> {
+        let mut redis_stream = create_redis_stream_connection(redis_addr)?;
+        let replay = self.session.reset_redis_connection();
+
+        if !replay.is_empty() {
+            redis_stream.write(&replay).map_err(RedisError::from_io_error)?;
+        }
+
+        self.redis_stream = redis_stream;
+        self.redis_addr = String::from(redis_addr);
+
+        Ok(())
+    }
+
+    /// Client can be handed to another process: nothing in flight and no state to replay.
+    pub fn is_transferable(&self) -> bool {
+        self.session.is_transferable() && self.client_stream.buffer().is_empty() && self.redis_stream.buffer().is_empty()
+            && !self.client_stream.has_pending_output() && !self.redis_stream.has_pending_output()
+    }
 }
 
 impl std::fmt::Debug for ClientConnectionParameter {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         write!(f, "ClientConnectionParameter {{ id: {}, client_addr: {:?}, client_stream: <can't display>, redis_stream: <can't display>}}", self.id, self.client_addr)
     }
-}
\ No newline at end of file
+}
+
+impl Drop for ClientConnectionParameter {
+    fn drop(&mut self) {
+        metrics::CONNECTED_CLIENTS.decrement();
+    }
+}
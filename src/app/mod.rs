@@ -2,63 +2,156 @@
 //! Wait message from watch_new_client_connection and workers and dispatch client to worker.
 //!
 use std::collections::VecDeque;
-use std::{net::{SocketAddr, TcpStream}, sync::mpsc::Receiver};
-use log::{debug, error};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{net::{SocketAddr, TcpStream}, sync::mpsc::{Receiver, RecvTimeoutError, Sender}};
+use log::{debug, error, info, warn};
 use uuid::Uuid;
 
-use messages::{GetAndReleaseClient, ClientConnectionParameter, MainLoopEvent};
+use messages::{GetAndReleaseClient, ClientConnectionParameter, MainLoopEvent, ReloadRequest};
+use crate::cli::ConfigSource;
+use crate::client::{listener_fd, session::ClientSession, set_max_clients, stop_accepting};
+use crate::config::{validate_config, Config, ConfigWorkerPool};
+use crate::metrics;
+use crate::ratelimit::RateLimiter;
+use crate::logging::reload_log;
+use crate::reload::{diff_config, merge_config};
 use crate::workers::messages::WorkerEvent;
-use crate::redis::{node::create_redis_stream_connection, stream::network::NetworkStream};
-use crate::workers::WorkerEventReceiver;
+use crate::redis::{node::create_redis_stream_connection, sentinel::MasterChangeNotification, stream::network::NetworkStream};
+use crate::redis::sentinel::events::{SentinelEvent, SENTINEL_STATE};
+use crate::redis::sentinel::reload_sentinels;
+use crate::upgrade::{send_clients, send_listener};
+use crate::workers::{create_workers_pool, WorkerEventReceiver};
+use crate::workers::push::{create_push_worker, PushEvent};
 
 pub mod messages;
 
-pub fn run_main_loop(rx_main_loop_message: Receiver<MainLoopEvent>, redis_addr: String) -> Result<(), String> {
+/// On shutdown, how often check if all clients are closed.
+const SHUTDOWN_CHECK: Duration = Duration::from_millis(100);
+
+/// State of main loop.
+struct MainLoopState {
+    /// Clients waiting a worker
+    clients: VecDeque<ClientConnectionParameter>,
+    /// Current master address
+    redis_master_addr: String,
+    /// Workers waiting a client
+    workers: VecDeque<WorkerEventReceiver>,
+    /// Number of workers (waiting or not)
+    workers_count: u8,
+    /// Channel to main loop, to create workers
+    tx_main_loop_message: Sender<MainLoopEvent>,
+    /// Channel to push worker that manages subscribed clients
+    tx_push_message: Sender<PushEvent>,
+    /// Channel to push worker that manages clients blocked by a command (BLPOP...)
+    tx_wait_message: Sender<PushEvent>,
+    /// Shared rate limiter
+    rate_limiter: Option<Arc<RateLimiter>>,
+    config: Config,
+    /// Config file and overrides, read again on reload
+    config_source: ConfigSource,
+    /// Shutdown in progress: time to stop waiting in-flight commands
+    shutdown_deadline: Option<Instant>,
+    /// Connection to new process that receives drained clients (upgrade)
+    upgrade_stream: Option<UnixStream>,
+}
+
+pub fn run_main_loop(rx_main_loop_message: Receiver<MainLoopEvent>, tx_main_loop_message: Sender<MainLoopEvent>, redis_addr: String, config: Config, config_source: ConfigSource) -> Result<(), String> {
     debug!("run_main_loop(): Start main event loop");
 
-    let mut clients: VecDeque<ClientConnectionParameter> = VecDeque::new(); 
-    let mut redis_master_addr = String::from(redis_addr);
-    let mut workers: VecDeque<WorkerEventReceiver> = VecDeque::new();
+    let mut state = MainLoopState {
+        clients: VecDeque::new(),
+        redis_master_addr: String::from(redis_addr),
+        workers: VecDeque::new(),
+        workers_count: config.workers.pool.min,
+        tx_push_message: create_push_worker("push-worker", tx_main_loop_message.clone()),
+        tx_wait_message: create_push_worker("wait-worker", tx_main_loop_message.clone()),
+        tx_main_loop_message,
+        rate_limiter: config.rate_limit.clone().map(|c| Arc::new(RateLimiter::new(c))),
+        config,
+        config_source,
+        shutdown_deadline: None,
+        upgrade_stream: None,
+    };
 
     loop {
+        if let Some(deadline) = state.shutdown_deadline {
+            if metrics::CONNECTED_CLIENTS.get() == 0 || Instant::now() >= deadline {
+                finish_shutdown(&mut state);
+                return Ok(());
+            }
+        }
+
         debug!("run_main_loop(): Wait to receive a new message");
-        match rx_main_loop_message.recv() {
-            Ok(event) => manage_message(event, &mut redis_master_addr, &mut clients, &mut workers),
-            Err(_) => return Err(String::from("Main channel is closed!"))
+
+        let event = match state.shutdown_deadline {
+            // Wake up regularly to check if clients are drained
+            Some(_) => rx_main_loop_message.recv_timeout(SHUTDOWN_CHECK),
+            None => rx_main_loop_message.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match event {
+            Ok(event) => manage_message(event, &mut state),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err(String::from("Main channel is closed!"))
         }
     }
 }
 
-fn manage_message(event: MainLoopEvent, redis_master_addr: &mut String, clients: &mut VecDeque<ClientConnectionParameter>, workers: &mut VecDeque<WorkerEventReceiver>) {
+fn manage_message(event: MainLoopEvent, state: &mut MainLoopState) {
     debug!("manage_message(): New message receive");
 
-    if let Some(client) = event.new_client {
+    if event.shutdown {
+        manage_message_shutdown(state);
+    } else if let Some(client) = event.new_client {
         let (client_stream, client_addr) = client;
-        
-        if let Some(()) = manage_message_new_client(client_addr, client_stream, clients, redis_master_addr) {
-            send_client_to_worker(clients, workers);
+
+        if state.shutdown_deadline.is_some() {
+            debug!("manage_message(): Shutdown in progress, close new client {}", client_addr);
+            metrics::CONNECTED_CLIENTS.decrement();
+            return;
+        }
+
+        if let Some(()) = manage_message_new_client(client_addr, client_stream, state) {
+            send_client_to_worker(&mut state.clients, &mut state.workers);
         }
     } else if let Some(worker_message) = event.worker_message {
-        manage_message_worker(worker_message, clients, workers);
+        manage_message_worker(worker_message, state);
+    } else if let Some(client) = event.released_client {
+        if let Some(client) = drain_client(client, state).and_then(|client| check_client_master(client, &state.redis_master_addr)) {
+            state.clients.push_back(client);
+            send_client_to_worker(&mut state.clients, &mut state.workers);
+        }
     } else if let Some(master) = event.master_change {
-        // TODO
+        manage_message_master_change(master, state);
+    } else if let Some(sentinel_event) = event.sentinel_event {
+        manage_message_sentinel_event(sentinel_event, state);
+    } else if let Some(reload) = event.reload {
+        manage_message_reload(reload, state);
+    } else if let Some(stream) = event.upgrade {
+        manage_message_upgrade(stream, state);
     }
 }
 
-fn manage_message_new_client(client_addr: SocketAddr, client_stream: TcpStream, clients: &mut VecDeque<ClientConnectionParameter>, redis_master_addr: &String) -> Option<()> {
+fn manage_message_new_client(client_addr: SocketAddr, client_stream: TcpStream, state: &mut MainLoopState) -> Option<()> {
     let key = format!("{}:{} - {}", client_addr.ip().to_string(), client_addr.port(), Uuid::new_v4());
 
     debug!("manage_message_new_client(): Main loop receive a new client from {}", key);
 
     // Create one connection to master per client
-    if let Ok(client_redis_stream) = create_redis_stream_connection(redis_master_addr) {
+    // Slot of client is reserved when connection is accepted, and released when client is dropped
+    if let Ok(client_redis_stream) = create_redis_stream_connection(&state.redis_master_addr) {
         // Appends an element at the end of collection.
-        clients.push_back(
+        state.clients.push_back(
             ClientConnectionParameter {
                 id: key,
                 client_addr: client_addr,
                 client_stream: NetworkStream::new(client_stream),
-                redis_stream: client_redis_stream
+                redis_stream: client_redis_stream,
+                redis_addr: state.redis_master_addr.clone(),
+                session: ClientSession::new(state.config.namespace.clone(), client_addr.ip(), state.rate_limiter.clone(), state.config.blocking_failover.clone())
             }
         );
 
@@ -66,23 +159,40 @@ fn manage_message_new_client(client_addr: SocketAddr, client_stream: TcpStream,
     } else {
         error!("Can't create new Redis master connection");
 
+        metrics::CONNECTED_CLIENTS.decrement();
+
         None
     }
 }
 
-fn manage_message_worker(worker_message: GetAndReleaseClient, clients: &mut VecDeque<ClientConnectionParameter>, workers: &mut VecDeque<WorkerEventReceiver>) {
+fn manage_message_worker(worker_message: GetAndReleaseClient, state: &mut MainLoopState) {
     let worker_name = worker_message.worker_id;
+    let client_idle_timeout = state.config.client_idle_timeout;
     
     // Check if client resend by worker to put client in clients list
     if let Some(client) = worker_message.client_to_release {
-        clients.push_back(client);
+        if client_idle_timeout > 0 && client.session.is_idle(Duration::from_secs(client_idle_timeout)) {
+            debug!("manage_message_worker(): Close idle client {}", client.id);
+            metrics::IDLE_CLIENTS_CLOSED.increment();
+            // Client is dropped, connections are closed
+        } else if let Some(client) = drain_client(client, state).and_then(|client| check_client_master(client, &state.redis_master_addr)) {
+            if client.session.is_subscribed() {
+                // Client waits messages, push worker manages it
+                let _ = state.tx_push_message.send(PushEvent::send_client(client));
+            } else if client.session.is_blocked() {
+                // Client waits reply of blocking command, don't hold a worker
+                let _ = state.tx_wait_message.send(PushEvent::send_client(client));
+            } else {
+                state.clients.push_back(client);
+            }
+        }
     }
 
-    if clients.is_empty() {
+    if state.clients.is_empty() {
         debug!("manage_message_worker(): Worker '{}' want a client, but no client connected. Put worker in list.", worker_name.clone());
 
         // No clients are available, push worker in workers list
-        workers.push_back(worker_message.tx_worker_message);
+        state.workers.push_back(worker_message.tx_worker_message);
         return;
     }
 
@@ -92,11 +202,244 @@ fn manage_message_worker(worker_message: GetAndReleaseClient, clients: &mut VecD
     debug!("manage_message_worker(): Worker '{}' want a client, send it.", worker_name.clone());
 
     // Get a client
-    let client = clients.pop_front().unwrap();
+    let client = state.clients.pop_front().unwrap();
 
     let _ = worker_message.tx_worker_message.send(WorkerEvent::send_client(client));
 }
 
+fn manage_message_master_change(master: MasterChangeNotification, state: &mut MainLoopState) {
+    info!("Master change from {} to {}", state.redis_master_addr, master.new);
+
+    state.redis_master_addr = master.new;
+
+    if let Ok(mut sentinel_state) = SENTINEL_STATE.lock() {
+        sentinel_state.master_changed();
+    }
+
+    // Clients owned by workers are reconnected when they come back
+    let clients = std::mem::take(&mut state.clients);
+
+    for client in clients {
+        if let Some(client) = check_client_master(client, &state.redis_master_addr) {
+            state.clients.push_back(client);
+        }
+    }
+
+    let _ = state.tx_push_message.send(PushEvent::master_change(state.redis_master_addr.clone()));
+    let _ = state.tx_wait_message.send(PushEvent::master_change(state.redis_master_addr.clone()));
+}
+
+fn manage_message_sentinel_event(event: SentinelEvent, state: &mut MainLoopState) {
+    match &event {
+        SentinelEvent::ObjectivelyDown(instance) if instance.addr == state.redis_master_addr => {
+            warn!("Master {} is objectively down, failover is coming", instance.addr);
+        }
+        SentinelEvent::FailoverAbort(reason, instance) => {
+            warn!("Failover of {} aborted: {}", instance.group_name, reason);
+        }
+        e => debug!("manage_message_sentinel_event(): {:?}", e),
+    }
+
+    if let Ok(mut sentinel_state) = SENTINEL_STATE.lock() {
+        sentinel_state.apply(&event);
+    }
+}
+
+fn manage_message_reload(reload: ReloadRequest, state: &mut MainLoopState) {
+    let result = reload_config(state);
+
+    match &result {
+        Ok(report) => info!("Config reloaded: {}", report.replace("\r\n", " ").trim()),
+        Err(e) => error!("Can't reload config: {}", e),
+    }
+
+    if let Some(reply) = reload.reply {
+        let _ = reply.send(result);
+    }
+}
+
+/// Read config file again and apply fields that can change live.
+/// Return report of changes.
+fn reload_config(state: &mut MainLoopState) -> Result<String, String> {
+    let new_config = state.config_source.load().map_err(|e| e.to_string())?;
+
+    let errors = validate_config(&new_config);
+
+    if !errors.is_empty() {
+        return Err(errors.join(", "));
+    }
+
+    let changes = diff_config(&state.config, &new_config);
+    let new_config = merge_config(&state.config, new_config);
+
+    for field in &changes.live {
+        match *field {
+            "log" => {
+                if let Err(e) = reload_log(&new_config.log) {
+                    error!("Can't reload log config: {}", e);
+                }
+            }
+            "workers" => resize_workers_pool(&new_config.workers.pool, state),
+            "max_clients" => set_max_clients(new_config.max_clients),
+            "sentinels" | "timeout" => {
+                if let Some(sentinels) = new_config.sentinels.as_ref() {
+                    reload_sentinels(sentinels.clone(), new_config.timeout.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Read by main loop for each new client, connected clients keep their copy
+    if changes.new_connections.contains(&"rate_limit") {
+        state.rate_limiter = new_config.rate_limit.clone().map(|c| Arc::new(RateLimiter::new(c)));
+    }
+
+    for field in &changes.restart {
+        warn!("Config field '{}' changed, restart is required to apply it", field);
+    }
+
+    state.config = new_config;
+
+    Ok(changes.render())
+}
+
+/// Create or stop waiting workers to respect min and max of pool.
+/// Busy workers are never stopped.
+fn resize_workers_pool(pool: &ConfigWorkerPool, state: &mut MainLoopState) {
+    if state.workers_count < pool.min {
+        info!("Create {} workers", pool.min - state.workers_count);
+
+        create_workers_pool(pool.min - state.workers_count, &state.tx_main_loop_message);
+        state.workers_count = pool.min;
+    }
+
+    while state.workers_count > pool.max {
+        match state.workers.pop_front() {
+            Some(worker) => {
+                let _ = worker.send(WorkerEvent::shutdown());
+                state.workers_count -= 1;
+            }
+            None => break,
+        }
+    }
+}
+
+fn manage_message_shutdown(state: &mut MainLoopState) {
+    if state.shutdown_deadline.is_some() {
+        return;
+    }
+
+    info!("Shutdown, wait in-flight commands during {}ms", state.config.timeout.shutdown_drain);
+
+    state.shutdown_deadline = Some(Instant::now() + Duration::from_millis(state.config.timeout.shutdown_drain));
+
+    stop_accepting();
+
+    // Clients waiting a worker without command in flight are closed now, others when their
+    // replies are sent
+    let clients = std::mem::take(&mut state.clients);
+
+    for client in clients {
+        if let Some(client) = drain_client(client, state) {
+            state.clients.push_back(client);
+        }
+    }
+
+    let _ = state.tx_push_message.send(PushEvent::shutdown());
+    let _ = state.tx_wait_message.send(PushEvent::shutdown());
+}
+
+/// Send listening socket to new process, then shutdown. Drained clients go to new process if
+/// enabled.
+fn manage_message_upgrade(stream: UnixStream, state: &mut MainLoopState) {
+    if state.shutdown_deadline.is_some() {
+        warn!("Upgrade refused, shutdown in progress");
+        return;
+    }
+
+    let listener = match listener_fd() {
+        Some(fd) => fd,
+        None => {
+            error!("Upgrade refused, no listening socket");
+            return;
+        }
+    };
+
+    if let Err(e) = send_listener(&stream, listener) {
+        error!("Can't send listening socket to new process: {}", e);
+        return;
+    }
+
+    info!("Listening socket sent to new process");
+
+    if state.config.upgrade.as_ref().map(|u| u.clients).unwrap_or(false) {
+        state.upgrade_stream = Some(stream);
+    }
+
+    manage_message_shutdown(state);
+}
+
+/// All clients are closed or drain timeout is reached: close remaining clients and stop workers.
+fn finish_shutdown(state: &mut MainLoopState) {
+    let remaining = metrics::CONNECTED_CLIENTS.get();
+
+    if remaining > 0 {
+        warn!("Drain timeout reached, close {} clients", remaining);
+    }
+
+    // Connections to client and Redis are closed, new process stops waiting clients
+    state.clients.clear();
+    state.upgrade_stream = None;
+
+    for worker in state.workers.drain(..) {
+        let _ = worker.send(WorkerEvent::shutdown());
+    }
+
+    info!("Shutdown complete");
+}
+
+/// On shutdown, client without command in flight is closed, or sent to new process on upgrade.
+/// Return None if client is closed.
+fn drain_client(client: ClientConnectionParameter, state: &MainLoopState) -> Option<ClientConnectionParameter> {
+    if state.shutdown_deadline.is_some() && !client.session.has_pending_replies() && !client.client_stream.has_pending_output() {
+        if let Some(stream) = state.upgrade_stream.as_ref().filter(|_| client.is_transferable()) {
+            match send_clients(stream, &[client.client_stream.as_raw_fd()]) {
+                // New process owns a copy of socket, only our descriptor is closed
+                Ok(()) => debug!("drain_client(): Client {} sent to new process", client.id),
+                Err(e) => warn!("Can't send client {} to new process: {}", client.id, e),
+            }
+
+            return None;
+        }
+
+        debug!("drain_client(): Close client {}, no command in flight", client.id);
+
+        return None;
+    }
+
+    Some(client)
+}
+
+/// If client is connected to an old master, reconnect it to current master.
+/// Return None if reconnection failed, client is dropped.
+fn check_client_master(mut client: ClientConnectionParameter, redis_master_addr: &String) -> Option<ClientConnectionParameter> {
+    if &client.redis_addr == redis_master_addr {
+        return Some(client);
+    }
+
+    debug!("check_client_master(): Reconnect client {} to {}", client.id, redis_master_addr);
+
+    match client.reconnect(redis_master_addr) {
+        Ok(()) => Some(client),
+        Err(e) => {
+            error!("Can't reconnect client {} to new master: {:?}", client.id, e);
+
+            None
+        }
+    }
+}
+
 fn send_client_to_worker(clients: &mut VecDeque<ClientConnectionParameter>, workers: &mut VecDeque<WorkerEventReceiver>) {
     // First check if we have client
     // Second check if a worker is free
@@ -0,0 +1,198 @@
+//! This module contains command line interface.
+//!
+//! Config is built in this order (last wins): default values < config file < environment
+//! variables `REDCONCENTRATOR_*` < command line flags.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::config::{get_config, Config, Sentinels};
+use std::io::Error;
+
+/// Prefix of environment variables.
+const ENV_PREFIX: &str = "REDCONCENTRATOR_";
+
+/// What to do.
+#[derive(Debug, PartialEq)]
+pub enum CliMode {
+    /// Run proxy.
+    Run,
+    /// Validate config and exit.
+    CheckConfig,
+    /// Print config with default values and exit.
+    PrintConfig,
+    /// Print version and exit.
+    Version,
+    /// Print usage and exit.
+    Help,
+}
+
+/// Values that override config file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConfigOverrides {
+    pub config_file: Option<String>,
+    pub bind: Option<String>,
+    pub group_name: Option<String>,
+    /// Replace sentinel list (not merged).
+    pub sentinels: Vec<String>,
+    pub log_config: Option<String>,
+}
+
+impl ConfigOverrides {
+    /// Return overrides where values of other win.
+    pub fn merge(self, other: ConfigOverrides) -> Self {
+        ConfigOverrides {
+            config_file: other.config_file.or(self.config_file),
+            bind: other.bind.or(self.bind),
+            group_name: other.group_name.or(self.group_name),
+            sentinels: if other.sentinels.is_empty() {
+                self.sentinels
+            } else {
+                other.sentinels
+            },
+            log_config: other.log_config.or(self.log_config),
+        }
+    }
+
+    /// Override fields of config.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(bind) = self.bind.as_ref() {
+            config.bind = bind.clone();
+        }
+
+        if let Some(group_name) = self.group_name.as_ref() {
+            config.group_name = group_name.clone();
+        }
+
+        if !self.sentinels.is_empty() {
+            match config.sentinels.as_mut() {
+                Some(sentinels) => sentinels.address = self.sentinels.clone(),
+                None => config.sentinels = Some(Sentinels::new(self.sentinels.clone())),
+            }
+        }
+
+        if let Some(log_config) = self.log_config.as_ref() {
+            config.log.file = Some(log_config.clone());
+        }
+    }
+}
+
+/// Options of command line.
+#[derive(Debug, PartialEq)]
+pub struct CliOptions {
+    pub mode: CliMode,
+    pub overrides: ConfigOverrides,
+}
+
+/// Where config comes from. Used again when config is reloaded.
+#[derive(Debug, Clone)]
+pub struct ConfigSource {
+    pub file: String,
+    pub overrides: ConfigOverrides,
+}
+
+impl ConfigSource {
+    /// Read config file and apply overrides.
+    pub fn load(&self) -> Result<Config, Error> {
+        let mut config = get_config(self.file.clone())?;
+
+        self.overrides.apply(&mut config);
+
+        Ok(config)
+    }
+}
+
+/// Return usage of command line.
+pub fn usage() -> String {
+    String::from(
+        "
+Usage: red-concentrator [options] [config-file]
+
+Options:
+  --config <file>        config file (REDCONCENTRATOR_CONFIG)
+  --bind <ip:port>       listen address (REDCONCENTRATOR_BIND)
+  --group-name <name>    name of Redis group (REDCONCENTRATOR_GROUP_NAME)
+  --sentinel <host:port> sentinel address, repeatable (REDCONCENTRATOR_SENTINELS, comma separated)
+  --log-config <file>    log4rs config file (REDCONCENTRATOR_LOG_CONFIG)
+  --check-config         validate config and exit
+  --print-config         print config with default values (YAML) and exit
+  --version              print version and exit
+  --help                 print this help and exit
+
+Order of precedence: default values < config file < environment variables < options.
+",
+    )
+}
+
+/// Parse arguments of command line (without program name).
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<CliOptions, String> {
+    let mut options = CliOptions {
+        mode: CliMode::Run,
+        overrides: ConfigOverrides::default(),
+    };
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // Accept "--flag value" and "--flag=value"
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (String::from(flag), Some(String::from(value)))
+            }
+            _ => (arg, None),
+        };
+
+        let mut value = || match inline_value.clone().or_else(|| args.next()) {
+            Some(v) => Ok(v),
+            None => Err(format!("missing value of option {}", flag)),
+        };
+
+        match flag.as_str() {
+            "--config" => options.overrides.config_file = Some(value()?),
+            "--bind" => options.overrides.bind = Some(value()?),
+            "--group-name" => options.overrides.group_name = Some(value()?),
+            "--sentinel" => options.overrides.sentinels.push(value()?),
+            "--log-config" => options.overrides.log_config = Some(value()?),
+            "--check-config" => options.mode = CliMode::CheckConfig,
+            "--print-config" => options.mode = CliMode::PrintConfig,
+            "--version" | "-V" => options.mode = CliMode::Version,
+            "--help" | "-h" => options.mode = CliMode::Help,
+            f if f.starts_with('-') => return Err(format!("unknown option {}", f)),
+            _ if options.overrides.config_file.is_some() => {
+                return Err(format!("unexpected argument {}", flag))
+            }
+            _ => options.overrides.config_file = Some(flag),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Return overrides from environment variables `REDCONCENTRATOR_*`.
+pub fn parse_env<I: IntoIterator<Item = (String, String)>>(vars: I) -> ConfigOverrides {
+    let mut overrides = ConfigOverrides::default();
+
+    for (key, value) in vars {
+        let name = match key.strip_prefix(ENV_PREFIX) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        match name {
+            "CONFIG" => overrides.config_file = Some(value),
+            "BIND" => overrides.bind = Some(value),
+            "GROUP_NAME" => overrides.group_name = Some(value),
+            "SENTINELS" => {
+                overrides.sentinels = value
+                    .split(',')
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect()
+            }
+            "LOG_CONFIG" => overrides.log_config = Some(value),
+            _ => {}
+        }
+    }
+
+    overrides
+}
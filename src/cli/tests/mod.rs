@@ -0,0 +1,75 @@
+use crate::cli::{parse_args, parse_env, CliMode, ConfigOverrides};
+use crate::config::Config;
+
+fn args(data: &[&str]) -> Vec<String> {
+    data.iter().map(|a| String::from(*a)).collect()
+}
+
+#[test]
+fn parse_args_flags() {
+    let options = parse_args(args(&[
+        "--bind=0.0.0.0:6578",
+        "--sentinel",
+        "s1:26379",
+        "--sentinel",
+        "s2:26379",
+        "--check-config",
+        "config.yaml",
+    ]))
+    .unwrap();
+
+    assert_eq!(options.mode, CliMode::CheckConfig);
+    assert_eq!(options.overrides.bind.as_deref(), Some("0.0.0.0:6578"));
+    assert_eq!(options.overrides.sentinels, vec!["s1:26379", "s2:26379"]);
+    assert_eq!(
+        options.overrides.config_file.as_deref(),
+        Some("config.yaml")
+    );
+}
+
+#[test]
+fn parse_args_errors() {
+    assert!(parse_args(args(&["--unknown"])).is_err());
+    assert!(parse_args(args(&["--bind"])).is_err());
+    assert!(parse_args(args(&["a.yaml", "b.yaml"])).is_err());
+    assert_eq!(
+        parse_args(args(&["--version"])).unwrap().mode,
+        CliMode::Version
+    );
+}
+
+#[test]
+fn env_then_flags_override_config() {
+    let env = parse_env(vec![
+        (
+            String::from("REDCONCENTRATOR_BIND"),
+            String::from("10.0.0.1:6578"),
+        ),
+        (
+            String::from("REDCONCENTRATOR_GROUP_NAME"),
+            String::from("env"),
+        ),
+        (
+            String::from("REDCONCENTRATOR_SENTINELS"),
+            String::from("s1:26379, s2:26379"),
+        ),
+        (String::from("HOME"), String::from("/root")),
+    ]);
+    let flags = ConfigOverrides {
+        group_name: Some(String::from("flag")),
+        ..Default::default()
+    };
+
+    let mut config: Config =
+        serde_yaml2::from_str("bind: 127.0.0.1:6578\ngroup_name: file\nmaster: redis:6379\n")
+            .unwrap();
+
+    env.merge(flags).apply(&mut config);
+
+    assert_eq!(config.bind, "10.0.0.1:6578");
+    assert_eq!(config.group_name, "flag");
+    assert_eq!(
+        config.sentinels.unwrap().address,
+        vec!["s1:26379", "s2:26379"]
+    );
+}
@@ -1,28 +1,107 @@
 //! This module contains routine to watch clients.
 //!
+pub mod session;
+
 use crate::app::messages::MainLoopEvent;
 use crate::config::Config;
+use crate::metrics;
 use crate::redis::types::RedisError;
-use std::net::TcpListener;
+use crate::supervisor::bind_reuse_port;
+use std::io::{ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
 use std::sync::mpsc::Sender;
 use std::thread;
+use std::time::Duration;
 use log::{error, info, debug, warn};
 
+/// How often listener checks if it must stop.
+const ACCEPT_CHECK: Duration = Duration::from_millis(100);
+
+/// New clients are accepted. Cleared on shutdown.
+static ACCEPTING: AtomicBool = AtomicBool::new(true);
+
+/// Descriptor of listening socket (-1 before listening).
+static LISTENER_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// Stop accepting new clients, listener is closed.
+pub fn stop_accepting() {
+    ACCEPTING.store(false, Ordering::Relaxed);
+}
+
+/// Descriptor of listening socket, valid until stop_accepting().
+pub fn listener_fd() -> Option<RawFd> {
+    match LISTENER_FD.load(Ordering::Relaxed) {
+        -1 => None,
+        fd => Some(fd),
+    }
+}
+
+/// Wait a new connection until timeout.
+fn wait_connection(listener: &TcpListener, timeout: Duration) {
+    let mut fd = libc::pollfd {
+        fd: listener.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    // Safety: one valid pollfd, result is checked by next accept().
+    unsafe { libc::poll(&mut fd, 1, timeout.as_millis() as libc::c_int) };
+}
+
+/// Max number of connected clients (0 is unlimited). Can change when config is reloaded.
+static MAX_CLIENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Change max number of connected clients.
+pub fn set_max_clients(max_clients: u64) {
+    MAX_CLIENTS.store(max_clients, Ordering::Relaxed);
+}
+
 /// Wait new client connection.
 /// Create a new thread for do this.
+/// Listener inherited from a previous process is used instead of binding (upgrade).
 pub fn watch_new_client_connection(
     config: &Config,
     tx_new_client: Sender<MainLoopEvent>,
+    inherited: Option<TcpListener>,
 ) -> Result<(), RedisError> {
-    info!("Listen connection to {}", &config.bind);
+    let listener = match inherited {
+        Some(l) => {
+            info!("Listen connection inherited from previous process");
+            l
+        }
+        None => {
+            info!("Listen connection to {}", &config.bind);
+
+            // Processes share address, kernel spreads clients between them
+            let bound = if config.processes > 1 {
+                bind_reuse_port(&config.bind)
+            } else {
+                TcpListener::bind(&config.bind)
+            };
 
-    let listener = match TcpListener::bind(&config.bind) {
-        Ok(l) => l,
-        Err(e) => return Err(RedisError::from_io_error(e)),
+            match bound {
+                Ok(l) => l,
+                Err(e) => return Err(RedisError::from_io_error(e)),
+            }
+        }
     };
 
+    // Accept doesn't block, so listener can stop without new connection
+    if let Err(e) = listener.set_nonblocking(true) {
+        return Err(RedisError::from_io_error(e));
+    }
+
+    LISTENER_FD.store(listener.as_raw_fd(), Ordering::Relaxed);
+    set_max_clients(config.max_clients);
+
     thread::spawn(move || loop {
-        debug!("watch_new_client_connection(): Wait a new client");
+        if !ACCEPTING.load(Ordering::Relaxed) {
+            info!("Stop listening client connection");
+            LISTENER_FD.store(-1, Ordering::Relaxed);
+            return;
+        }
 
         match listener.accept() {
             Ok(d) => {
@@ -34,6 +113,23 @@ pub fn watch_new_client_connection(
                     client_addr.port()
                 );
 
+                metrics::TOTAL_CONNECTIONS.increment();
+
+                let max_clients = MAX_CLIENTS.load(Ordering::Relaxed);
+
+                // Slot is reserved now, main loop receives client later
+                if !metrics::CONNECTED_CLIENTS.increment_below(max_clients) {
+                    warn!(
+                        "Max number of clients reached, refuse client from {}:{}",
+                        client_addr.ip(),
+                        client_addr.port()
+                    );
+
+                    refuse_client(client_stream);
+
+                    continue;
+                }
+
                 // Set non blocking mode to incoming connection
                 if let Err(e) = client_stream.set_nonblocking(true) {
                     error!(
@@ -43,6 +139,8 @@ pub fn watch_new_client_connection(
                         e
                     );
 
+                    metrics::CONNECTED_CLIENTS.decrement();
+
                     continue;
                 }
 
@@ -57,6 +155,13 @@ pub fn watch_new_client_connection(
 
                 tx_new_client.send(MainLoopEvent::new_client(client_stream, client_addr)).unwrap();
             }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                debug!("watch_new_client_connection(): Wait a new client");
+
+                wait_connection(&listener, ACCEPT_CHECK);
+
+                continue;
+            }
             Err(e) => {
                 error!("Error when establish client connection {:?}.", e);
 
@@ -68,3 +173,11 @@ pub fn watch_new_client_connection(
     Ok(())
 }
 
+
+/// Send error to client and close connection.
+fn refuse_client(mut client_stream: TcpStream) {
+    metrics::REJECTED_CONNECTIONS.increment();
+
+    let _ = client_stream.write_all(b"-ERR max number of clients reached\r\n");
+    let _ = client_stream.shutdown(std::net::Shutdown::Both);
+}
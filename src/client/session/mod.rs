@@ -0,0 +1,892 @@
+//! This module contain protocol state of a client connection.
+//!
+//! Commands sent by client and replies sent by Redis are framed, so we know which reply
+//! answers which command. That allow to rewrite commands and replies on the fly.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::{BlockingFailoverPolicy, ConfigNamespace, RateLimitPolicy};
+use crate::metrics;
+use crate::ratelimit::RateLimiter;
+use crate::redis::command::{command_name, is_blocking, is_write};
+use crate::redis::encoder::{encode_command, encode_value};
+use crate::redis::namespace::{
+    add_prefix, remove_prefix, remove_prefix_from_message, ReplyRewrite,
+};
+use crate::redis::parser::{parse_command_from, parse_value, value_length, CommandCursor};
+use crate::redis::splitbrain::writes_refused;
+use crate::redis::types::{
+    RedisError, RedisValue, REDIS_TYPE_ARRAY, REDIS_TYPE_BLOB_ERROR, REDIS_TYPE_ERROR,
+    REDIS_TYPE_PUSH,
+};
+use log::warn;
+
+/// Kind of message pushed by Redis when client is in subscribe mode.
+const PUBSUB_MESSAGES: [&[u8]; 10] = [
+    b"message",
+    b"pmessage",
+    b"smessage",
+    b"subscribe",
+    b"psubscribe",
+    b"ssubscribe",
+    b"unsubscribe",
+    b"punsubscribe",
+    b"sunsubscribe",
+    b"pong",
+];
+
+/// Commands that switch client in subscribe mode or leave it.
+const PUBSUB_COMMANDS: [&str; 6] = [
+    "SUBSCRIBE",
+    "PSUBSCRIBE",
+    "SSUBSCRIBE",
+    "UNSUBSCRIBE",
+    "PUNSUBSCRIBE",
+    "SUNSUBSCRIBE",
+];
+
+/// Reply sent to client when rate limit is reached.
+const RATE_LIMITED_REPLY: &[u8] = b"-ERR rate limited\r\n";
+/// Reply sent to client when connection to Redis is replaced before reply.
+const REPLY_LOST: &[u8] = b"-ERR master changed, reply lost\r\n";
+/// Reply sent to client when write is refused because several nodes claim master.
+const SPLIT_BRAIN_REPLY: &[u8] = b"-ERR split brain detected\r\n";
+/// Reply to a command queued in transaction.
+const QUEUED_REPLY: &[u8] = b"+QUEUED\r\n";
+/// Max size of data read from client but not yet sent, like Redis client-query-buffer-limit.
+const MAX_QUERY_BUFFER: usize = 1024 * 1024 * 1024;
+
+/// A reply waited by client.
+#[derive(Debug)]
+enum PendingReply {
+    /// Reply is sent by Redis.
+    Redis {
+        /// How rewrite reply.
+        rewrite: ReplyRewrite,
+        /// If command change state of connection (AUTH, SELECT...), command to replay on a new
+        /// connection.
+        state: Option<Vec<Vec<u8>>>,
+        /// If command is blocking (BLPOP...), command to re-issue on a new connection.
+        blocking: Option<Vec<Vec<u8>>>,
+        /// Reply sent to client if connection to Redis is replaced before reply.
+        lost: &'static [u8],
+        /// Reply is EXEC of a transaction with pub/sub commands, it contains confirmations.
+        pubsub: bool,
+    },
+    /// Reply of a pub/sub command: Redis sends a confirmation per channel, like messages.
+    Subscribe {
+        /// Command, re-issued on a new connection.
+        args: Vec<Vec<u8>>,
+        /// Confirmations not received yet, None when command has no channel (unsubscribe from
+        /// all channels).
+        remaining: Option<usize>,
+    },
+    /// Reply is generated by RedConcentrator.
+    Local(Vec<u8>),
+    /// Reply of a command sent by RedConcentrator, not sent to client.
+    Discard,
+}
+
+/// Protocol state of a client.
+#[derive(Debug)]
+pub struct ClientSession {
+    /// Data read from client but not yet a complete command.
+    client_buffer: Vec<u8>,
+    /// Progress of parse of first command of client buffer.
+    client_cursor: CommandCursor,
+    /// Data read from Redis but not yet a complete reply.
+    redis_buffer: Vec<u8>,
+    /// Replies waited from Redis, in order of commands.
+    pending: VecDeque<PendingReply>,
+    /// Namespace configuration.
+    namespace: Option<ConfigNamespace>,
+    /// Prefix currently applied to keys.
+    prefix: Option<Vec<u8>>,
+    /// Client is in subscribe mode.
+    subscribed: bool,
+    /// IP of client.
+    client_ip: IpAddr,
+    /// Authenticated user.
+    user: Option<String>,
+    /// Rate limiter shared by all clients.
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Client is over limit, commands wait in buffer.
+    throttled: bool,
+    /// Last time client sent data.
+    last_activity: Instant,
+    /// Commands that change state of connection, by order of replay.
+    state_commands: BTreeMap<u8, Vec<Vec<u8>>>,
+    /// Subscribed channels (with prefix).
+    channels: BTreeSet<Vec<u8>>,
+    /// Subscribed patterns (with prefix).
+    patterns: BTreeSet<Vec<u8>>,
+    /// Subscribed shard channels (with prefix).
+    shard_channels: BTreeSet<Vec<u8>>,
+    /// Subscribe confirmations (kind, channel) of resubscribe, not sent to client.
+    discard_pushes: BTreeSet<(Vec<u8>, Vec<u8>)>,
+    /// What to do with blocking commands when connection to Redis is replaced.
+    blocking_failover: BlockingFailoverPolicy,
+    /// Client sent MULTI, commands are queued until EXEC or DISCARD.
+    in_multi: bool,
+    /// Client sent WATCH, keys are watched until EXEC, DISCARD or UNWATCH.
+    watching: bool,
+    /// Connection to Redis was replaced during transaction, transaction must fail.
+    transaction_aborted: bool,
+    /// Connection to Redis was replaced while keys were watched, next EXEC must fail.
+    watch_lost: bool,
+    /// A command of transaction was refused (split brain, namespace), EXEC must fail.
+    write_refused_in_multi: bool,
+    /// A pub/sub command is queued in transaction, EXEC reply contains confirmations.
+    pubsub_in_multi: bool,
+    /// Protocol version negotiated by client with HELLO (2 or 3).
+    protocol: u8,
+}
+
+impl ClientSession {
+    pub fn new(
+        namespace: Option<ConfigNamespace>,
+        client_ip: IpAddr,
+        rate_limiter: Option<Arc<RateLimiter>>,
+        blocking_failover: BlockingFailoverPolicy,
+    ) -> Self {
+        let prefix = namespace
+            .as_ref()
+            .and_then(|n| n.prefix.as_ref())
+            .map(|p| p.as_bytes().to_vec());
+
+        ClientSession {
+            client_buffer: Vec::new(),
+            client_cursor: CommandCursor::default(),
+            redis_buffer: Vec::new(),
+            pending: VecDeque::new(),
+            namespace,
+            prefix,
+            subscribed: false,
+            client_ip,
+            user: None,
+            rate_limiter,
+            throttled: false,
+            last_activity: Instant::now(),
+            state_commands: BTreeMap::new(),
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+            shard_channels: BTreeSet::new(),
+            discard_pushes: BTreeSet::new(),
+            blocking_failover,
+            in_multi: false,
+            watching: false,
+            transaction_aborted: false,
+            watch_lost: false,
+            write_refused_in_multi: false,
+            pubsub_in_multi: false,
+            protocol: 2,
+        }
+    }
+
+    /// Client sent nothing since timeout and doesn't wait reply.
+    /// Like Redis, client in subscribe mode is never idle.
+    pub fn is_idle(&self, timeout: Duration) -> bool {
+        !self.subscribed
+            && self.pending.is_empty()
+            && self.client_buffer.is_empty()
+            && self.last_activity.elapsed() > timeout
+    }
+
+    /// Session can read more data from client.
+    /// When client is over limit and policy is delay, data stay in socket.
+    pub fn wants_data(&self) -> bool {
+        !self.throttled
+    }
+
+    /// Check rate limit.
+    /// Return None if command can be sent, otherwise policy to apply.
+    fn check_rate_limit(&self, size: usize) -> Option<RateLimitPolicy> {
+        let limiter = self.rate_limiter.as_ref()?;
+
+        if limiter.allow(&self.client_ip, self.user.as_deref(), size) {
+            None
+        } else {
+            metrics::RATE_LIMITED_COMMANDS.increment();
+            Some(limiter.policy().clone())
+        }
+    }
+
+    /// Client is in subscribe mode.
+    pub fn is_subscribed(&self) -> bool {
+        self.subscribed
+    }
+
+    /// Client waits reply of a command.
+    pub fn has_pending_replies(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Client can continue on a new connection without replay: no command in flight, no
+    /// partial data, no state (authentication, database, subscriptions, transaction).
+    pub fn is_transferable(&self) -> bool {
+        self.pending.is_empty()
+            && self.client_buffer.is_empty()
+            && self.redis_buffer.is_empty()
+            && self.state_commands.is_empty()
+            && !self.subscribed
+            && !self.in_multi
+            && !self.watching
+            && !self.throttled
+    }
+
+    /// Client waits reply of a blocking command.
+    pub fn is_blocked(&self) -> bool {
+        self.pending.iter().any(|p| {
+            matches!(
+                p,
+                PendingReply::Redis {
+                    blocking: Some(_),
+                    ..
+                }
+            )
+        })
+    }
+
+    /// Read data from client.
+    /// Return data to send to Redis.
+    pub fn read_from_client(&mut self, data: &[u8]) -> Result<Vec<u8>, RedisError> {
+        if !data.is_empty() {
+            self.last_activity = Instant::now();
+        }
+
+        let mut buffer = std::mem::take(&mut self.client_buffer);
+        buffer.extend_from_slice(data);
+
+        let mut output = Vec::new();
+        let mut start = 0;
+
+        self.throttled = false;
+
+        while let Some((mut args, size)) =
+            parse_command_from(&buffer[start..], &mut self.client_cursor)?
+        {
+            let raw = &buffer[start..start + size];
+
+            // Empty inline command
+            if args.is_empty() {
+                start += size;
+                continue;
+            }
+
+            match self.check_rate_limit(size) {
+                Some(RateLimitPolicy::Delay) => {
+                    // Command stay in buffer until tokens are available
+                    self.throttled = true;
+                    break;
+                }
+                Some(RateLimitPolicy::Reject) => {
+                    start += size;
+                    self.pending
+                        .push_back(PendingReply::Local(RATE_LIMITED_REPLY.to_vec()));
+                    continue;
+                }
+                None => start += size,
+            }
+
+            let name = command_name(&args);
+
+            if writes_refused() && is_write(&args) {
+                metrics::SPLIT_BRAIN_REFUSED_WRITES.increment();
+                self.write_refused_in_multi |= self.in_multi;
+                self.pending
+                    .push_back(PendingReply::Local(SPLIT_BRAIN_REPLY.to_vec()));
+                continue;
+            }
+
+            let rewrite = match self.prefix.as_ref() {
+                Some(prefix) => match add_prefix(&mut args, prefix) {
+                    Ok(rewrite) => Some(rewrite),
+                    Err(e) => {
+                        // Like a command rejected by Redis, transaction will be discarded
+                        self.write_refused_in_multi |= self.in_multi;
+                        self.pending.push_back(PendingReply::Local(e.to_reply()));
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
+            if self.transaction_aborted {
+                if let Some(reply) = self.aborted_transaction_reply(&name) {
+                    self.pending.push_back(PendingReply::Local(reply));
+                    continue;
+                }
+            }
+
+            if self.watch_lost && self.in_multi && name == "EXEC" {
+                // Watched keys may have changed on new master, transaction is discarded
+                self.update_transaction(&name);
+                encode_command(&[b"DISCARD".to_vec()], &mut output);
+                self.pending.push_back(PendingReply::Discard);
+                self.pending
+                    .push_back(PendingReply::Local(exec_abort_reply()));
+                continue;
+            }
+
+            if self.write_refused_in_multi && name == "EXEC" {
+                // Like a command rejected by Redis, transaction is discarded
+                self.update_transaction(&name);
+                encode_command(&[b"DISCARD".to_vec()], &mut output);
+                self.pending.push_back(PendingReply::Discard);
+                self.pending.push_back(PendingReply::Local(
+                    RedisError::from_redis(
+                        "EXECABORT",
+                        "Transaction discarded because of previous errors.",
+                    )
+                    .to_reply(),
+                ));
+                continue;
+            }
+
+            let lost = lost_reply(&name, self.in_multi);
+            let pubsub = name == "EXEC" && self.pubsub_in_multi;
+
+            self.update_transaction(&name);
+
+            let rewrite = match rewrite {
+                Some(rewrite) => {
+                    encode_command(&args, &mut output);
+
+                    rewrite
+                }
+                None => {
+                    output.extend_from_slice(raw);
+
+                    ReplyRewrite::None
+                }
+            };
+
+            if PUBSUB_COMMANDS.contains(&name.as_str()) {
+                if self.in_multi {
+                    // Command is queued, confirmations are in EXEC reply
+                    self.pubsub_in_multi = true;
+                } else {
+                    if !name.contains("UNSUB") {
+                        self.subscribed = true;
+                    }
+
+                    let remaining = Some(args.len() - 1).filter(|n| *n > 0);
+
+                    self.pending
+                        .push_back(PendingReply::Subscribe { args, remaining });
+                    continue;
+                }
+            }
+
+            let state = if state_priority(&name, &args).is_some() || name == "RESET" {
+                Some(args.clone())
+            } else {
+                None
+            };
+            let blocking = if is_blocking(&args) { Some(args) } else { None };
+
+            self.pending.push_back(PendingReply::Redis {
+                rewrite,
+                state,
+                blocking,
+                lost,
+                pubsub,
+            });
+        }
+
+        buffer.drain(..start);
+
+        if buffer.len() > MAX_QUERY_BUFFER {
+            // Like Redis, client is disconnected
+            return Err(RedisError::from_message(
+                "Protocol error: client query buffer limit reached",
+            ));
+        }
+
+        self.client_buffer = buffer;
+
+        Ok(output)
+    }
+
+    /// Read data from Redis.
+    /// Return data to send to client.
+    pub fn read_from_redis(&mut self, data: &[u8]) -> Result<Vec<u8>, RedisError> {
+        let mut buffer = std::mem::take(&mut self.redis_buffer);
+        buffer.extend_from_slice(data);
+
+        let mut output = Vec::new();
+        let mut start = 0;
+
+        self.flush_local_replies(&mut output);
+
+        while let Some(size) = value_length(&buffer[start..])? {
+            let raw = &buffer[start..start + size];
+            start += size;
+
+            // RESP3 sends messages as push, RESP2 as array in subscribe mode or to confirm a
+            // pub/sub command
+            let is_push = raw[0] == REDIS_TYPE_PUSH;
+            let confirmation = matches!(self.pending.front(), Some(PendingReply::Subscribe { .. }));
+
+            if is_push
+                || (self.protocol == 2
+                    && (self.subscribed || confirmation)
+                    && raw[0] == REDIS_TYPE_ARRAY)
+            {
+                if let Some((value, _)) = parse_value(raw)? {
+                    if is_pubsub_message(&value) {
+                        if self.update_subscribe_mode(&value, true) {
+                            match self.prefix.as_ref() {
+                                Some(prefix) => encode_value(
+                                    &remove_prefix_from_message(value, prefix),
+                                    &mut output,
+                                ),
+                                None => output.extend_from_slice(raw),
+                            }
+                        }
+
+                        self.flush_local_replies(&mut output);
+                        continue;
+                    }
+                }
+
+                // Other push (e.g. client tracking invalidation) doesn't answer a command
+                if is_push {
+                    output.extend_from_slice(raw);
+                    continue;
+                }
+            }
+
+            let is_error = raw[0] == REDIS_TYPE_ERROR || raw[0] == REDIS_TYPE_BLOB_ERROR;
+
+            let (rewrite, state, pubsub) = match self.pending.pop_front() {
+                Some(PendingReply::Redis {
+                    rewrite,
+                    state,
+                    pubsub,
+                    ..
+                }) => (rewrite, state, pubsub),
+                Some(PendingReply::Subscribe { .. }) => {
+                    // Command failed, there is no confirmation
+                    self.subscribed = self.has_subscriptions();
+                    output.extend_from_slice(raw);
+                    self.flush_local_replies(&mut output);
+                    continue;
+                }
+                Some(PendingReply::Discard) => {
+                    if is_error {
+                        warn!(
+                            "Replay of client state failed: {}",
+                            String::from_utf8_lossy(raw).trim_end()
+                        );
+                    }
+
+                    self.flush_local_replies(&mut output);
+                    continue;
+                }
+                _ => {
+                    output.extend_from_slice(raw);
+                    continue;
+                }
+            };
+
+            if let Some(args) = state {
+                if !is_error {
+                    self.record_state(args);
+                }
+            }
+
+            if pubsub && !is_error {
+                if let Some((RedisValue::Array(replies), _)) = parse_value(raw)? {
+                    let replies = self.update_subscribe_mode_from_exec(replies);
+
+                    encode_value(&RedisValue::Array(replies), &mut output);
+                    self.flush_local_replies(&mut output);
+                    continue;
+                }
+            }
+
+            match (self.prefix.as_ref(), rewrite) {
+                (Some(prefix), rewrite) if rewrite != ReplyRewrite::None && !is_error => {
+                    match parse_value(raw)? {
+                        Some((value, _)) => {
+                            encode_value(&remove_prefix(value, &rewrite, prefix), &mut output)
+                        }
+                        None => output.extend_from_slice(raw),
+                    }
+                }
+                _ => output.extend_from_slice(raw),
+            }
+
+            self.flush_local_replies(&mut output);
+        }
+
+        buffer.drain(..start);
+        self.redis_buffer = buffer;
+
+        Ok(output)
+    }
+
+    /// Track MULTI and WATCH state of connection.
+    fn update_transaction(&mut self, name: &str) {
+        match name {
+            "MULTI" => self.in_multi = true,
+            "WATCH" if !self.in_multi => self.watching = true,
+            "UNWATCH" if !self.in_multi => {
+                self.watching = false;
+                self.watch_lost = false;
+            }
+            "EXEC" | "DISCARD" | "RESET" => {
+                self.in_multi = false;
+                self.watching = false;
+                self.watch_lost = false;
+                self.write_refused_in_multi = false;
+                self.pubsub_in_multi = false;
+            }
+            _ => {}
+        }
+    }
+
+    /// When transaction is aborted, reply locally to commands of transaction until EXEC or
+    /// DISCARD: commands are queued like Redis does, EXEC fails. Return None if command must be
+    /// sent to Redis.
+    fn aborted_transaction_reply(&mut self, name: &str) -> Option<Vec<u8>> {
+        match name {
+            "EXEC" | "DISCARD" | "RESET" => {
+                self.update_transaction(name);
+                self.transaction_aborted = false;
+
+                match name {
+                    "EXEC" => Some(exec_abort_reply()),
+                    "DISCARD" => Some(b"+OK\r\n".to_vec()),
+                    // New connection is not in transaction, RESET is sent
+                    _ => None,
+                }
+            }
+            "MULTI" => Some(b"-ERR MULTI calls can not be nested\r\n".to_vec()),
+            "WATCH" => Some(b"-ERR WATCH inside MULTI is not allowed\r\n".to_vec()),
+            _ => Some(QUEUED_REPLY.to_vec()),
+        }
+    }
+
+    /// Send replies generated by RedConcentrator that are next in order.
+    fn flush_local_replies(&mut self, output: &mut Vec<u8>) {
+        while let Some(PendingReply::Local(_)) = self.pending.front() {
+            if let Some(PendingReply::Local(data)) = self.pending.pop_front() {
+                output.extend_from_slice(&data);
+            }
+        }
+    }
+
+    /// Connection to Redis is replaced (e.g. master changed).
+    /// Replies not received are lost, client gets an error for each of them.
+    /// Blocking commands get a timeout reply or are re-issued, depending on policy.
+    /// Return commands to send on new connection to restore state of client (authentication,
+    /// database, subscriptions...).
+    pub fn reset_redis_connection(&mut self) -> Vec<u8> {
+        self.redis_buffer.clear();
+
+        let mut output = Vec::new();
+        let pending = std::mem::take(&mut self.pending);
+
+        // Replies of state commands come first
+        for args in self.state_commands.values() {
+            encode_command(args, &mut output);
+            self.pending.push_back(PendingReply::Discard);
+        }
+
+        // Transaction can't continue on another connection, keys are no more watched
+        self.transaction_aborted = self.in_multi;
+        self.watch_lost = self.watching && !self.in_multi;
+
+        if self.transaction_aborted || self.watch_lost {
+            metrics::ABORTED_TRANSACTIONS.increment();
+        }
+
+        let mut reissued = Vec::new();
+
+        for reply in pending {
+            match reply {
+                PendingReply::Redis {
+                    rewrite,
+                    state,
+                    blocking: Some(args),
+                    lost,
+                    pubsub,
+                } => match self.blocking_failover {
+                    BlockingFailoverPolicy::Nil => self.pending.push_back(PendingReply::Local(
+                        timeout_reply(&command_name(&args), self.protocol).to_vec(),
+                    )),
+                    BlockingFailoverPolicy::Retry => {
+                        encode_command(&args, &mut reissued);
+                        self.pending.push_back(PendingReply::Redis {
+                            rewrite,
+                            state,
+                            blocking: Some(args),
+                            lost,
+                            pubsub,
+                        });
+                    }
+                },
+                PendingReply::Redis { lost, .. } => {
+                    self.pending.push_back(PendingReply::Local(lost.to_vec()))
+                }
+                PendingReply::Subscribe { args, remaining } => {
+                    // Confirmations are sent in order of channels, only missing ones are asked
+                    let args = match remaining {
+                        Some(n) => {
+                            let mut command = vec![args[0].clone()];
+                            command.extend_from_slice(&args[args.len() - n..]);
+                            command
+                        }
+                        None => args,
+                    };
+
+                    encode_command(&args, &mut reissued);
+                    self.pending
+                        .push_back(PendingReply::Subscribe { args, remaining });
+                }
+                PendingReply::Local(data) => self.pending.push_back(PendingReply::Local(data)),
+                PendingReply::Discard => {}
+            }
+        }
+
+        self.discard_pushes.clear();
+
+        for (name, kind, channels) in [
+            ("SUBSCRIBE", b"subscribe".as_slice(), &self.channels),
+            ("PSUBSCRIBE", b"psubscribe".as_slice(), &self.patterns),
+            ("SSUBSCRIBE", b"ssubscribe".as_slice(), &self.shard_channels),
+        ] {
+            if channels.is_empty() {
+                continue;
+            }
+
+            let mut args = vec![name.as_bytes().to_vec()];
+            args.extend(channels.iter().cloned());
+
+            encode_command(&args, &mut output);
+            self.discard_pushes
+                .extend(channels.iter().map(|c| (kind.to_vec(), c.clone())));
+        }
+
+        output.extend_from_slice(&reissued);
+
+        output
+    }
+
+    /// Command that change state of connection succeed.
+    fn record_state(&mut self, args: Vec<Vec<u8>>) {
+        let name = command_name(&args);
+
+        if name == "RESET" {
+            // Connection is back to initial state
+            self.state_commands.clear();
+            self.channels.clear();
+            self.patterns.clear();
+            self.shard_channels.clear();
+            self.subscribed = false;
+            self.protocol = 2;
+            self.set_user("default");
+            return;
+        }
+
+        if let Some(user) = get_auth_user(&name, &args) {
+            self.set_user(&user);
+        }
+
+        if name == "HELLO" {
+            if let Some(version) = args.get(1) {
+                self.protocol = String::from_utf8_lossy(version).parse().unwrap_or(2);
+            }
+        }
+
+        if let Some(priority) = state_priority(&name, &args) {
+            self.state_commands.insert(priority, args);
+        }
+    }
+
+    /// Client is authenticated, update user and prefix.
+    fn set_user(&mut self, user: &str) {
+        self.user = Some(String::from(user));
+
+        if let Some(namespace) = self.namespace.as_ref() {
+            self.prefix = namespace
+                .users
+                .get(user)
+                .or(namespace.prefix.as_ref())
+                .map(|p| p.as_bytes().to_vec());
+        }
+    }
+
+    /// Update subscribed channels with confirmation sent by Redis.
+    /// If confirmation answers pub/sub command first in pending replies, command is complete
+    /// when a confirmation is received for each channel, or for an unsubscribe from all
+    /// channels, when no channel remains.
+    /// When all channels are unsubscribed, client leaves subscribe mode.
+    /// Return false if message must not be sent to client.
+    fn update_subscribe_mode(&mut self, value: &RedisValue, pending: bool) -> bool {
+        let data = match value {
+            RedisValue::Array(data) | RedisValue::Push(data) => data,
+            _ => return true,
+        };
+
+        let kind = match data.first() {
+            Some(RedisValue::BulkString(kind)) => kind,
+            _ => return true,
+        };
+
+        let channels = match kind.as_slice() {
+            b"subscribe" | b"unsubscribe" => &mut self.channels,
+            b"psubscribe" | b"punsubscribe" => &mut self.patterns,
+            b"ssubscribe" | b"sunsubscribe" => &mut self.shard_channels,
+            _ => return true,
+        };
+
+        // Channel is null when client unsubscribes without subscription
+        let channel = match data.get(1) {
+            Some(RedisValue::BulkString(channel)) => Some(channel),
+            _ => None,
+        };
+
+        let last = if kind.ends_with(b"unsubscribe") {
+            if let Some(channel) = channel {
+                channels.remove(channel);
+            }
+
+            let last = channel.is_none() || channels.is_empty();
+
+            self.subscribed = self.has_subscriptions();
+            last
+        } else {
+            let channel = match channel {
+                Some(channel) => channel,
+                None => return true,
+            };
+
+            channels.insert(channel.clone());
+            self.subscribed = true;
+
+            // Confirmation of resubscribe, client's own subscribe to same channel is still sent
+            if self.discard_pushes.remove(&(kind.clone(), channel.clone())) {
+                return false;
+            }
+
+            false
+        };
+
+        if pending {
+            if let Some(PendingReply::Subscribe { remaining, .. }) = self.pending.front_mut() {
+                let complete = match remaining {
+                    Some(n) => {
+                        *n -= 1;
+                        *n == 0
+                    }
+                    None => last,
+                };
+
+                if complete {
+                    self.pending.pop_front();
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Update subscribed channels with confirmations of pub/sub commands queued in
+    /// transaction. Return replies of EXEC to send to client.
+    fn update_subscribe_mode_from_exec(&mut self, replies: Vec<RedisValue>) -> Vec<RedisValue> {
+        replies
+            .into_iter()
+            .map(|reply| {
+                if !is_pubsub_message(&reply) {
+                    return reply;
+                }
+
+                self.update_subscribe_mode(&reply, false);
+
+                match self.prefix.as_ref() {
+                    Some(prefix) => remove_prefix_from_message(reply, prefix),
+                    None => reply,
+                }
+            })
+            .collect()
+    }
+
+    /// Client is subscribed to at least a channel, a pattern or a shard channel.
+    fn has_subscriptions(&self) -> bool {
+        !self.channels.is_empty() || !self.patterns.is_empty() || !self.shard_channels.is_empty()
+    }
+}
+
+/// Return user of AUTH or HELLO command.
+/// `AUTH password` authenticate the default user.
+fn get_auth_user(name: &str, args: &[Vec<u8>]) -> Option<String> {
+    match name {
+        "AUTH" if args.len() == 2 => Some(String::from("default")),
+        "AUTH" if args.len() == 3 => Some(String::from_utf8_lossy(&args[1]).to_string()),
+        "HELLO" => args
+            .iter()
+            .position(|a| a.eq_ignore_ascii_case(b"AUTH"))
+            .and_then(|p| args.get(p + 1))
+            .map(|u| String::from_utf8_lossy(u).to_string()),
+        _ => None,
+    }
+}
+
+/// If command change state of connection, return order to replay it.
+/// Authentication is replayed first.
+fn state_priority(name: &str, args: &[Vec<u8>]) -> Option<u8> {
+    match name {
+        "AUTH" => Some(0),
+        "HELLO" => Some(1),
+        "SELECT" => Some(2),
+        "CLIENT" if args.len() == 3 && args[1].eq_ignore_ascii_case(b"SETNAME") => Some(3),
+        _ => None,
+    }
+}
+
+/// Reply to commands of a transaction aborted by a change of connection.
+fn exec_abort_reply() -> Vec<u8> {
+    RedisError::from_redis("EXECABORT", "Transaction discarded because master changed.").to_reply()
+}
+
+/// Reply of a command when connection to Redis is replaced before reply.
+/// In transaction, commands are queued like Redis does and transaction fails at EXEC.
+fn lost_reply(name: &str, in_multi: bool) -> &'static [u8] {
+    match name {
+        "MULTI" if !in_multi => b"+OK\r\n",
+        "MULTI" | "WATCH" | "EXEC" | "DISCARD" | "RESET" => REPLY_LOST,
+        _ if in_multi => QUEUED_REPLY,
+        _ => REPLY_LOST,
+    }
+}
+
+/// Reply of a blocking command when timeout is reached.
+fn timeout_reply(name: &str, protocol: u8) -> &'static [u8] {
+    match name {
+        "WAIT" => b":0\r\n",
+        "WAITAOF" => b"*2\r\n:0\r\n:0\r\n",
+        _ if protocol >= 3 => b"_\r\n",
+        "BLMOVE" | "BRPOPLPUSH" => b"$-1\r\n",
+        _ => b"*-1\r\n",
+    }
+}
+
+/// Check if value is a message pushed by Redis in subscribe mode.
+fn is_pubsub_message(value: &RedisValue) -> bool {
+    match value {
+        RedisValue::Array(data) | RedisValue::Push(data) => match data.first() {
+            Some(RedisValue::BulkString(kind)) => PUBSUB_MESSAGES.contains(&kind.as_slice()),
+            _ => false,
+        },
+        _ => false,
+    }
+}
@@ -0,0 +1,613 @@
+use crate::client::session::ClientSession;
+use crate::config::{
+    BlockingFailoverPolicy, ConfigNamespace, ConfigRateLimit, ConfigRateLimitValue, RateLimitPolicy,
+};
+use crate::ratelimit::RateLimiter;
+use crate::redis::types::RedisError;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+fn localhost() -> IpAddr {
+    "127.0.0.1".parse().unwrap()
+}
+
+fn rate_limiter(policy: RateLimitPolicy) -> Option<Arc<RateLimiter>> {
+    Some(Arc::new(RateLimiter::new(ConfigRateLimit {
+        policy,
+        global: None,
+        per_ip: Some(ConfigRateLimitValue {
+            ops: Some(1),
+            bytes: None,
+        }),
+        per_user: None,
+        ips: BTreeMap::new(),
+        users: BTreeMap::new(),
+    })))
+}
+
+fn namespace() -> Option<ConfigNamespace> {
+    let mut users = BTreeMap::new();
+    users.insert(String::from("billing"), String::from("billing:"));
+
+    Some(ConfigNamespace {
+        prefix: Some(String::from("app:")),
+        users,
+    })
+}
+
+#[test]
+fn session_without_namespace_copy_data() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    assert_eq!(
+        session.read_from_client(b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n")?,
+        b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n"
+    );
+    assert_eq!(session.read_from_redis(b"$1\r\n1\r\n")?, b"$1\r\n1\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn session_wait_complete_command() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    assert_eq!(session.read_from_client(b"*2\r\n$3\r\nGET\r\n$1")?, b"");
+    assert_eq!(
+        session.read_from_client(b"\r\na\r\n")?,
+        b"*2\r\n$3\r\nGET\r\n$5\r\napp:a\r\n"
+    );
+    assert_eq!(session.read_from_redis(b"$1\r")?, b"");
+    assert_eq!(session.read_from_redis(b"\n1\r\n")?, b"$1\r\n1\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn session_inline_command() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    assert_eq!(
+        session.read_from_client(b"GET a\r\n")?,
+        b"*2\r\n$3\r\nGET\r\n$5\r\napp:a\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_remove_prefix_from_keys() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*1\r\n$3\r\nDEL\r\n*2\r\n$4\r\nKEYS\r\n$1\r\n*\r\n")?;
+
+    assert_eq!(
+        session.read_from_redis(b":0\r\n*2\r\n$5\r\napp:a\r\n$5\r\napp:b\r\n")?,
+        b":0\r\n*2\r\n$1\r\na\r\n$1\r\nb\r\n"
+    );
+
+    // Timeout of blocking pop keeps null array
+    session.read_from_client(b"*3\r\n$5\r\nBLPOP\r\n$1\r\na\r\n$1\r\n1\r\n")?;
+    assert_eq!(session.read_from_redis(b"*-1\r\n")?, b"*-1\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn session_prefix_of_authenticated_user() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"AUTH billing secret\r\n")?;
+    session.read_from_redis(b"+OK\r\n")?;
+
+    assert_eq!(
+        session.read_from_client(b"GET a\r\n")?,
+        b"*2\r\n$3\r\nGET\r\n$9\r\nbilling:a\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_failed_authentication_keep_prefix() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"AUTH billing bad\r\n")?;
+    session.read_from_redis(b"-WRONGPASS invalid password\r\n")?;
+
+    assert_eq!(
+        session.read_from_client(b"GET a\r\n")?,
+        b"*2\r\n$3\r\nGET\r\n$5\r\napp:a\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_namespace_refuse_command() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    assert_eq!(
+        session.read_from_client(b"MULTI\r\nFLUSHALL\r\nEXEC\r\n")?,
+        b"*1\r\n$5\r\nMULTI\r\n*1\r\n$7\r\nDISCARD\r\n"
+    );
+    assert_eq!(
+        session.read_from_redis(b"+OK\r\n+OK\r\n")?,
+        b"+OK\r\n-ERR command 'FLUSHALL' is not allowed in namespace\r\n\
+        -EXECABORT Transaction discarded because of previous errors.\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_subscribe_mode() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"SUBSCRIBE news\r\n")?;
+    assert!(session.is_subscribed());
+
+    assert_eq!(
+        session.read_from_redis(b"*3\r\n$9\r\nsubscribe\r\n$8\r\napp:news\r\n:1\r\n")?,
+        b"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n"
+    );
+    assert_eq!(
+        session.read_from_redis(b"*3\r\n$7\r\nmessage\r\n$8\r\napp:news\r\n$2\r\nhi\r\n")?,
+        b"*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n"
+    );
+
+    session.read_from_client(b"UNSUBSCRIBE\r\n")?;
+    session.read_from_redis(b"*3\r\n$11\r\nunsubscribe\r\n$8\r\napp:news\r\n:0\r\n")?;
+    assert!(!session.is_subscribed());
+
+    Ok(())
+}
+
+#[test]
+fn session_unsubscribe_without_subscription() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"UNSUBSCRIBE\r\n*2\r\n$4\r\nKEYS\r\n$1\r\n*\r\n")?;
+
+    // Single confirmation with null channel
+    assert_eq!(
+        session.read_from_redis(b"*3\r\n$11\r\nunsubscribe\r\n$-1\r\n:0\r\n")?,
+        b"*3\r\n$11\r\nunsubscribe\r\n$-1\r\n:0\r\n"
+    );
+    assert!(!session.is_subscribed());
+    assert!(session.has_pending_replies());
+
+    // Reply of next command is still rewritten
+    assert_eq!(
+        session.read_from_redis(b"*1\r\n$5\r\napp:a\r\n")?,
+        b"*1\r\n$1\r\na\r\n"
+    );
+    assert!(!session.has_pending_replies());
+
+    Ok(())
+}
+
+#[test]
+fn session_subscribe_in_transaction() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"MULTI\r\nSUBSCRIBE news\r\nEXEC\r\n")?;
+    assert!(!session.is_subscribed());
+
+    assert_eq!(
+        session.read_from_redis(
+            b"+OK\r\n+QUEUED\r\n*1\r\n*3\r\n$9\r\nsubscribe\r\n$8\r\napp:news\r\n:1\r\n"
+        )?,
+        b"+OK\r\n+QUEUED\r\n*1\r\n*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n"
+    );
+    assert!(session.is_subscribed());
+    assert!(!session.has_pending_replies());
+
+    Ok(())
+}
+
+#[test]
+fn session_subscribe_confirmations_complete_command() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"SUBSCRIBE a a\r\n")?;
+    session.read_from_redis(b"*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n:1\r\n")?;
+    assert!(session.has_pending_replies());
+
+    // One confirmation per channel, even when count doesn't change
+    session.read_from_redis(b"*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n:1\r\n")?;
+    assert!(!session.has_pending_replies());
+
+    // Unsubscribe from all channels ends with last channel
+    session.read_from_client(b"UNSUBSCRIBE\r\nSSUBSCRIBE b\r\n")?;
+    session.read_from_redis(b"*3\r\n$11\r\nunsubscribe\r\n$1\r\na\r\n:0\r\n")?;
+    assert!(session.has_pending_replies());
+
+    // Command fails, client is not subscribed
+    session.read_from_redis(b"-ERR This command is not allowed\r\n")?;
+    assert!(!session.is_subscribed());
+    assert!(!session.has_pending_replies());
+
+    Ok(())
+}
+
+#[test]
+fn session_rate_limit_reject() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(
+        None,
+        localhost(),
+        rate_limiter(RateLimitPolicy::Reject),
+        BlockingFailoverPolicy::Nil,
+    );
+
+    assert_eq!(
+        session.read_from_client(b"GET a\r\nGET b\r\n")?,
+        b"GET a\r\n"
+    );
+    assert_eq!(session.read_from_redis(b"")?, b"");
+    assert_eq!(
+        session.read_from_redis(b"$1\r\n1\r\n")?,
+        b"$1\r\n1\r\n-ERR rate limited\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_rate_limit_delay() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(
+        None,
+        localhost(),
+        rate_limiter(RateLimitPolicy::Delay),
+        BlockingFailoverPolicy::Nil,
+    );
+
+    assert_eq!(
+        session.read_from_client(b"GET a\r\nGET b\r\n")?,
+        b"GET a\r\n"
+    );
+    assert!(!session.wants_data());
+
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+
+    assert_eq!(session.read_from_client(b"")?, b"GET b\r\n");
+    assert!(session.wants_data());
+
+    Ok(())
+}
+
+#[test]
+fn session_reset_replay_state() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session
+        .read_from_client(b"*2\r\n$6\r\nSELECT\r\n$1\r\n2\r\n*2\r\n$4\r\nAUTH\r\n$1\r\np\r\n")?;
+    session.read_from_redis(b"+OK\r\n+OK\r\n")?;
+    session.read_from_client(b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n")?;
+
+    // AUTH is replayed before SELECT
+    assert_eq!(
+        session.reset_redis_connection(),
+        b"*2\r\n$4\r\nAUTH\r\n$1\r\np\r\n*2\r\n$6\r\nSELECT\r\n$1\r\n2\r\n"
+    );
+    // Reply of GET is lost, replies of replay are not sent to client
+    assert_eq!(
+        session.read_from_redis(b"+OK\r\n+OK\r\n")?,
+        b"-ERR master changed, reply lost\r\n"
+    );
+    assert_eq!(session.read_from_redis(b"$1\r\n1\r\n")?, b"$1\r\n1\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn session_transferable_without_state() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    assert!(session.is_transferable());
+
+    session.read_from_client(b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n")?;
+    assert!(!session.is_transferable());
+
+    session.read_from_redis(b"$1\r\n1\r\n")?;
+    assert!(session.is_transferable());
+
+    // Database must be selected again on new connection
+    session.read_from_client(b"*2\r\n$6\r\nSELECT\r\n$1\r\n2\r\n")?;
+    session.read_from_redis(b"+OK\r\n")?;
+    assert!(!session.is_transferable());
+
+    Ok(())
+}
+
+#[test]
+fn session_reset_resubscribe() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*2\r\n$9\r\nSUBSCRIBE\r\n$1\r\nc\r\n")?;
+    session.read_from_redis(b"*3\r\n$9\r\nsubscribe\r\n$5\r\napp:c\r\n:1\r\n")?;
+    session.read_from_client(b"*2\r\n$10\r\nPSUBSCRIBE\r\n$2\r\np*\r\n")?;
+    session.read_from_redis(b"*3\r\n$10\r\npsubscribe\r\n$6\r\napp:p*\r\n:2\r\n")?;
+
+    assert_eq!(
+        session.reset_redis_connection(),
+        b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\napp:c\r\n*2\r\n$10\r\nPSUBSCRIBE\r\n$6\r\napp:p*\r\n"
+    );
+    assert!(session.is_subscribed());
+    // Confirmations of resubscribe are not sent to client
+    assert_eq!(
+        session.read_from_redis(
+            b"*3\r\n$9\r\nsubscribe\r\n$5\r\napp:c\r\n:1\r\n*3\r\n$10\r\npsubscribe\r\n$6\r\napp:p*\r\n:2\r\n"
+        )?,
+        b""
+    );
+    assert_eq!(
+        session.read_from_redis(b"*3\r\n$7\r\nmessage\r\n$5\r\napp:c\r\n$1\r\nm\r\n")?,
+        b"*3\r\n$7\r\nmessage\r\n$1\r\nc\r\n$1\r\nm\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_reset_send_own_subscribe_confirmation() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*2\r\n$9\r\nSUBSCRIBE\r\n$1\r\nc\r\n")?;
+    session.read_from_redis(b"*3\r\n$9\r\nsubscribe\r\n$5\r\napp:c\r\n:1\r\n")?;
+    session.reset_redis_connection();
+
+    // Client subscribes to another channel and to same channel than resubscribe
+    session.read_from_client(b"*3\r\n$9\r\nSUBSCRIBE\r\n$1\r\nd\r\n$1\r\nc\r\n")?;
+    assert_eq!(
+        session.read_from_redis(b"*3\r\n$9\r\nsubscribe\r\n$5\r\napp:d\r\n:2\r\n")?,
+        b"*3\r\n$9\r\nsubscribe\r\n$1\r\nd\r\n:2\r\n"
+    );
+    assert_eq!(
+        session.read_from_redis(
+            b"*3\r\n$9\r\nsubscribe\r\n$5\r\napp:c\r\n:1\r\n*3\r\n$9\r\nsubscribe\r\n$5\r\napp:c\r\n:2\r\n"
+        )?,
+        b"*3\r\n$9\r\nsubscribe\r\n$1\r\nc\r\n:2\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_reset_reissue_missing_confirmations() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*3\r\n$9\r\nSUBSCRIBE\r\n$1\r\nc\r\n$1\r\nd\r\n")?;
+    session.read_from_redis(b"*3\r\n$9\r\nsubscribe\r\n$1\r\nc\r\n:1\r\n")?;
+
+    // Only channel not confirmed is asked again after resubscribe
+    assert_eq!(
+        session.reset_redis_connection(),
+        b"*2\r\n$9\r\nSUBSCRIBE\r\n$1\r\nc\r\n*2\r\n$9\r\nSUBSCRIBE\r\n$1\r\nd\r\n"
+    );
+    assert_eq!(
+        session.read_from_redis(
+            b"*3\r\n$9\r\nsubscribe\r\n$1\r\nc\r\n:1\r\n*3\r\n$9\r\nsubscribe\r\n$1\r\nd\r\n:2\r\n"
+        )?,
+        b"*3\r\n$9\r\nsubscribe\r\n$1\r\nd\r\n:2\r\n"
+    );
+    assert!(!session.has_pending_replies());
+
+    Ok(())
+}
+
+#[test]
+fn session_blocking_command_reply_nil_after_reset() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*3\r\n$5\r\nBLPOP\r\n$1\r\na\r\n$1\r\n0\r\n")?;
+    assert!(session.is_blocked());
+
+    assert_eq!(session.reset_redis_connection(), b"");
+    assert!(!session.is_blocked());
+    assert_eq!(session.read_from_redis(b"")?, b"*-1\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn session_blocking_command_retry_after_reset() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(
+        namespace(),
+        localhost(),
+        None,
+        BlockingFailoverPolicy::Retry,
+    );
+
+    session.read_from_client(b"*3\r\n$5\r\nBLPOP\r\n$1\r\na\r\n$1\r\n0\r\n")?;
+
+    assert_eq!(
+        session.reset_redis_connection(),
+        b"*3\r\n$5\r\nBLPOP\r\n$5\r\napp:a\r\n$1\r\n0\r\n"
+    );
+    assert!(session.is_blocked());
+    assert_eq!(
+        session.read_from_redis(b"*2\r\n$5\r\napp:a\r\n$1\r\n1\r\n")?,
+        b"*2\r\n$1\r\na\r\n$1\r\n1\r\n"
+    );
+    assert!(!session.is_blocked());
+
+    Ok(())
+}
+
+#[test]
+fn session_transaction_aborted_after_reset() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*1\r\n$5\r\nMULTI\r\n*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n")?;
+    session.read_from_redis(b"+OK\r\n+QUEUED\r\n")?;
+
+    assert_eq!(session.reset_redis_connection(), b"");
+
+    // Commands of transaction are not sent to new master
+    assert_eq!(
+        session
+            .read_from_client(b"*3\r\n$3\r\nSET\r\n$1\r\nb\r\n$1\r\n2\r\n*1\r\n$4\r\nEXEC\r\n")?,
+        b""
+    );
+    // Commands are queued, only EXEC fails
+    assert_eq!(
+        session.read_from_redis(b"")?,
+        b"+QUEUED\r\n-EXECABORT Transaction discarded because master changed.\r\n"
+    );
+
+    // Transaction is ended
+    assert_eq!(
+        session.read_from_client(b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n")?,
+        b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_transaction_aborted_reply_queued() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*1\r\n$5\r\nMULTI\r\n*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n")?;
+    session.read_from_redis(b"+OK\r\n")?;
+
+    // Reply of SET is lost with connection
+    assert_eq!(session.reset_redis_connection(), b"");
+    assert_eq!(session.read_from_redis(b"")?, b"+QUEUED\r\n");
+
+    assert_eq!(
+        session.read_from_client(
+            b"*1\r\n$5\r\nMULTI\r\n*2\r\n$5\r\nWATCH\r\n$1\r\na\r\n*1\r\n$7\r\nDISCARD\r\n"
+        )?,
+        b""
+    );
+    assert_eq!(
+        session.read_from_redis(b"")?,
+        b"-ERR MULTI calls can not be nested\r\n\
+        -ERR WATCH inside MULTI is not allowed\r\n+OK\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_watch_aborted_after_reset() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*2\r\n$5\r\nWATCH\r\n$1\r\na\r\n")?;
+    session.read_from_redis(b"+OK\r\n")?;
+
+    session.reset_redis_connection();
+
+    // Transaction is sent to new master, only EXEC fails
+    assert_eq!(
+        session.read_from_client(b"*1\r\n$5\r\nMULTI\r\n*2\r\n$4\r\nINCR\r\n$1\r\na\r\n")?,
+        b"*1\r\n$5\r\nMULTI\r\n*2\r\n$4\r\nINCR\r\n$1\r\na\r\n"
+    );
+    assert_eq!(
+        session.read_from_redis(b"+OK\r\n+QUEUED\r\n")?,
+        b"+OK\r\n+QUEUED\r\n"
+    );
+    assert_eq!(
+        session.read_from_client(b"*1\r\n$4\r\nEXEC\r\n")?,
+        b"*1\r\n$7\r\nDISCARD\r\n"
+    );
+    assert_eq!(
+        session.read_from_redis(b"+OK\r\n")?,
+        b"-EXECABORT Transaction discarded because master changed.\r\n"
+    );
+
+    // Next transaction runs
+    assert_eq!(
+        session.read_from_client(b"*1\r\n$5\r\nMULTI\r\n*1\r\n$4\r\nEXEC\r\n")?,
+        b"*1\r\n$5\r\nMULTI\r\n*1\r\n$4\r\nEXEC\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_watch_aborted_cleared_by_unwatch() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*2\r\n$5\r\nWATCH\r\n$1\r\na\r\n")?;
+    session.read_from_redis(b"+OK\r\n")?;
+
+    session.reset_redis_connection();
+
+    assert_eq!(
+        session.read_from_client(b"*1\r\n$7\r\nUNWATCH\r\n*2\r\n$3\r\nGET\r\n$1\r\na\r\n")?,
+        b"*1\r\n$7\r\nUNWATCH\r\n*2\r\n$3\r\nGET\r\n$1\r\na\r\n"
+    );
+    assert_eq!(
+        session.read_from_client(b"*1\r\n$5\r\nMULTI\r\n*1\r\n$4\r\nEXEC\r\n")?,
+        b"*1\r\n$5\r\nMULTI\r\n*1\r\n$4\r\nEXEC\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_multi_in_flight_after_reset() -> Result<(), RedisError> {
+    let mut session = ClientSession::new(None, localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(
+        b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n*1\r\n$5\r\nMULTI\r\n*2\r\n$4\r\nINCR\r\n$1\r\na\r\n",
+    )?;
+
+    // Command before transaction is lost, MULTI succeeded, command is queued
+    session.reset_redis_connection();
+    assert_eq!(
+        session.read_from_redis(b"")?,
+        b"-ERR master changed, reply lost\r\n+OK\r\n+QUEUED\r\n"
+    );
+
+    assert_eq!(session.read_from_client(b"*1\r\n$4\r\nEXEC\r\n")?, b"");
+    assert_eq!(
+        session.read_from_redis(b"")?,
+        b"-EXECABORT Transaction discarded because master changed.\r\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn session_resp3_push_messages() -> Result<(), RedisError> {
+    let mut session =
+        ClientSession::new(namespace(), localhost(), None, BlockingFailoverPolicy::Nil);
+
+    session.read_from_client(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n")?;
+    assert_eq!(
+        session.read_from_redis(b"%1\r\n$5\r\nproto\r\n:3\r\n")?,
+        b"%1\r\n$5\r\nproto\r\n:3\r\n"
+    );
+
+    session.read_from_client(b"*2\r\n$9\r\nSUBSCRIBE\r\n$1\r\nc\r\n")?;
+    assert_eq!(
+        session.read_from_redis(b">3\r\n$9\r\nsubscribe\r\n$5\r\napp:c\r\n:1\r\n")?,
+        b">3\r\n$9\r\nsubscribe\r\n$1\r\nc\r\n:1\r\n"
+    );
+    assert!(session.is_subscribed());
+
+    // With RESP3, client can send any command in subscribe mode
+    session.read_from_client(b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n")?;
+    assert_eq!(
+        session.read_from_redis(b">3\r\n$7\r\nmessage\r\n$5\r\napp:c\r\n$1\r\nm\r\n_\r\n")?,
+        b">3\r\n$7\r\nmessage\r\n$1\r\nc\r\n$1\r\nm\r\n_\r\n"
+    );
+
+    Ok(())
+}
@@ -1,5 +1,13 @@
+//! This module contains config of RedConcentrator.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{Error, ErrorKind, Read};
+use std::net::SocketAddr;
+use std::path::Path;
 use serde::{Serialize, Deserialize};
 
 /// Config structure of RedConcentrator
@@ -9,25 +17,102 @@ pub struct Config {
     pub group_name: String,
     #[serde(default)]
     pub sentinels: Option<Sentinels>,
+    /// Static master "host:port", used without sentinels.
+    #[serde(default)]
+    pub master: Option<String>,
+    /// Static list of master and replicas "host:port", used without sentinels.
+    #[serde(default)]
+    pub nodes: Option<Vec<String>>,
+    /// Health check frequency (ms) of static master.
+    #[serde(default = "default_master_check_freqency")]
+    pub master_check_freqency: u64,
     #[serde(default = "ConfigLog::default")]
     pub log: ConfigLog,
     #[serde(default = "ConfigTimeout::default")]
     pub timeout: ConfigTimeout,
     #[serde(default = "ConfigWorker::default")]
-    pub workers: ConfigWorker
+    pub workers: ConfigWorker,
+    #[serde(default)]
+    pub namespace: Option<ConfigNamespace>,
+    #[serde(default)]
+    pub rate_limit: Option<ConfigRateLimit>,
+    /// Max number of connected clients (0 is unlimited).
+    #[serde(default)]
+    pub max_clients: u64,
+    /// Close client after this number of seconds without traffic (0 is never).
+    #[serde(default)]
+    pub client_idle_timeout: u64,
+    #[serde(default)]
+    pub admin: Option<ConfigAdmin>,
+    /// What to do with blocking commands (BLPOP...) when master changes.
+    #[serde(default = "BlockingFailoverPolicy::default")]
+    pub blocking_failover: BlockingFailoverPolicy,
+    /// Check that only one node of group claims master.
+    #[serde(default)]
+    pub split_brain: Option<ConfigSplitBrain>,
+    /// Hand listening socket to a new process (zero-downtime restart).
+    #[serde(default)]
+    pub upgrade: Option<ConfigUpgrade>,
+    /// Number of processes binding `bind` with SO_REUSEPORT, supervised by a parent process.
+    #[serde(default = "default_processes")]
+    pub processes: u8
+}
+
+/// Admin interface.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ConfigAdmin {
+    pub bind: String
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Sentinels {
     pub address: Vec<String>,
     #[serde(default = "default_sentinel_check_freqency_default")]
-    pub check_freqency: u64
+    pub check_freqency: u64,
+    /// Number of sentinels that must report same master address (0 trusts first sentinel).
+    #[serde(default)]
+    pub quorum: usize,
+    /// Time (ms) after which a discovered sentinel not seen is forgotten.
+    #[serde(default = "default_sentinel_discovery_expire")]
+    pub discovery_expire: u64,
+    /// Patterns of sentinel events to follow (PSUBSCRIBE).
+    #[serde(default = "default_sentinel_events")]
+    pub events: Vec<String>
+}
+
+impl Sentinels {
+    /// Sentinels with default values.
+    pub fn new(address: Vec<String>) -> Self {
+        Self {
+            address,
+            check_freqency: default_sentinel_check_freqency_default(),
+            quorum: 0,
+            discovery_expire: default_sentinel_discovery_expire(),
+            events: default_sentinel_events()
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct ConfigLog {
-    #[serde(default = "default_file")]
-    pub file: String,
+    /// log4rs config file. When set, fields below (except logo) are ignored.
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default = "LogType::default", rename = "type")]
+    pub log_type: LogType,
+    #[serde(default = "LogLevel::default")]
+    pub level: LogLevel,
+    /// Level by module (e.g. "red_concentrator::workers").
+    #[serde(default)]
+    pub modules: BTreeMap<String, LogLevel>,
+    /// File of log type file.
+    #[serde(default = "default_log_path")]
+    pub path: String,
+    #[serde(default = "ConfigLogRotation::default")]
+    pub rotation: ConfigLogRotation,
+    /// Facility of log type syslog.
+    #[serde(default = "SyslogFacility::default")]
+    pub facility: SyslogFacility,
     #[serde(default = "default_logo")]
     pub logo: bool,
 }
@@ -35,25 +120,130 @@ pub struct ConfigLog {
 impl ConfigLog {
     pub fn default() -> Self {
         Self {
-            file: String::from("log4rs.yml"),
+            file: None,
+            log_type: LogType::default(),
+            level: LogLevel::default(),
+            modules: BTreeMap::new(),
+            path: default_log_path(),
+            rotation: ConfigLogRotation::default(),
+            facility: SyslogFacility::default(),
             logo: true,
         }
     }
 }
 
+/// Where logs are written.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum LogType {
+    Console,
+    File,
+    Syslog
+}
+
+impl LogType {
+    pub fn default() -> Self {
+        LogType::Console
+    }
+}
+
+/// Level of logs.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    #[serde(alias = "critical")]
+    Error,
+    #[serde(alias = "warning")]
+    Warn,
+    Info,
+    Debug,
+    Trace
+}
+
+impl LogLevel {
+    pub fn default() -> Self {
+        LogLevel::Info
+    }
+}
+
+/// Rotation of log file, when size or age is reached.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ConfigLogRotation {
+    /// Max size of file in bytes (0 is unlimited).
+    #[serde(default)]
+    pub size: u64,
+    /// Max age of file in seconds (0 is unlimited).
+    #[serde(default)]
+    pub age: u64,
+    /// Number of rotated files kept (0 deletes them).
+    #[serde(default = "default_log_rotation_count")]
+    pub count: u32
+}
+
+impl ConfigLogRotation {
+    pub fn default() -> Self {
+        Self {
+            size: 0,
+            age: 0,
+            count: default_log_rotation_count()
+        }
+    }
+}
+
+/// Syslog facility.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogFacility {
+    Kern,
+    User,
+    Mail,
+    Daemon,
+    Auth,
+    Syslog,
+    Lpr,
+    News,
+    Uucp,
+    Cron,
+    Authpriv,
+    Ftp,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7
+}
+
+impl SyslogFacility {
+    pub fn default() -> Self {
+        SyslogFacility::User
+    }
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct ConfigTimeout {
     #[serde(default = "default_timeout")]
     pub sentinels: u64,
     #[serde(default = "default_timeout")]
-    pub worker_idle_timeout: u64    
+    pub worker_idle_timeout: u64,
+    /// Max time (ms) to wait new master reports role master before switching to it.
+    #[serde(default = "default_timeout")]
+    pub master_role_check: u64,
+    /// Max time (ms) to wait in-flight commands on shutdown.
+    #[serde(default = "default_timeout")]
+    pub shutdown_drain: u64
 }
 
 impl ConfigTimeout {
     pub fn default() -> Self {
         Self {
             sentinels: default_timeout(),
-            worker_idle_timeout: default_timeout()
+            worker_idle_timeout: default_timeout(),
+            master_role_check: default_timeout(),
+            shutdown_drain: default_timeout()
         }
     }
 }
@@ -89,9 +279,128 @@ impl ConfigWorkerPool {
     }
 }
 
+/// Prefix added to keys of clients.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ConfigNamespace {
+    /// Prefix of all clients connected to listener.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Prefix by authenticated user (override listener prefix).
+    #[serde(default)]
+    pub users: BTreeMap<String, String>
+}
+
+/// Rate limit of clients.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ConfigRateLimit {
+    /// What to do when client is over limit.
+    #[serde(default = "RateLimitPolicy::default")]
+    pub policy: RateLimitPolicy,
+    /// Limit for all clients.
+    #[serde(default)]
+    pub global: Option<ConfigRateLimitValue>,
+    /// Limit for each client IP.
+    #[serde(default)]
+    pub per_ip: Option<ConfigRateLimitValue>,
+    /// Limit for each authenticated user.
+    #[serde(default)]
+    pub per_user: Option<ConfigRateLimitValue>,
+    /// Limit of a specific IP (override per_ip).
+    #[serde(default)]
+    pub ips: BTreeMap<String, ConfigRateLimitValue>,
+    /// Limit of a specific user (override per_user).
+    #[serde(default)]
+    pub users: BTreeMap<String, ConfigRateLimitValue>
+}
+
+/// Limit value.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ConfigRateLimitValue {
+    /// Operations per second.
+    #[serde(default)]
+    pub ops: Option<u64>,
+    /// Bytes per second.
+    #[serde(default)]
+    pub bytes: Option<u64>
+}
+
+/// What to do when client is over limit.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitPolicy {
+    /// Wait to send command to Redis (backpressure).
+    Delay,
+    /// Reply '-ERR rate limited'.
+    Reject
+}
+
+impl RateLimitPolicy {
+    pub fn default() -> Self {
+        RateLimitPolicy::Delay
+    }
+}
+
+/// What to do with blocking commands when master changes.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockingFailoverPolicy {
+    /// Reply like timeout is reached (nil).
+    Nil,
+    /// Send command again to new master.
+    Retry
+}
+
+impl BlockingFailoverPolicy {
+    pub fn default() -> Self {
+        BlockingFailoverPolicy::Nil
+    }
+}
+
+/// Split brain detection.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ConfigSplitBrain {
+    /// Check frequency (ms) of role of all nodes.
+    #[serde(default = "default_split_brain_check_freqency")]
+    pub check_freqency: u64,
+    /// What to do when several nodes claim master.
+    #[serde(default = "SplitBrainPolicy::default")]
+    pub policy: SplitBrainPolicy
+}
+
+/// Zero-downtime restart.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ConfigUpgrade {
+    /// Unix socket where running process waits a new process.
+    pub socket: String,
+    /// Send idle clients to new process too.
+    #[serde(default)]
+    pub clients: bool
+}
+
+/// What to do when several nodes claim master.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitBrainPolicy {
+    /// Only log and count.
+    Alert,
+    /// Reply '-ERR split brain detected' to write commands until resolved.
+    RefuseWrites
+}
+
+impl SplitBrainPolicy {
+    pub fn default() -> Self {
+        SplitBrainPolicy::Alert
+    }
+}
+
 // Call by serde to have default value.
-fn default_file() -> String {
-    String::from("log4rs.yml")
+fn default_log_path() -> String {
+    String::from("red-concentrator.log")
+}
+
+// Default value
+fn default_log_rotation_count() -> u32 {
+    5
 }
 
 // Call by serde to have default value.
@@ -104,6 +413,31 @@ fn default_sentinel_check_freqency_default() -> u64 {
     1000
 }
 
+// Default value
+fn default_sentinel_discovery_expire() -> u64 {
+    60000
+}
+
+// Default value
+fn default_sentinel_events() -> Vec<String> {
+    vec![String::from("*")]
+}
+
+// Default value
+fn default_master_check_freqency() -> u64 {
+    1000
+}
+
+// Default value
+fn default_split_brain_check_freqency() -> u64 {
+    10000
+}
+
+// Default value
+fn default_processes() -> u8 {
+    1
+}
+
 // Default value
 fn default_timeout() -> u64 {
     5000
@@ -118,6 +452,139 @@ fn default_pool_size_max() -> u8 {
     10
 }
 
+/// Check that address is "host:port".
+fn check_address(field: &str, address: &str, errors: &mut Vec<String>) {
+    let valid = match address.rsplit_once(':') {
+        Some((host, port)) => !host.is_empty() && port.parse::<u16>().is_ok(),
+        None => false,
+    };
+
+    if !valid {
+        errors.push(format!("{}: '{}' is not a valid address (host:port)", field, address));
+    }
+}
+
+/// Check that value is not zero.
+fn check_not_zero(field: &str, value: u64, errors: &mut Vec<String>) {
+    if value == 0 {
+        errors.push(format!("{}: must be greater than 0", field));
+    }
+}
+
+///
+/// Return all semantic errors of config (empty if config is valid).
+///
+pub fn validate_config(config: &Config) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if config.bind.parse::<SocketAddr>().is_err() {
+        errors.push(format!("bind: '{}' is not a valid socket address (ip:port)", config.bind));
+    }
+
+    if let Some(admin) = config.admin.as_ref() {
+        match admin.bind.parse::<SocketAddr>() {
+            // Admin interface of process N listens on port + N
+            Ok(addr) if addr.port().checked_add(u16::from(config.processes.saturating_sub(1))).is_none() => {
+                errors.push(format!(
+                    "admin.bind: port {} is too high for {} processes",
+                    addr.port(), config.processes
+                ));
+            }
+            Ok(_) => {}
+            Err(_) => errors.push(format!("admin.bind: '{}' is not a valid socket address (ip:port)", admin.bind))
+        }
+    }
+
+    if config.group_name.is_empty() {
+        errors.push(String::from("group_name: must not be empty"));
+    }
+
+    match (config.sentinels.as_ref(), config.master.as_ref(), config.nodes.as_ref()) {
+        (Some(sentinels), _, _) => {
+            if sentinels.address.is_empty() {
+                errors.push(String::from("sentinels.address: must not be empty"));
+            }
+
+            for address in &sentinels.address {
+                check_address("sentinels.address", address, &mut errors);
+            }
+
+            if sentinels.quorum > sentinels.address.len() {
+                errors.push(format!(
+                    "sentinels.quorum: {} is greater than number of sentinels ({})",
+                    sentinels.quorum, sentinels.address.len()
+                ));
+            }
+
+            check_not_zero("sentinels.check_freqency", sentinels.check_freqency, &mut errors);
+        }
+        (None, None, None) => errors.push(String::from("sentinels, master or nodes must be set")),
+        (None, master, nodes) => {
+            if let Some(master) = master {
+                check_address("master", master, &mut errors);
+            }
+
+            if let Some(nodes) = nodes {
+                if nodes.is_empty() {
+                    errors.push(String::from("nodes: must not be empty"));
+                }
+
+                for address in nodes {
+                    check_address("nodes", address, &mut errors);
+                }
+            }
+
+            check_not_zero("master_check_freqency", config.master_check_freqency, &mut errors);
+        }
+    }
+
+    match config.log.file.as_ref() {
+        Some(file) if !Path::new(file).exists() => {
+            errors.push(format!("log.file: '{}' doesn't exist", file));
+        }
+        Some(_) => {}
+        None if config.log.log_type == LogType::File && config.log.path.is_empty() => {
+            errors.push(String::from("log.path: must not be empty"));
+        }
+        None => {}
+    }
+
+    check_not_zero("timeout.sentinels", config.timeout.sentinels, &mut errors);
+    check_not_zero("timeout.worker_idle_timeout", config.timeout.worker_idle_timeout, &mut errors);
+    check_not_zero("timeout.master_role_check", config.timeout.master_role_check, &mut errors);
+    check_not_zero("timeout.shutdown_drain", config.timeout.shutdown_drain, &mut errors);
+
+    let pool = &config.workers.pool;
+
+    if pool.min == 0 {
+        errors.push(String::from("workers.pool.min: must be greater than 0"));
+    }
+
+    if pool.min > pool.max {
+        errors.push(format!("workers.pool: min ({}) is greater than max ({})", pool.min, pool.max));
+    }
+
+    if let Some(split_brain) = config.split_brain.as_ref() {
+        check_not_zero("split_brain.check_freqency", split_brain.check_freqency, &mut errors);
+    }
+
+    if let Some(upgrade) = config.upgrade.as_ref() {
+        if upgrade.socket.is_empty() {
+            errors.push(String::from("upgrade.socket: must not be empty"));
+        }
+
+        if config.processes > 1 {
+            errors.push(String::from("upgrade: can't be used with processes greater than 1"));
+        }
+    }
+
+    if config.processes == 0 {
+        errors.push(String::from("processes: must be greater than 0"));
+    }
+
+    errors
+}
+
 ///
 /// Return config structure.
 ///
@@ -0,0 +1,41 @@
+use crate::config::{validate_config, Config};
+
+fn config(yaml: &str) -> Config {
+    serde_yaml2::from_str(yaml).unwrap()
+}
+
+#[test]
+fn validate_valid_config() {
+    let config = config(
+        "bind: 127.0.0.1:6578\ngroup_name: mymaster\nlog:\n  file: Cargo.toml\nsentinels:\n  address:\n    - 127.0.0.1:26379\n",
+    );
+
+    assert_eq!(validate_config(&config), Vec::<String>::new());
+}
+
+#[test]
+fn validate_lists_all_errors() {
+    let config = config(
+        "bind: localhost\ngroup_name: mymaster\nlog:\n  file: not-found.yml\nmaster: redis\ntimeout:\n  sentinels: 0\nworkers:\n  pool:\n    min: 10\n    max: 5\n",
+    );
+
+    let errors = validate_config(&config);
+
+    assert_eq!(errors.len(), 5);
+    assert!(errors[0].starts_with("bind:"));
+    assert!(errors[1].starts_with("master:"));
+    assert!(errors[2].starts_with("log.file:"));
+    assert!(errors[3].starts_with("timeout.sentinels:"));
+    assert!(errors[4].starts_with("workers.pool:"));
+}
+
+#[test]
+fn validate_admin_port_of_each_process() {
+    let yaml = "bind: 127.0.0.1:6578\ngroup_name: g\nmaster: redis:6379\nadmin:\n  bind: 127.0.0.1:65534\n";
+
+    assert_eq!(validate_config(&config(&format!("{}processes: 2\n", yaml))), Vec::<String>::new());
+
+    let errors = validate_config(&config(&format!("{}processes: 3\n", yaml)));
+
+    assert_eq!(errors, vec![String::from("admin.bind: port 65534 is too high for 3 processes")]);
+}
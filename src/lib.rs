@@ -0,0 +1,23 @@
+//! RedConcentrator connects applications to Redis master without knowing Redis infrastructure
+//! (Sentinel, failover).
+//!
+//! Binary is the proxy. Library gives its modules, e.g. `redis::RedisConnector` as a small
+//! blocking Redis client.
+//!
+// Config types have `default()` used by serde, and Pipeline has `add()`: they are not std traits.
+#![allow(clippy::should_implement_trait)]
+
+pub mod admin;
+pub mod app;
+pub mod cli;
+pub mod client;
+pub mod config;
+pub mod logging;
+pub mod metrics;
+pub mod ratelimit;
+pub mod redis;
+pub mod reload;
+pub mod signals;
+pub mod supervisor;
+pub mod upgrade;
+pub mod workers;
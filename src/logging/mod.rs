@@ -1,91 +1,267 @@
-//! This module contain log's routine.
+//! This module contains logger.
 //!
-extern crate slog_scope;
-extern crate slog_stdlog;
-extern crate slog_syslog;
-extern crate slog_term;
-
-use crate::config::Config;
-use slog::{Drain, Fuse, Level};
-use slog_syslog::Facility;
-use std::fs::OpenOptions;
-
-/// Create logger.
-pub fn build_log(config: &Config) -> slog::Logger {
-    match create_log(
-        config.log.log_type.as_str(),
-        config.log.level.as_str().to_lowercase().as_str(),
-        config.log.file.as_ref(),
-    ) {
-        Some(l) => l,
-        None => {
-            eprintln!("Error: cannot create log!");
-            std::process::exit(-1);
-        }
+//! Logger is built from `log` section of config: console, file with rotation by size and age, or
+//! syslog, with a level by module. When `log.file` is set, a log4rs file is read instead. Logger
+//! can be changed without restart.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::config::{ConfigLog, LogLevel, LogType, SyslogFacility};
+use log::{Level, LevelFilter, Record};
+use log4rs::append::console::ConsoleAppender;
+use log4rs::append::file::FileAppender;
+use log4rs::append::rolling_file::policy::compound::roll::delete::DeleteRoller;
+use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
+use log4rs::append::rolling_file::policy::compound::roll::Roll;
+use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
+use log4rs::append::rolling_file::policy::compound::trigger::time::{
+    TimeTrigger, TimeTriggerConfig, TimeTriggerInterval,
+};
+use log4rs::append::rolling_file::policy::compound::trigger::Trigger;
+use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
+use log4rs::append::rolling_file::{LogFile, RollingFileAppender};
+use log4rs::append::Append;
+use log4rs::config::{Appender, Logger, Root};
+use log4rs::encode::pattern::PatternEncoder;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Handle of logger, to change its config without restart.
+static LOG_HANDLE: OnceLock<log4rs::Handle> = OnceLock::new();
+
+/// Pattern of console logs.
+const CONSOLE_PATTERN: &str = "{d(%Y-%m-%d %H:%M:%S)(utc)} | {h({l}):5.5} | {m}{n}";
+/// Pattern of file logs.
+const FILE_PATTERN: &str = "{d(%Y-%m-%d %H:%M:%S)(utc)} | {l:5.5} | {M} | {m}{n}";
+/// Local syslog socket.
+const SYSLOG_SOCKET: &str = "/dev/log";
+/// Name of program in syslog messages.
+const SYSLOG_TAG: &str = "red-concentrator";
+/// Name of appender of root logger.
+const APPENDER: &str = "main";
+
+/// Convert level of config.
+fn level_filter(level: &LogLevel) -> LevelFilter {
+    match level {
+        LogLevel::Off => LevelFilter::Off,
+        LogLevel::Error => LevelFilter::Error,
+        LogLevel::Warn => LevelFilter::Warn,
+        LogLevel::Info => LevelFilter::Info,
+        LogLevel::Debug => LevelFilter::Debug,
+        LogLevel::Trace => LevelFilter::Trace,
     }
 }
 
-/// Create log.
-fn create_log(log_type: &str, log_level: &str, log_file: Option<&String>) -> Option<slog::Logger> {
-    // Get log level
-    let log_level = match log_level {
-        "critical" => Level::Critical,
-        "error" => Level::Error,
-        "warning" => Level::Warning,
-        "info" => Level::Info,
-        "debug" => Level::Debug,
-        "trace" => Level::Trace,
-        e => {
-            println!("Debug level '{}' not supported!", e);
-            return None;
-        }
+/// Code of syslog facility (RFC 3164).
+fn facility_code(facility: &SyslogFacility) -> u8 {
+    match facility {
+        SyslogFacility::Kern => 0,
+        SyslogFacility::User => 1,
+        SyslogFacility::Mail => 2,
+        SyslogFacility::Daemon => 3,
+        SyslogFacility::Auth => 4,
+        SyslogFacility::Syslog => 5,
+        SyslogFacility::Lpr => 6,
+        SyslogFacility::News => 7,
+        SyslogFacility::Uucp => 8,
+        SyslogFacility::Cron => 9,
+        SyslogFacility::Authpriv => 10,
+        SyslogFacility::Ftp => 11,
+        SyslogFacility::Local0 => 16,
+        SyslogFacility::Local1 => 17,
+        SyslogFacility::Local2 => 18,
+        SyslogFacility::Local3 => 19,
+        SyslogFacility::Local4 => 20,
+        SyslogFacility::Local5 => 21,
+        SyslogFacility::Local6 => 22,
+        SyslogFacility::Local7 => 23,
+    }
+}
+
+/// Return syslog message "<priority>tag[pid]: message". Timestamp and host are added by syslog.
+pub fn syslog_message(facility: &SyslogFacility, level: Level, pid: u32, message: &str) -> String {
+    let severity = match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
     };
 
-    match log_type {
-        "console" => Some(create_console_log(log_level)),
-        "file" => match log_file {
-            Some(s) => Some(create_file_log(s.to_string(), log_level)),
-            None => None,
-        },
-        "syslog" => Some(create_syslog_log(log_level)),
-        e => {
-            println!("Log type '{}' not supported!", e);
-            return None;
+    format!(
+        "<{}>{}[{}]: {}",
+        facility_code(facility) as u32 * 8 + severity,
+        SYSLOG_TAG,
+        pid,
+        message
+    )
+}
+
+/// Send logs to local syslog.
+#[derive(Debug)]
+struct SyslogAppender {
+    socket: UnixDatagram,
+    facility: SyslogFacility,
+}
+
+impl Append for SyslogAppender {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        let message = syslog_message(
+            &self.facility,
+            record.level(),
+            std::process::id(),
+            &record.args().to_string(),
+        );
+
+        // Not connected, so logs continue after syslog restarts
+        self.socket.send_to(message.as_bytes(), SYSLOG_SOCKET)?;
+
+        Ok(())
+    }
+
+    fn flush(&self) {}
+}
+
+/// Roll file when one of triggers fires (size or age).
+#[derive(Debug)]
+struct AnyTrigger {
+    triggers: Vec<Box<dyn Trigger>>,
+}
+
+impl Trigger for AnyTrigger {
+    fn trigger(&self, file: &LogFile) -> anyhow::Result<bool> {
+        let mut roll = false;
+
+        // All triggers are checked, to keep their state
+        for trigger in &self.triggers {
+            roll |= trigger.trigger(file)?;
         }
+
+        Ok(roll)
+    }
+
+    fn is_pre_process(&self) -> bool {
+        self.triggers.iter().any(|t| t.is_pre_process())
     }
 }
 
-fn create_file_log(filename: String, log_level: Level) -> slog::Logger {
-    let file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(filename)
-        .unwrap();
+/// Create appender of log type file.
+fn create_file_appender(log: &ConfigLog) -> Result<Box<dyn Append>, String> {
+    let encoder = Box::new(PatternEncoder::new(FILE_PATTERN));
+    let rotation = &log.rotation;
 
-    // create logger
-    let decorator = slog_term::PlainSyncDecorator::new(file);
-    let drain = slog_term::FullFormat::new(decorator).build().fuse();
-    let drain = slog::LevelFilter::new(drain, log_level).map(Fuse);
+    if rotation.size == 0 && rotation.age == 0 {
+        return FileAppender::builder()
+            .encoder(encoder)
+            .build(&log.path)
+            .map(|a| Box::new(a) as Box<dyn Append>)
+            .map_err(|e| e.to_string());
+    }
+
+    let mut triggers: Vec<Box<dyn Trigger>> = Vec::new();
+
+    if rotation.size > 0 {
+        triggers.push(Box::new(SizeTrigger::new(rotation.size)));
+    }
 
-    slog::Logger::root(drain, o!())
+    if rotation.age > 0 {
+        triggers.push(Box::new(TimeTrigger::new(TimeTriggerConfig {
+            interval: TimeTriggerInterval::Second(rotation.age as i64),
+            modulate: false,
+            max_random_delay: 0,
+        })));
+    }
+
+    let roller: Box<dyn Roll> = if rotation.count == 0 {
+        Box::new(DeleteRoller::new())
+    } else {
+        // Rotated files are "path.0" (newest) to "path.count-1"
+        Box::new(
+            FixedWindowRoller::builder()
+                .build(&format!("{}.{{}}", log.path), rotation.count)
+                .map_err(|e| e.to_string())?,
+        )
+    };
+
+    RollingFileAppender::builder()
+        .encoder(encoder)
+        .build(
+            &log.path,
+            Box::new(CompoundPolicy::new(
+                Box::new(AnyTrigger { triggers }),
+                roller,
+            )),
+        )
+        .map(|a| Box::new(a) as Box<dyn Append>)
+        .map_err(|e| e.to_string())
 }
 
-fn create_console_log(log_level: Level) -> slog::Logger {
-    let decorator = slog_term::TermDecorator::new().build();
-    let drain = slog_term::CompactFormat::new(decorator).build().fuse();
-    let drain = slog_async::Async::new(drain).build().fuse();
-    let drain = slog::LevelFilter::new(drain, log_level).map(Fuse);
+/// Create appender of log type syslog.
+fn create_syslog_appender(log: &ConfigLog) -> Result<Box<dyn Append>, String> {
+    if !Path::new(SYSLOG_SOCKET).exists() {
+        return Err(format!("Syslog socket {} doesn't exist", SYSLOG_SOCKET));
+    }
+
+    let socket = UnixDatagram::unbound().map_err(|e| e.to_string())?;
 
-    slog::Logger::root(drain, o!())
+    Ok(Box::new(SyslogAppender {
+        socket,
+        facility: log.facility.clone(),
+    }))
 }
 
-fn create_syslog_log(log_level: Level) -> slog::Logger {
-    // TODO allow change facility
-    let syslog = slog_syslog::unix_3164(Facility::LOG_USER).unwrap();
-    let drain = slog::LevelFilter::new(syslog.fuse(), log_level).map(Fuse);
-    let drain = slog::Logger::root(drain, o!());
-    //root.new(o!())
+/// Build log4rs config from `log` section, or read log4rs file if set.
+pub fn build_log_config(log: &ConfigLog) -> Result<log4rs::Config, String> {
+    if let Some(file) = log.file.as_ref() {
+        return log4rs::config::load_config_file(file, Default::default())
+            .map_err(|e| e.to_string());
+    }
+
+    let appender: Box<dyn Append> = match log.log_type {
+        LogType::Console => Box::new(
+            ConsoleAppender::builder()
+                .encoder(Box::new(PatternEncoder::new(CONSOLE_PATTERN)))
+                .build(),
+        ),
+        LogType::File => create_file_appender(log)?,
+        LogType::Syslog => create_syslog_appender(log)?,
+    };
 
-    slog::Logger::root(drain, o!())
+    let mut builder =
+        log4rs::Config::builder().appender(Appender::builder().build(APPENDER, appender));
+
+    for (module, level) in &log.modules {
+        builder = builder.logger(Logger::builder().build(module, level_filter(level)));
+    }
+
+    builder
+        .build(
+            Root::builder()
+                .appender(APPENDER)
+                .build(level_filter(&log.level)),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// Init logger. Logger can be reloaded later.
+pub fn init_log(log: &ConfigLog) -> Result<(), String> {
+    let handle = log4rs::init_config(build_log_config(log)?).map_err(|e| e.to_string())?;
+
+    let _ = LOG_HANDLE.set(handle);
+
+    Ok(())
+}
+
+/// Build logger again (e.g. to change level).
+pub fn reload_log(log: &ConfigLog) -> Result<(), String> {
+    let config = build_log_config(log)?;
+
+    match LOG_HANDLE.get() {
+        Some(handle) => {
+            handle.set_config(config);
+            Ok(())
+        }
+        None => Err(String::from("Logger is not initialized")),
+    }
 }
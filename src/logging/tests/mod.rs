@@ -0,0 +1,56 @@
+use crate::config::{ConfigLog, LogLevel, LogType, SyslogFacility};
+use crate::logging::{build_log_config, syslog_message};
+use log::{Level, LevelFilter};
+
+fn log_config(yaml: &str) -> ConfigLog {
+    serde_yaml2::from_str(yaml).unwrap()
+}
+
+#[test]
+fn build_console_with_module_levels() {
+    let log = log_config(
+        "level: warning\nmodules:\n  red_concentrator::workers: debug\n  red_concentrator::client: off\n",
+    );
+
+    assert_eq!(log.log_type, LogType::Console);
+    assert_eq!(log.level, LogLevel::Warn);
+
+    let config = build_log_config(&log).unwrap();
+
+    assert_eq!(config.root().level(), LevelFilter::Warn);
+    assert_eq!(config.loggers().len(), 2);
+    assert_eq!(config.loggers()[1].name(), "red_concentrator::workers");
+    assert_eq!(config.loggers()[1].level(), LevelFilter::Debug);
+}
+
+#[test]
+fn build_file_with_rotation() {
+    let path = std::env::temp_dir().join(format!("red-concentrator-{}.log", std::process::id()));
+    let log = log_config(&format!(
+        "type: file\npath: {}\nrotation:\n  size: 1048576\n  age: 86400\n  count: 3\n",
+        path.display()
+    ));
+
+    assert!(build_log_config(&log).is_ok());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn build_from_missing_log4rs_file() {
+    let log = log_config("file: not-found.yml\n");
+
+    assert!(build_log_config(&log).is_err());
+}
+
+#[test]
+fn syslog_priority() {
+    assert_eq!(
+        syslog_message(&SyslogFacility::Local0, Level::Warn, 42, "hello"),
+        "<132>red-concentrator[42]: hello"
+    );
+    assert_eq!(
+        syslog_message(&SyslogFacility::User, Level::Error, 1, "e"),
+        "<11>red-concentrator[1]: e"
+    );
+}
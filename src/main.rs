@@ -1,24 +1,23 @@
-extern crate log;
-extern crate serde_json;
-
-mod app;
-mod client;
-mod config;
-mod redis;
-mod workers;
-
 use std::env;
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::time;
 
-use app::messages::MainLoopEvent;
 use log::{error, info, debug};
-use workers::create_workers_pool;
+use red_concentrator::app::messages::MainLoopEvent;
+use red_concentrator::workers::create_workers_pool;
+use red_concentrator::{admin, app};
 
-use crate::client::watch_new_client_connection;
-use crate::config::{get_config, Config};
-use crate::redis::sentinel::watch_sentinel;
+use red_concentrator::cli::{parse_args, parse_env, usage, CliMode, ConfigSource};
+use red_concentrator::client::watch_new_client_connection;
+use red_concentrator::config::{validate_config, Config};
+use red_concentrator::redis::sentinel::watch_sentinel;
+use red_concentrator::redis::splitbrain::watch_split_brain;
+use red_concentrator::logging::init_log;
+use red_concentrator::signals::watch_signals;
+use red_concentrator::redis::standalone::watch_master;
+use red_concentrator::supervisor::{child_config, child_index, stop_with_parent, supervise};
+use red_concentrator::upgrade::{inherit_listener, resume_clients, watch_upgrade};
 
 const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
 
@@ -29,9 +28,37 @@ struct InitSentinelData {
 }
 
 fn help() {
-    println!();
-    println!("Usage: rsl config-file");
-    println!();
+    println!("{}", usage());
+}
+
+/// Validate config and exit: 0 if valid, 1 otherwise.
+fn check_config(config: &Config) -> ! {
+    let errors = validate_config(config);
+
+    if errors.is_empty() {
+        println!("Config file is valid");
+        std::process::exit(0);
+    }
+
+    for e in &errors {
+        eprintln!("Error: {}", e);
+    }
+
+    std::process::exit(1);
+}
+
+/// Print config with default values and exit.
+fn print_config(config: &Config) -> ! {
+    match serde_yaml2::to_string(config) {
+        Ok(yaml) => {
+            print!("{}", yaml);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("Error: can't print config: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
 
 fn print_logo() {
@@ -49,16 +76,51 @@ fn run_watch(
     config: &Config,
     tx_main_loop_message: Sender<MainLoopEvent>,
     rx_main_loop_message: Receiver<MainLoopEvent>,
-    redis_master_address: String) -> Result<(), String> {
+    redis_master_address: String,
+    config_source: ConfigSource) -> Result<(), String> {
     debug!("Receive first master change notification. Start all thread of RedConcentrator");
 
-    if let Err(e) = watch_new_client_connection(&config, tx_main_loop_message.clone()) {
+    // Running process (if any) hands its listening socket before we listen
+    let inherited = match config.upgrade.as_ref() {
+        Some(upgrade) => match inherit_listener(&upgrade.socket) {
+            Ok(inherited) => inherited,
+            Err(e) => return Err(format!("Error from upgrade: {:?}", e))
+        },
+        None => None
+    };
+
+    let (listener, upgrade_stream) = match inherited {
+        Some((listener, stream)) => (Some(listener), Some(stream)),
+        None => (None, None)
+    };
+
+    if let Err(e) = watch_new_client_connection(&config, tx_main_loop_message.clone(), listener) {
         return Err(format!("Error from listen client: {:?}", e));
     }
 
+    if let Some(stream) = upgrade_stream {
+        resume_clients(stream, tx_main_loop_message.clone());
+    }
+
+    if let Some(upgrade) = config.upgrade.as_ref() {
+        if let Err(e) = watch_upgrade(&upgrade.socket, tx_main_loop_message.clone()) {
+            return Err(format!("Error from watch upgrade: {:?}", e));
+        }
+    }
+
+    if let Err(e) = admin::watch_admin_connection(config, tx_main_loop_message.clone()) {
+        return Err(format!("Error from listen admin: {:?}", e));
+    }
+
     create_workers_pool(config.workers.pool.min, &tx_main_loop_message);
 
-    if let Err(e) = app::run_main_loop(rx_main_loop_message, redis_master_address) {
+    watch_split_brain(config);
+
+    if let Err(e) = watch_signals(tx_main_loop_message.clone()) {
+        return Err(format!("Error from watch signals: {:?}", e));
+    }
+
+    if let Err(e) = app::run_main_loop(rx_main_loop_message, tx_main_loop_message, redis_master_address, config.clone(), config_source) {
         return Err(format!("Error run main loop: {:?}", e));
     }
 
@@ -83,7 +145,7 @@ fn run_watch_sentinel(
 
     info!("Wait to get master address");
 
-    let timeout = time::Duration::from_millis(config.timeout.sentinels.clone());
+    let timeout = time::Duration::from_millis(config.timeout.sentinels);
 
     // Wait master addr.
     let redis_master_address = match rx_main_loop_message.recv_timeout(timeout) {
@@ -111,6 +173,30 @@ fn run_watch_sentinel(
     })
 }
 
+/// Run watch of static master.
+fn run_watch_standalone(
+    config: &Config,
+) -> Result<InitSentinelData, String> {
+    // Channel to main loop
+    let (tx_main_loop_message, rx_main_loop_message): (
+        Sender<MainLoopEvent>,
+        Receiver<MainLoopEvent>,
+    ) = mpsc::channel();
+
+    info!("No sentinels, use static master");
+
+    let redis_master_address = match watch_master(config, tx_main_loop_message.clone()) {
+        Ok(addr) => addr,
+        Err(e) => return Err(format!("Cannot resolve master: {:?}", e))
+    };
+
+    Ok(InitSentinelData {
+        tx_main_loop_message,
+        rx_main_loop_message,
+        redis_master_address
+    })
+}
+
 fn fatal_error(e: String) {
     error!("{}", e);
     eprintln!("{}", e);
@@ -119,21 +205,44 @@ fn fatal_error(e: String) {
 
 // TODO return error value
 fn main() {
-    println!("RedConcentrator {}", VERSION.unwrap_or("unknown"));
-
     // Get command line options
-    let args: Vec<String> = env::args().collect();
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            help();
 
-    if args.len() != 2 {
-        help();
+            std::process::exit(-1);
+        }
+    };
 
-        std::process::exit(-1);
+    match options.mode {
+        CliMode::Help => {
+            help();
+            return;
+        }
+        CliMode::Version => {
+            println!("RedConcentrator {}", VERSION.unwrap_or("unknown"));
+            return;
+        }
+        _ => {}
     }
 
-    let config_file = args[1].clone();
+    // Flags override environment variables
+    let overrides = parse_env(env::vars()).merge(options.overrides);
+
+    let config_source = match overrides.config_file.clone() {
+        Some(file) => ConfigSource { file, overrides },
+        None => {
+            eprintln!("Error: no config file");
+            help();
+
+            std::process::exit(-1);
+        }
+    };
 
     // We load config file.
-    let config = match get_config(config_file) {
+    let config = match config_source.load() {
         Ok(c) => c,
         Err(e) => {
             eprintln!("Error: can't read config file: {}", e);
@@ -141,29 +250,77 @@ fn main() {
         }
     };
 
-    if let Err(e) = log4rs::init_file(config.log.file.clone(), Default::default()) {
-        eprintln!("Log file error ({}): {}", config.log.file.clone(), e);
+    match options.mode {
+        CliMode::CheckConfig => check_config(&config),
+        CliMode::PrintConfig => print_config(&config),
+        _ => {}
+    }
+
+    let errors = validate_config(&config);
+
+    if !errors.is_empty() {
+        for e in &errors {
+            eprintln!("Error: {}", e);
+        }
+
+        std::process::exit(-1);
+    }
+
+    println!("RedConcentrator {}", VERSION.unwrap_or("unknown"));
+
+    if let Err(e) = init_log(&config.log) {
+        eprintln!("Log error: {}", e);
         std::process::exit(-1);
     }
 
-    if config.log.logo {
+    let index = child_index();
+
+    if config.log.logo && index.is_none() {
         print_logo();
     }
 
-    if config.sentinels.is_some() {
-        match run_watch_sentinel(&config) {
-            Ok(sentinel_data) => {
-                if let Err(e) = run_watch(
-                    &config,
-                    sentinel_data.tx_main_loop_message,
-                    sentinel_data.rx_main_loop_message,
-                    sentinel_data.redis_master_address) {
-                        fatal_error(e);
-                    }
-            },
-            Err(e) => fatal_error(e)
+    // Parent only supervises processes that serve clients
+    if config.processes > 1 && index.is_none() {
+        if let Err(e) = supervise(config.processes) {
+            fatal_error(e);
         }
+
+        return;
+    }
+
+    let config = match index {
+        Some(index) => {
+            info!("Start process {}", index);
+            stop_with_parent();
+
+            match child_config(config, index) {
+                Ok(config) => config,
+                Err(e) => return fatal_error(e)
+            }
+        }
+        None => config
+    };
+
+    let init_data = if config.sentinels.is_some() {
+        run_watch_sentinel(&config)
+    } else if config.master.is_some() || config.nodes.is_some() {
+        run_watch_standalone(&config)
     } else {
-        error!("No sentinels found in config file");
+        error!("No sentinels, master or nodes found in config file");
+        return;
+    };
+
+    match init_data {
+        Ok(sentinel_data) => {
+            if let Err(e) = run_watch(
+                &config,
+                sentinel_data.tx_main_loop_message,
+                sentinel_data.rx_main_loop_message,
+                sentinel_data.redis_master_address,
+                config_source) {
+                    fatal_error(e);
+                }
+        },
+        Err(e) => fatal_error(e)
     }
 }
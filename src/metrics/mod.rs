@@ -0,0 +1,105 @@
+//! This module contains counters of RedConcentrator.
+//!
+//! Counters are global and can be read by admin interface.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A named counter.
+pub struct Counter {
+    name: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    const fn new(name: &'static str) -> Self {
+        Counter {
+            name,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    /// Add one.
+    pub fn increment(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add one if value is below max (0 is no max).
+    /// Return false if max is reached.
+    pub fn increment_below(&self, max: u64) -> bool {
+        self.value
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+                if max == 0 || v < max {
+                    Some(v + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    /// Remove one, never below zero.
+    pub fn decrement(&self) {
+        let _ = self
+            .value
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| v.checked_sub(1));
+    }
+
+    /// Current value.
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Name of counter.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// Clients currently connected.
+pub static CONNECTED_CLIENTS: Counter = Counter::new("connected_clients");
+/// Clients accepted since start.
+pub static TOTAL_CONNECTIONS: Counter = Counter::new("total_connections_received");
+/// Clients refused because max_clients is reached.
+pub static REJECTED_CONNECTIONS: Counter = Counter::new("rejected_connections");
+/// Clients closed because they are idle.
+pub static IDLE_CLIENTS_CLOSED: Counter = Counter::new("idle_clients_closed");
+/// Commands delayed or rejected by rate limit.
+pub static RATE_LIMITED_COMMANDS: Counter = Counter::new("rate_limited_commands");
+/// Transactions (MULTI/WATCH) aborted because master changed.
+pub static ABORTED_TRANSACTIONS: Counter = Counter::new("aborted_transactions");
+/// New master ignored because it never reported role master.
+pub static MASTER_ROLE_CHECK_FAILED: Counter = Counter::new("master_role_check_failed");
+/// Failed health checks of static master (without Sentinel).
+pub static MASTER_HEALTH_CHECK_FAILED: Counter = Counter::new("master_health_check_failed");
+/// Checks that found several nodes claiming master.
+pub static SPLIT_BRAIN_DETECTED: Counter = Counter::new("split_brain_detected");
+/// Write commands refused because of split brain.
+pub static SPLIT_BRAIN_REFUSED_WRITES: Counter = Counter::new("split_brain_refused_writes");
+
+/// All counters.
+static COUNTERS: [&Counter; 10] = [
+    &CONNECTED_CLIENTS,
+    &TOTAL_CONNECTIONS,
+    &REJECTED_CONNECTIONS,
+    &IDLE_CLIENTS_CLOSED,
+    &RATE_LIMITED_COMMANDS,
+    &ABORTED_TRANSACTIONS,
+    &MASTER_ROLE_CHECK_FAILED,
+    &MASTER_HEALTH_CHECK_FAILED,
+    &SPLIT_BRAIN_DETECTED,
+    &SPLIT_BRAIN_REFUSED_WRITES,
+];
+
+/// Return all counters like Redis INFO command: "name:value\r\n".
+pub fn render() -> String {
+    let mut output = String::from("# RedConcentrator\r\n");
+
+    for counter in COUNTERS.iter() {
+        output.push_str(&format!("{}:{}\r\n", counter.name(), counter.get()));
+    }
+
+    output
+}
@@ -0,0 +1,31 @@
+use crate::metrics::{render, Counter, IDLE_CLIENTS_CLOSED};
+
+#[test]
+fn render_counters() {
+    IDLE_CLIENTS_CLOSED.increment();
+
+    let output = render();
+
+    assert!(output.starts_with("# RedConcentrator\r\n"));
+    assert!(output.contains("connected_clients:"));
+    assert!(output.contains(&format!(
+        "idle_clients_closed:{}\r\n",
+        IDLE_CLIENTS_CLOSED.get()
+    )));
+}
+
+#[test]
+fn counter_increment_below_max() {
+    let counter = Counter::new("test");
+
+    assert!(counter.increment_below(2));
+    assert!(counter.increment_below(2));
+    assert!(!counter.increment_below(2));
+    assert_eq!(counter.get(), 2);
+
+    counter.decrement();
+    counter.decrement();
+    counter.decrement();
+    assert_eq!(counter.get(), 0);
+    assert!(counter.increment_below(0));
+}
@@ -0,0 +1,217 @@
+//! This module contains token bucket rate limiter.
+//!
+//! Limits are in operations per second and bytes per second. They can be set globally, per
+//! client IP and per authenticated user. A bucket can hold one second of traffic.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::{ConfigRateLimit, ConfigRateLimitValue, RateLimitPolicy};
+
+/// Remove bucket of IP or user unused since this duration.
+const BUCKET_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Clean buckets when there are more than this number.
+const BUCKET_CLEAN_THRESHOLD: usize = 1024;
+
+/// A token bucket.
+/// Tokens can be negative when a big command is allowed (e.g. bytes of a huge SET). Next
+/// commands wait until debt is paid.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Tokens added per second, also max tokens.
+    rate: f64,
+    /// Current tokens.
+    tokens: f64,
+    /// Last time tokens was updated.
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u64, now: Instant) -> Self {
+        TokenBucket {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last: now,
+        }
+    }
+
+    /// Add tokens since last update.
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last = now;
+    }
+
+    /// Check if a token is available.
+    pub fn is_available(&mut self, now: Instant) -> bool {
+        self.refill(now);
+
+        self.tokens >= 1.0
+    }
+
+    /// Take tokens. Must be called after is_available().
+    pub fn take(&mut self, count: u64) {
+        self.tokens -= count as f64;
+    }
+}
+
+/// Buckets for one limit.
+#[derive(Debug)]
+struct Limits {
+    ops: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl Limits {
+    fn new(config: &ConfigRateLimitValue, now: Instant) -> Self {
+        Limits {
+            ops: config.ops.map(|r| TokenBucket::new(r, now)),
+            bytes: config.bytes.map(|r| TokenBucket::new(r, now)),
+        }
+    }
+
+    fn is_available(&mut self, now: Instant) -> bool {
+        let ops = match self.ops.as_mut() {
+            Some(b) => b.is_available(now),
+            None => true,
+        };
+        let bytes = match self.bytes.as_mut() {
+            Some(b) => b.is_available(now),
+            None => true,
+        };
+
+        ops && bytes
+    }
+
+    fn take(&mut self, bytes: u64) {
+        if let Some(b) = self.ops.as_mut() {
+            b.take(1);
+        }
+
+        if let Some(b) = self.bytes.as_mut() {
+            b.take(bytes);
+        }
+    }
+
+    /// Buckets is full and not used since timeout.
+    fn is_idle(&self, now: Instant) -> bool {
+        [self.ops.as_ref(), self.bytes.as_ref()]
+            .iter()
+            .flatten()
+            .all(|b| now.saturating_duration_since(b.last) > BUCKET_IDLE_TIMEOUT)
+    }
+}
+
+/// Get or create limits of a key.
+fn get_limits<'a, K: std::hash::Hash + Eq + Clone>(
+    map: &'a mut HashMap<K, Limits>,
+    key: &K,
+    config: Option<&ConfigRateLimitValue>,
+    now: Instant,
+) -> Option<&'a mut Limits> {
+    let config = config?;
+
+    if !map.contains_key(key) {
+        if map.len() > BUCKET_CLEAN_THRESHOLD {
+            map.retain(|_, l| !l.is_idle(now));
+        }
+
+        map.insert(key.clone(), Limits::new(config, now));
+    }
+
+    map.get_mut(key)
+}
+
+/// Check optional limits.
+fn is_available(limits: &mut Option<&mut Limits>, now: Instant) -> bool {
+    match limits {
+        Some(l) => l.is_available(now),
+        None => true,
+    }
+}
+
+/// Rate limiter shared by all clients.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: ConfigRateLimit,
+    global: Mutex<Limits>,
+    ips: Mutex<HashMap<IpAddr, Limits>>,
+    users: Mutex<HashMap<String, Limits>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: ConfigRateLimit) -> Self {
+        let now = Instant::now();
+        let global = match config.global.as_ref() {
+            Some(c) => Limits::new(c, now),
+            None => Limits {
+                ops: None,
+                bytes: None,
+            },
+        };
+
+        RateLimiter {
+            config,
+            global: Mutex::new(global),
+            ips: Mutex::new(HashMap::new()),
+            users: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// What to do when client is over limit.
+    pub fn policy(&self) -> &RateLimitPolicy {
+        &self.config.policy
+    }
+
+    /// Check if a command of `bytes` size can be sent.
+    /// If allowed, tokens are taken from global, IP and user buckets.
+    pub fn allow(&self, ip: &IpAddr, user: Option<&str>, bytes: usize) -> bool {
+        self.allow_at(ip, user, bytes, Instant::now())
+    }
+
+    /// Same as allow() with current time.
+    pub fn allow_at(&self, ip: &IpAddr, user: Option<&str>, bytes: usize, now: Instant) -> bool {
+        let ip_config = self
+            .config
+            .ips
+            .get(&ip.to_string())
+            .or(self.config.per_ip.as_ref());
+        let user_config =
+            user.and_then(|u| self.config.users.get(u).or(self.config.per_user.as_ref()));
+
+        // Always lock in same order: global, ip, user
+        let mut global = self.global.lock().unwrap();
+        let mut ips = self.ips.lock().unwrap();
+        let mut users = self.users.lock().unwrap();
+
+        let mut ip_limits = get_limits(&mut ips, ip, ip_config, now);
+        let user = user.map(String::from).unwrap_or_default();
+        let mut user_limits = get_limits(&mut users, &user, user_config, now);
+
+        let available = global.is_available(now)
+            && is_available(&mut ip_limits, now)
+            && is_available(&mut user_limits, now);
+
+        if available {
+            let bytes = bytes as u64;
+
+            global.take(bytes);
+
+            if let Some(l) = ip_limits {
+                l.take(bytes);
+            }
+
+            if let Some(l) = user_limits {
+                l.take(bytes);
+            }
+        }
+
+        available
+    }
+}
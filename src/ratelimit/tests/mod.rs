@@ -0,0 +1,83 @@
+use crate::config::{ConfigRateLimit, ConfigRateLimitValue, RateLimitPolicy};
+use crate::ratelimit::{RateLimiter, TokenBucket};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+fn limit(ops: Option<u64>, bytes: Option<u64>) -> Option<ConfigRateLimitValue> {
+    Some(ConfigRateLimitValue { ops, bytes })
+}
+
+#[test]
+fn token_bucket_refill() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(2, now);
+
+    assert!(bucket.is_available(now));
+    bucket.take(1);
+    assert!(bucket.is_available(now));
+    bucket.take(1);
+    assert!(!bucket.is_available(now));
+    assert!(bucket.is_available(now + Duration::from_millis(500)));
+}
+
+#[test]
+fn token_bucket_debt() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(10, now);
+
+    assert!(bucket.is_available(now));
+    bucket.take(30);
+    assert!(!bucket.is_available(now + Duration::from_secs(2)));
+    assert!(bucket.is_available(now + Duration::from_millis(3100)));
+}
+
+#[test]
+fn rate_limiter_per_ip() {
+    let mut ips = BTreeMap::new();
+    ips.insert(
+        String::from("10.0.0.2"),
+        ConfigRateLimitValue {
+            ops: Some(2),
+            bytes: None,
+        },
+    );
+
+    let limiter = RateLimiter::new(ConfigRateLimit {
+        policy: RateLimitPolicy::Reject,
+        global: None,
+        per_ip: limit(Some(1), None),
+        per_user: None,
+        ips,
+        users: BTreeMap::new(),
+    });
+    let now = Instant::now();
+    let ip1: IpAddr = "10.0.0.1".parse().unwrap();
+    let ip2: IpAddr = "10.0.0.2".parse().unwrap();
+
+    assert!(limiter.allow_at(&ip1, None, 10, now));
+    assert!(!limiter.allow_at(&ip1, None, 10, now));
+    assert!(limiter.allow_at(&ip2, None, 10, now));
+    assert!(limiter.allow_at(&ip2, None, 10, now));
+    assert!(!limiter.allow_at(&ip2, None, 10, now));
+}
+
+#[test]
+fn rate_limiter_global_and_user() {
+    let limiter = RateLimiter::new(ConfigRateLimit {
+        policy: RateLimitPolicy::Delay,
+        global: limit(None, Some(100)),
+        per_ip: None,
+        per_user: limit(Some(1), None),
+        ips: BTreeMap::new(),
+        users: BTreeMap::new(),
+    });
+    let now = Instant::now();
+    let ip: IpAddr = "10.0.0.1".parse().unwrap();
+
+    assert!(limiter.allow_at(&ip, Some("batch"), 10, now));
+    assert!(!limiter.allow_at(&ip, Some("batch"), 10, now));
+    assert!(limiter.allow_at(&ip, Some("web"), 200, now));
+    // Global bytes bucket is empty
+    assert!(!limiter.allow_at(&ip, None, 10, now));
+}
@@ -0,0 +1,464 @@
+//! This module contain table of Redis commands.
+//! It's used to know where are keys (or channels) in arguments of a command.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::redis::types::RedisError;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// How to find keys in arguments of command.
+/// Argument 0 is command name.
+#[derive(Debug, PartialEq)]
+pub enum KeySpec {
+    /// Keys from first to last (negative is from end of arguments) every step.
+    Range(isize, isize, usize),
+    /// Argument at this position is number of keys. Keys follow it.
+    NumKeys(usize),
+    /// Keys follow keyword (e.g. STREAMS for XREAD).
+    /// Arguments after keyword are keys then ids, so only first half are keys.
+    Keyword(&'static str),
+    /// Argument after keyword is a key (e.g. STORE of SORT).
+    AfterKeyword(&'static str),
+}
+
+/// Description of a Redis command.
+#[derive(Debug)]
+pub struct CommandSpec {
+    /// Name of command in upper case.
+    pub name: &'static str,
+    /// Where keys are (for pub/sub commands, channels).
+    pub keys: &'static [KeySpec],
+    /// Flags of command (e.g. BLOCKING).
+    pub flags: u8,
+}
+
+/// Command can block connection until data is available or timeout.
+pub const BLOCKING: u8 = 1;
+/// Command can modify data (scripts are considered as writing).
+pub const WRITE: u8 = 2;
+
+const ONE_KEY: &[KeySpec] = &[KeySpec::Range(1, 1, 1)];
+const ALL_KEYS: &[KeySpec] = &[KeySpec::Range(1, -1, 1)];
+const TWO_KEYS: &[KeySpec] = &[KeySpec::Range(1, 2, 1)];
+const ALL_KEYS_BUT_LAST: &[KeySpec] = &[KeySpec::Range(1, -2, 1)];
+const KEY_VALUE: &[KeySpec] = &[KeySpec::Range(1, -1, 2)];
+const NO_KEY: &[KeySpec] = &[];
+
+/// Create a command.
+const fn cmd(name: &'static str, keys: &'static [KeySpec]) -> CommandSpec {
+    CommandSpec {
+        name,
+        keys,
+        flags: 0,
+    }
+}
+
+/// Create a blocking command.
+const fn blocking(name: &'static str, keys: &'static [KeySpec]) -> CommandSpec {
+    CommandSpec {
+        name,
+        keys,
+        flags: BLOCKING,
+    }
+}
+
+/// Create a write command.
+const fn write(name: &'static str, keys: &'static [KeySpec]) -> CommandSpec {
+    CommandSpec {
+        name,
+        keys,
+        flags: WRITE,
+    }
+}
+
+/// Create a blocking write command.
+const fn blocking_write(name: &'static str, keys: &'static [KeySpec]) -> CommandSpec {
+    CommandSpec {
+        name,
+        keys,
+        flags: BLOCKING | WRITE,
+    }
+}
+
+/// Known commands.
+static COMMANDS: &[CommandSpec] = &[
+    // Connection and server
+    cmd("AUTH", NO_KEY),
+    cmd("HELLO", NO_KEY),
+    cmd("PING", NO_KEY),
+    cmd("ECHO", NO_KEY),
+    cmd("SELECT", NO_KEY),
+    cmd("QUIT", NO_KEY),
+    cmd("RESET", NO_KEY),
+    cmd("INFO", NO_KEY),
+    cmd("ROLE", NO_KEY),
+    cmd("CLIENT", NO_KEY),
+    cmd("CONFIG", NO_KEY),
+    cmd("DBSIZE", NO_KEY),
+    write("FLUSHDB", NO_KEY),
+    write("FLUSHALL", NO_KEY),
+    cmd("TIME", NO_KEY),
+    cmd("COMMAND", NO_KEY),
+    cmd("RANDOMKEY", NO_KEY),
+    cmd("SCAN", NO_KEY),
+    cmd("KEYS", ONE_KEY),
+    blocking("WAIT", NO_KEY),
+    blocking("WAITAOF", NO_KEY),
+    // Transaction
+    cmd("MULTI", NO_KEY),
+    cmd("EXEC", NO_KEY),
+    cmd("DISCARD", NO_KEY),
+    cmd("WATCH", ALL_KEYS),
+    cmd("UNWATCH", NO_KEY),
+    // Generic
+    write("DEL", ALL_KEYS),
+    write("UNLINK", ALL_KEYS),
+    cmd("EXISTS", ALL_KEYS),
+    cmd("TOUCH", ALL_KEYS),
+    cmd("TYPE", ONE_KEY),
+    write("EXPIRE", ONE_KEY),
+    write("EXPIREAT", ONE_KEY),
+    write("PEXPIRE", ONE_KEY),
+    write("PEXPIREAT", ONE_KEY),
+    cmd("EXPIRETIME", ONE_KEY),
+    cmd("PEXPIRETIME", ONE_KEY),
+    write("PERSIST", ONE_KEY),
+    cmd("TTL", ONE_KEY),
+    cmd("PTTL", ONE_KEY),
+    write("RENAME", TWO_KEYS),
+    write("RENAMENX", TWO_KEYS),
+    write("COPY", TWO_KEYS),
+    write("MOVE", ONE_KEY),
+    cmd("DUMP", ONE_KEY),
+    write("RESTORE", ONE_KEY),
+    cmd("OBJECT", &[KeySpec::Range(2, 2, 1)]),
+    cmd("MEMORY", &[KeySpec::Range(2, 2, 1)]),
+    write(
+        "SORT",
+        &[KeySpec::Range(1, 1, 1), KeySpec::AfterKeyword("STORE")],
+    ),
+    cmd("SORT_RO", ONE_KEY),
+    // String
+    cmd("GET", ONE_KEY),
+    write("SET", ONE_KEY),
+    write("SETNX", ONE_KEY),
+    write("SETEX", ONE_KEY),
+    write("PSETEX", ONE_KEY),
+    write("GETSET", ONE_KEY),
+    write("GETDEL", ONE_KEY),
+    write("GETEX", ONE_KEY),
+    cmd("GETRANGE", ONE_KEY),
+    write("SETRANGE", ONE_KEY),
+    cmd("STRLEN", ONE_KEY),
+    write("APPEND", ONE_KEY),
+    write("INCR", ONE_KEY),
+    write("DECR", ONE_KEY),
+    write("INCRBY", ONE_KEY),
+    write("DECRBY", ONE_KEY),
+    write("INCRBYFLOAT", ONE_KEY),
+    cmd("MGET", ALL_KEYS),
+    write("MSET", KEY_VALUE),
+    write("MSETNX", KEY_VALUE),
+    cmd("GETBIT", ONE_KEY),
+    write("SETBIT", ONE_KEY),
+    cmd("BITCOUNT", ONE_KEY),
+    cmd("BITPOS", ONE_KEY),
+    write("BITFIELD", ONE_KEY),
+    write("BITOP", &[KeySpec::Range(2, -1, 1)]),
+    write("PFADD", ONE_KEY),
+    cmd("PFCOUNT", ALL_KEYS),
+    write("PFMERGE", ALL_KEYS),
+    // Hash
+    cmd("HGET", ONE_KEY),
+    write("HSET", ONE_KEY),
+    write("HSETNX", ONE_KEY),
+    cmd("HMGET", ONE_KEY),
+    write("HMSET", ONE_KEY),
+    write("HDEL", ONE_KEY),
+    cmd("HEXISTS", ONE_KEY),
+    cmd("HGETALL", ONE_KEY),
+    cmd("HKEYS", ONE_KEY),
+    cmd("HVALS", ONE_KEY),
+    cmd("HLEN", ONE_KEY),
+    cmd("HSTRLEN", ONE_KEY),
+    write("HINCRBY", ONE_KEY),
+    write("HINCRBYFLOAT", ONE_KEY),
+    cmd("HRANDFIELD", ONE_KEY),
+    cmd("HSCAN", ONE_KEY),
+    // List
+    write("LPUSH", ONE_KEY),
+    write("RPUSH", ONE_KEY),
+    write("LPUSHX", ONE_KEY),
+    write("RPUSHX", ONE_KEY),
+    write("LPOP", ONE_KEY),
+    write("RPOP", ONE_KEY),
+    cmd("LLEN", ONE_KEY),
+    cmd("LRANGE", ONE_KEY),
+    cmd("LINDEX", ONE_KEY),
+    write("LSET", ONE_KEY),
+    write("LREM", ONE_KEY),
+    write("LTRIM", ONE_KEY),
+    write("LINSERT", ONE_KEY),
+    cmd("LPOS", ONE_KEY),
+    write("RPOPLPUSH", TWO_KEYS),
+    write("LMOVE", TWO_KEYS),
+    blocking_write("BLMOVE", TWO_KEYS),
+    blocking_write("BRPOPLPUSH", TWO_KEYS),
+    blocking_write("BLPOP", ALL_KEYS_BUT_LAST),
+    blocking_write("BRPOP", ALL_KEYS_BUT_LAST),
+    write("LMPOP", &[KeySpec::NumKeys(1)]),
+    blocking_write("BLMPOP", &[KeySpec::NumKeys(2)]),
+    // Set
+    write("SADD", ONE_KEY),
+    write("SREM", ONE_KEY),
+    cmd("SMEMBERS", ONE_KEY),
+    cmd("SISMEMBER", ONE_KEY),
+    cmd("SMISMEMBER", ONE_KEY),
+    cmd("SCARD", ONE_KEY),
+    write("SPOP", ONE_KEY),
+    cmd("SRANDMEMBER", ONE_KEY),
+    cmd("SSCAN", ONE_KEY),
+    write("SMOVE", TWO_KEYS),
+    cmd("SINTER", ALL_KEYS),
+    cmd("SUNION", ALL_KEYS),
+    cmd("SDIFF", ALL_KEYS),
+    write("SINTERSTORE", ALL_KEYS),
+    write("SUNIONSTORE", ALL_KEYS),
+    write("SDIFFSTORE", ALL_KEYS),
+    cmd("SINTERCARD", &[KeySpec::NumKeys(1)]),
+    // Sorted set
+    write("ZADD", ONE_KEY),
+    write("ZREM", ONE_KEY),
+    cmd("ZCARD", ONE_KEY),
+    cmd("ZCOUNT", ONE_KEY),
+    cmd("ZSCORE", ONE_KEY),
+    cmd("ZMSCORE", ONE_KEY),
+    write("ZINCRBY", ONE_KEY),
+    cmd("ZRANK", ONE_KEY),
+    cmd("ZREVRANK", ONE_KEY),
+    cmd("ZRANGE", ONE_KEY),
+    cmd("ZREVRANGE", ONE_KEY),
+    cmd("ZRANGEBYSCORE", ONE_KEY),
+    cmd("ZREVRANGEBYSCORE", ONE_KEY),
+    cmd("ZRANGEBYLEX", ONE_KEY),
+    cmd("ZREVRANGEBYLEX", ONE_KEY),
+    cmd("ZLEXCOUNT", ONE_KEY),
+    write("ZREMRANGEBYRANK", ONE_KEY),
+    write("ZREMRANGEBYSCORE", ONE_KEY),
+    write("ZREMRANGEBYLEX", ONE_KEY),
+    write("ZPOPMIN", ONE_KEY),
+    write("ZPOPMAX", ONE_KEY),
+    cmd("ZRANDMEMBER", ONE_KEY),
+    cmd("ZSCAN", ONE_KEY),
+    write("ZRANGESTORE", TWO_KEYS),
+    blocking_write("BZPOPMIN", ALL_KEYS_BUT_LAST),
+    blocking_write("BZPOPMAX", ALL_KEYS_BUT_LAST),
+    write(
+        "ZUNIONSTORE",
+        &[KeySpec::Range(1, 1, 1), KeySpec::NumKeys(2)],
+    ),
+    write(
+        "ZINTERSTORE",
+        &[KeySpec::Range(1, 1, 1), KeySpec::NumKeys(2)],
+    ),
+    write(
+        "ZDIFFSTORE",
+        &[KeySpec::Range(1, 1, 1), KeySpec::NumKeys(2)],
+    ),
+    cmd("ZUNION", &[KeySpec::NumKeys(1)]),
+    cmd("ZINTER", &[KeySpec::NumKeys(1)]),
+    cmd("ZDIFF", &[KeySpec::NumKeys(1)]),
+    cmd("ZINTERCARD", &[KeySpec::NumKeys(1)]),
+    write("ZMPOP", &[KeySpec::NumKeys(1)]),
+    blocking_write("BZMPOP", &[KeySpec::NumKeys(2)]),
+    // Geo
+    write("GEOADD", ONE_KEY),
+    cmd("GEODIST", ONE_KEY),
+    cmd("GEOHASH", ONE_KEY),
+    cmd("GEOPOS", ONE_KEY),
+    cmd("GEOSEARCH", ONE_KEY),
+    write("GEOSEARCHSTORE", TWO_KEYS),
+    write(
+        "GEORADIUS",
+        &[
+            KeySpec::Range(1, 1, 1),
+            KeySpec::AfterKeyword("STORE"),
+            KeySpec::AfterKeyword("STOREDIST"),
+        ],
+    ),
+    write(
+        "GEORADIUSBYMEMBER",
+        &[
+            KeySpec::Range(1, 1, 1),
+            KeySpec::AfterKeyword("STORE"),
+            KeySpec::AfterKeyword("STOREDIST"),
+        ],
+    ),
+    // Stream
+    write("XADD", ONE_KEY),
+    cmd("XLEN", ONE_KEY),
+    cmd("XRANGE", ONE_KEY),
+    cmd("XREVRANGE", ONE_KEY),
+    write("XDEL", ONE_KEY),
+    write("XTRIM", ONE_KEY),
+    write("XACK", ONE_KEY),
+    cmd("XPENDING", ONE_KEY),
+    write("XCLAIM", ONE_KEY),
+    write("XAUTOCLAIM", ONE_KEY),
+    write("XSETID", ONE_KEY),
+    write("XGROUP", &[KeySpec::Range(2, 2, 1)]),
+    cmd("XINFO", &[KeySpec::Range(2, 2, 1)]),
+    blocking("XREAD", &[KeySpec::Keyword("STREAMS")]),
+    blocking_write("XREADGROUP", &[KeySpec::Keyword("STREAMS")]),
+    // Scripting
+    write("EVAL", &[KeySpec::NumKeys(2)]),
+    write("EVALSHA", &[KeySpec::NumKeys(2)]),
+    cmd("EVAL_RO", &[KeySpec::NumKeys(2)]),
+    cmd("EVALSHA_RO", &[KeySpec::NumKeys(2)]),
+    write("FCALL", &[KeySpec::NumKeys(2)]),
+    cmd("FCALL_RO", &[KeySpec::NumKeys(2)]),
+    // Pub/Sub
+    cmd("PUBLISH", ONE_KEY),
+    cmd("SPUBLISH", ONE_KEY),
+    cmd("SUBSCRIBE", ALL_KEYS),
+    cmd("UNSUBSCRIBE", ALL_KEYS),
+    cmd("PSUBSCRIBE", ALL_KEYS),
+    cmd("PUNSUBSCRIBE", ALL_KEYS),
+    cmd("SSUBSCRIBE", ALL_KEYS),
+    cmd("SUNSUBSCRIBE", ALL_KEYS),
+];
+
+/// Return command table indexed by name.
+fn commands() -> &'static HashMap<&'static str, &'static CommandSpec> {
+    static TABLE: OnceLock<HashMap<&'static str, &'static CommandSpec>> = OnceLock::new();
+
+    TABLE.get_or_init(|| COMMANDS.iter().map(|c| (c.name, c)).collect())
+}
+
+/// Return name of command in upper case.
+pub fn command_name(args: &[Vec<u8>]) -> String {
+    match args.first() {
+        Some(name) => String::from_utf8_lossy(name).to_ascii_uppercase(),
+        None => String::new(),
+    }
+}
+
+/// Search command in table.
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    commands().get(name).copied()
+}
+
+/// Check if command can block connection.
+/// XREAD and XREADGROUP block only with BLOCK option.
+pub fn is_blocking(args: &[Vec<u8>]) -> bool {
+    let name = command_name(args);
+
+    match lookup(&name) {
+        Some(spec) if spec.flags & BLOCKING != 0 => match name.as_str() {
+            "XREAD" | "XREADGROUP" => find_keyword(args, "BLOCK").is_some(),
+            _ => true,
+        },
+        _ => false,
+    }
+}
+
+/// Check if command can modify data.
+/// Unknown command is not a write.
+pub fn is_write(args: &[Vec<u8>]) -> bool {
+    match lookup(&command_name(args)) {
+        Some(spec) => spec.flags & WRITE != 0,
+        None => false,
+    }
+}
+
+/// Convert a position (negative is from end) to index in arguments.
+fn to_index(position: isize, len: usize) -> Option<usize> {
+    if position >= 0 {
+        Some(position as usize)
+    } else {
+        let index = len as isize + position;
+
+        if index < 0 {
+            None
+        } else {
+            Some(index as usize)
+        }
+    }
+}
+
+/// Return position of keys in arguments of command, or error if arguments are inconsistent.
+/// Unknown command has no key.
+pub fn key_positions(args: &[Vec<u8>]) -> Result<Vec<usize>, RedisError> {
+    let spec = match lookup(&command_name(args)) {
+        Some(s) => s,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut positions = Vec::new();
+
+    for key_spec in spec.keys {
+        match key_spec {
+            KeySpec::Range(first, last, step) => {
+                let first = match to_index(*first, args.len()) {
+                    Some(f) => f,
+                    None => continue,
+                };
+                let last = match to_index(*last, args.len()) {
+                    Some(l) => l,
+                    None => continue,
+                };
+
+                let mut index = first;
+
+                while index <= last && index < args.len() {
+                    positions.push(index);
+                    index += step;
+                }
+            }
+            KeySpec::NumKeys(index) => {
+                let numkeys = args
+                    .get(*index)
+                    .and_then(|n| String::from_utf8_lossy(n).parse::<usize>().ok())
+                    .unwrap_or(0);
+                let first = index + 1;
+
+                // Number of keys is sent by client, never trust it
+                match first.checked_add(numkeys) {
+                    Some(end) if end <= args.len() => positions.extend(first..end),
+                    _ => {
+                        return Err(RedisError::from_redis(
+                            "ERR",
+                            "Number of keys can't be greater than number of args",
+                        ))
+                    }
+                }
+            }
+            KeySpec::Keyword(keyword) => {
+                if let Some(index) = find_keyword(args, keyword) {
+                    let count = (args.len() - index - 1) / 2;
+
+                    positions.extend(index + 1..index + 1 + count);
+                }
+            }
+            KeySpec::AfterKeyword(keyword) => {
+                if let Some(index) = find_keyword(args, keyword) {
+                    if index + 1 < args.len() {
+                        positions.push(index + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(positions)
+}
+
+/// Search keyword in arguments (case insensitive). Command name is skipped.
+fn find_keyword(args: &[Vec<u8>], keyword: &str) -> Option<usize> {
+    args.iter()
+        .skip(1)
+        .position(|a| a.eq_ignore_ascii_case(keyword.as_bytes()))
+        .map(|p| p + 1)
+}
@@ -0,0 +1,107 @@
+use crate::redis::command::{command_name, is_blocking, is_write, key_positions, lookup};
+
+fn args(data: &[&str]) -> Vec<Vec<u8>> {
+    data.iter().map(|a| a.as_bytes().to_vec()).collect()
+}
+
+#[test]
+fn command_name_upper_case() {
+    assert_eq!(command_name(&args(&["get", "a"])), "GET");
+    assert_eq!(command_name(&[]), "");
+}
+
+#[test]
+fn lookup_unknown_command() {
+    assert!(lookup("GET").is_some());
+    assert!(lookup("NOT-A-COMMAND").is_none());
+}
+
+#[test]
+fn key_positions_range() {
+    assert_eq!(key_positions(&args(&["GET", "a"])).unwrap(), vec![1]);
+    assert_eq!(
+        key_positions(&args(&["DEL", "a", "b", "c"])).unwrap(),
+        vec![1, 2, 3]
+    );
+    assert_eq!(
+        key_positions(&args(&["MSET", "a", "1", "b", "2"])).unwrap(),
+        vec![1, 3]
+    );
+    assert_eq!(
+        key_positions(&args(&["BLPOP", "a", "b", "0"])).unwrap(),
+        vec![1, 2]
+    );
+    assert_eq!(
+        key_positions(&args(&["PING"])).unwrap(),
+        Vec::<usize>::new()
+    );
+}
+
+#[test]
+fn key_positions_numkeys() {
+    assert_eq!(
+        key_positions(&args(&["EVAL", "return 1", "2", "a", "b", "arg"])).unwrap(),
+        vec![3, 4]
+    );
+    assert_eq!(
+        key_positions(&args(&[
+            "ZUNIONSTORE",
+            "dest",
+            "2",
+            "a",
+            "b",
+            "WEIGHTS",
+            "1",
+            "2"
+        ]))
+        .unwrap(),
+        vec![1, 3, 4]
+    );
+}
+
+#[test]
+fn key_positions_invalid_numkeys() {
+    assert!(key_positions(&args(&["EVAL", "return 1", "3", "a", "b"])).is_err());
+    assert!(key_positions(&args(&["EVAL", "return 1", "4000000000", "a"])).is_err());
+    assert!(key_positions(&args(&["EVAL", "return 1", "18446744073709551615", "a"])).is_err());
+    assert_eq!(
+        key_positions(&args(&["EVAL", "return 1", "1", "a"])).unwrap(),
+        vec![3]
+    );
+}
+
+#[test]
+fn key_positions_keyword() {
+    assert_eq!(
+        key_positions(&args(&[
+            "XREAD", "COUNT", "2", "STREAMS", "a", "b", "0", "0"
+        ]))
+        .unwrap(),
+        vec![4, 5]
+    );
+    assert_eq!(
+        key_positions(&args(&["SORT", "a", "LIMIT", "0", "5", "store", "b"])).unwrap(),
+        vec![1, 6]
+    );
+}
+
+#[test]
+fn blocking_commands() {
+    assert!(is_blocking(&args(&["blpop", "a", "0"])));
+    assert!(is_blocking(&args(&["WAIT", "1", "0"])));
+    assert!(!is_blocking(&args(&["LPOP", "a"])));
+    assert!(!is_blocking(&args(&["XREAD", "STREAMS", "s", "0"])));
+    assert!(is_blocking(&args(&[
+        "XREAD", "BLOCK", "0", "STREAMS", "s", "$"
+    ])));
+}
+
+#[test]
+fn write_commands() {
+    assert!(is_write(&args(&["set", "a", "1"])));
+    assert!(is_write(&args(&["BLPOP", "a", "0"])));
+    assert!(is_write(&args(&["EVAL", "return 1", "0"])));
+    assert!(!is_write(&args(&["GET", "a"])));
+    assert!(!is_write(&args(&["EVAL_RO", "return 1", "0"])));
+    assert!(!is_write(&args(&["NOT-A-COMMAND"])));
+}
@@ -0,0 +1,120 @@
+//! This module contain serializer of Redis protocol.
+//!
+//! Commands are always sent as array of bulk strings, so arguments are binary safe: a key with
+//! spaces or CRLF can't break the command.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::redis::types::{
+    RedisValue, REDIS_TYPE_ARRAY, REDIS_TYPE_ATTRIBUTE, REDIS_TYPE_MAP, REDIS_TYPE_PUSH,
+    REDIS_TYPE_SET,
+};
+
+/// Command to send to Redis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    args: Vec<Vec<u8>>,
+}
+
+impl Command {
+    /// Create command with its name.
+    pub fn new(name: &str) -> Self {
+        Command {
+            args: vec![name.as_bytes().to_vec()],
+        }
+    }
+
+    /// Add an argument.
+    pub fn arg<T: AsRef<[u8]>>(mut self, arg: T) -> Self {
+        self.args.push(arg.as_ref().to_vec());
+        self
+    }
+
+    /// Add several arguments.
+    pub fn args<I, T>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        self.args
+            .extend(args.into_iter().map(|a| a.as_ref().to_vec()));
+        self
+    }
+
+    /// Encode command to Redis protocol.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        encode_command(&self.args, &mut output);
+
+        output
+    }
+}
+
+/// Encode command as array of bulk strings.
+pub fn encode_command(args: &[Vec<u8>], output: &mut Vec<u8>) {
+    output.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+
+    for arg in args {
+        encode_bulk_string(arg, output);
+    }
+}
+
+/// Encode value to Redis protocol.
+pub fn encode_value(value: &RedisValue, output: &mut Vec<u8>) {
+    match value {
+        RedisValue::Nil => output.extend_from_slice(b"$-1\r\n"),
+        RedisValue::NilArray => output.extend_from_slice(b"*-1\r\n"),
+        RedisValue::Integer(i) => output.extend_from_slice(format!(":{}\r\n", i).as_bytes()),
+        RedisValue::String(s) => output.extend_from_slice(format!("+{}\r\n", s).as_bytes()),
+        RedisValue::BulkString(s) => encode_bulk_string(s, output),
+        RedisValue::Array(values) => encode_values(REDIS_TYPE_ARRAY, values, output),
+        RedisValue::Null => output.extend_from_slice(b"_\r\n"),
+        RedisValue::Double(d) => output.extend_from_slice(format!(",{}\r\n", d).as_bytes()),
+        RedisValue::Boolean(b) => output.extend_from_slice(if *b { b"#t\r\n" } else { b"#f\r\n" }),
+        RedisValue::BigNumber(n) => output.extend_from_slice(format!("({}\r\n", n).as_bytes()),
+        RedisValue::VerbatimString(format, data) => {
+            output.extend_from_slice(
+                format!("={}\r\n{}:", format.len() + 1 + data.len(), format).as_bytes(),
+            );
+            output.extend_from_slice(data);
+            output.extend_from_slice(b"\r\n");
+        }
+        RedisValue::Map(pairs) => encode_pairs(REDIS_TYPE_MAP, pairs, output),
+        RedisValue::Set(values) => encode_values(REDIS_TYPE_SET, values, output),
+        RedisValue::Attribute(pairs, value) => {
+            encode_pairs(REDIS_TYPE_ATTRIBUTE, pairs, output);
+            encode_value(value, output);
+        }
+        RedisValue::Push(values) => encode_values(REDIS_TYPE_PUSH, values, output),
+    }
+}
+
+/// Encode bulk string.
+fn encode_bulk_string(data: &[u8], output: &mut Vec<u8>) {
+    output.extend_from_slice(format!("${}\r\n", data.len()).as_bytes());
+    output.extend_from_slice(data);
+    output.extend_from_slice(b"\r\n");
+}
+
+/// Encode aggregate type (array, set, push).
+fn encode_values(kind: u8, values: &[RedisValue], output: &mut Vec<u8>) {
+    output.push(kind);
+    output.extend_from_slice(format!("{}\r\n", values.len()).as_bytes());
+
+    for v in values {
+        encode_value(v, output);
+    }
+}
+
+/// Encode pairs of key/value (map, attributes).
+fn encode_pairs(kind: u8, pairs: &[(RedisValue, RedisValue)], output: &mut Vec<u8>) {
+    output.push(kind);
+    output.extend_from_slice(format!("{}\r\n", pairs.len()).as_bytes());
+
+    for (k, v) in pairs {
+        encode_value(k, output);
+        encode_value(v, output);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::redis::encoder::{encode_value, Command};
+use crate::redis::parser::{parse_command, parse_value};
+use crate::redis::types::RedisValue;
+
+#[test]
+fn command_is_multibulk() {
+    let cmd = Command::new("GET").arg("my key\r\nFLUSHALL");
+
+    assert_eq!(
+        cmd.encode(),
+        b"*2\r\n$3\r\nGET\r\n$16\r\nmy key\r\nFLUSHALL\r\n".to_vec()
+    );
+}
+
+#[test]
+fn command_binary_args() {
+    let cmd = Command::new("SET").arg(b"k\x00ey").arg(vec![0xff, b'\n']);
+
+    let (args, size) = parse_command(&cmd.encode()).unwrap().unwrap();
+
+    assert_eq!(size, cmd.encode().len());
+    assert_eq!(
+        args,
+        vec![b"SET".to_vec(), b"k\x00ey".to_vec(), vec![0xff, b'\n']]
+    );
+}
+
+#[test]
+fn encode_then_parse_value() {
+    let values = vec![
+        RedisValue::Nil,
+        RedisValue::NilArray,
+        RedisValue::Integer(-12),
+        RedisValue::String(String::from("OK")),
+        RedisValue::BulkString(b"a\r\nb".to_vec()),
+        RedisValue::Array(vec![RedisValue::Integer(1), RedisValue::Nil]),
+        RedisValue::Null,
+        RedisValue::Boolean(true),
+        RedisValue::Double(String::from("1.5")),
+        RedisValue::BigNumber(String::from("1234567890123456789")),
+        RedisValue::VerbatimString(String::from("txt"), b"hello".to_vec()),
+        RedisValue::Map(vec![(
+            RedisValue::String(String::from("a")),
+            RedisValue::Integer(1),
+        )]),
+        RedisValue::Set(vec![RedisValue::Integer(2)]),
+        RedisValue::Push(vec![RedisValue::BulkString(b"message".to_vec())]),
+    ];
+
+    for value in values {
+        let mut output = Vec::new();
+
+        encode_value(&value, &mut output);
+
+        let (parsed, size) = parse_value(&output).unwrap().unwrap();
+
+        assert_eq!(parsed, value);
+        assert_eq!(size, output.len());
+    }
+}
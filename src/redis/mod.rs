@@ -1,15 +1,29 @@
 //! This module contain basic Redis commands.
 //!
-mod parser;
+#[cfg(test)]
+pub mod tests;
+
+pub mod command;
+pub mod encoder;
+pub mod namespace;
 pub mod node;
+pub mod parser;
+pub mod pipeline;
 pub mod stream;
 pub mod sentinel;
+pub mod splitbrain;
+pub mod standalone;
 pub mod subscription;
 pub mod types;
 
-use crate::redis::parser::{read_array, read_bulk_string, read_strict_string};
+use crate::redis::encoder::Command;
+use crate::redis::parser::{read_reply, read_strict_string, read_transaction_reply};
+use crate::redis::pipeline::{Pipeline, PipelineReply};
 use crate::redis::stream::RedisStream;
-use crate::redis::types::{RedisError, RedisValue};
+use crate::redis::types::{ErrorKind, RedisError, RedisValue};
+
+/// Fields and values returned by HGETALL.
+pub type BulkPairs = Vec<(Vec<u8>, Vec<u8>)>;
 
 pub struct RedisConnector {
     stream: Box<dyn RedisStream>,
@@ -20,14 +34,16 @@ impl RedisConnector {
         RedisConnector { stream }
     }
 
+    /// Send command to Redis.
+    fn send(&mut self, cmd: &Command) -> Result<(), RedisError> {
+        self.stream
+            .write(&cmd.encode())
+            .map_err(RedisError::from_io_error)
+    }
+
     /// Send PING command and wait PONG response.
-    #[allow(dead_code)]
     pub fn ping(&mut self) -> Result<(), RedisError> {
-        let cmd = "PING\r\n".as_bytes();
-
-        if let Err(e) = self.stream.write(cmd) {
-            return Err(RedisError::from_io_error(e));
-        }
+        self.send(&Command::new("PING"))?;
 
         let response = read_strict_string(&mut self.stream)?;
 
@@ -41,20 +57,12 @@ impl RedisConnector {
     }
 
     /// Get bulk string.
-    #[allow(dead_code)]
-    pub fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>, RedisError> {
-        let key = format!("GET {}\r\n", key);
-
-        if let Err(e) = self.stream.write(key.as_bytes()) {
-            return Err(RedisError::from_io_error(e));
-        }
-
-        read_bulk_string(&mut self.stream)
+    pub fn get<K: AsRef<[u8]>>(&mut self, key: K) -> Result<Option<Vec<u8>>, RedisError> {
+        to_bulk(self.execute(&Command::new("GET").arg(key))?)
     }
 
     /// Get string
-    #[allow(dead_code)]
-    pub fn get_string(&mut self, key: &str) -> Result<Option<String>, RedisError> {
+    pub fn get_string<K: AsRef<[u8]>>(&mut self, key: K) -> Result<Option<String>, RedisError> {
         let data = self.get(key)?;
 
         if let Some(data) = data {
@@ -72,26 +80,602 @@ impl RedisConnector {
 
     /// Get master addr
     pub fn get_master_addr(&mut self, master_name: &str) -> Result<String, RedisError> {
-        let cmd = format!("SENTINEL GET-MASTER-ADDR-BY-NAME {}\r\n", master_name);
+        self.send(&master_addr_command(master_name))?;
+
+        to_master_addr(read_reply(&mut self.stream)?)
+    }
+}
+
+/// Typed commands.
+/// Keys, fields and values are binary safe. Error replies of Redis are returned with their kind
+/// (e.g. WrongTypeError), a reply of unexpected type is returned as TypeError.
+impl RedisConnector {
+    /// Send any command and return reply.
+    pub fn execute(&mut self, cmd: &Command) -> Result<RedisValue, RedisError> {
+        self.send(cmd)?;
+
+        read_reply(&mut self.stream)
+    }
+
+    /// Send commands of pipeline in one batch and return reply of each command in order.
+    /// Error of a command is returned in its reply, connection error fails whole pipeline.
+    /// If MULTI of atomic pipeline fails, commands are not atomic and their own replies are
+    /// returned.
+    pub fn execute_pipeline(&mut self, pipeline: &Pipeline) -> Result<PipelineReply, RedisError> {
+        self.stream
+            .write(&pipeline.encode())
+            .map_err(RedisError::from_io_error)?;
 
-        if let Err(e) = self.stream.write(cmd.as_bytes()) {
-            return Err(RedisError::from_io_error(e));
+        if !pipeline.is_atomic() {
+            return (0..pipeline.len())
+                .map(|_| self.read_command_reply())
+                .collect();
         }
 
-        let data = read_array(&mut self.stream)?;
+        // Replies of MULTI, commands and EXEC are all read even if MULTI fails, so no stale
+        // reply is left on connection.
+        let multi = self.read_command_reply()?;
 
-        match data {
-            RedisValue::Array(d) => {
-                let addr = convert_to_string(d.get(0).unwrap())?;
-                let port = convert_to_string(d.get(1).unwrap())?;
+        // Command rejected when queued (e.g. wrong number of arguments) aborts transaction.
+        let mut queued = Vec::new();
 
-                Ok(String::from(format!("{}:{}", addr, port)))
+        for _ in 0..pipeline.len() {
+            queued.push(self.read_command_reply()?);
+        }
+
+        let exec = match read_transaction_reply(&mut self.stream) {
+            Err(e) if matches!(e.kind(), ErrorKind::IoError | ErrorKind::NoDataAvailable) => {
+                return Err(e)
             }
-            _ => Err(RedisError::from_message(
-                "Impossible, get_master_addr don't return array!",
-            )),
+            exec => exec,
+        };
+
+        if multi.and_then(to_ok).is_err() {
+            // Commands were not queued but run one by one (or queued in a transaction already
+            // started), their replies tell what happened.
+            return Ok(queued);
+        }
+
+        match exec {
+            Ok(Some(replies)) => Ok(replies),
+            Ok(None) => Ok((0..pipeline.len())
+                .map(|_| {
+                    Err(RedisError::from_redis(
+                        "EXECABORT",
+                        "Transaction aborted by WATCH.",
+                    ))
+                })
+                .collect()),
+            Err(e) if e.kind() == ErrorKind::ExecAbortError => Ok(queued
+                .into_iter()
+                .map(|reply| {
+                    Err(reply
+                        .err()
+                        .unwrap_or_else(|| RedisError::from_redis("EXECABORT", &e.message())))
+                })
+                .collect()),
+            Err(e) => Err(e),
         }
     }
+
+    /// Read reply of a command of pipeline.
+    /// Only connection errors are returned as error.
+    fn read_command_reply(&mut self) -> Result<Result<RedisValue, RedisError>, RedisError> {
+        match read_reply(&mut self.stream) {
+            Err(e) if matches!(e.kind(), ErrorKind::IoError | ErrorKind::NoDataAvailable) => Err(e),
+            reply => Ok(reply),
+        }
+    }
+
+    /// SET key value.
+    pub fn set<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<(), RedisError> {
+        let reply = self.execute(&Command::new("SET").arg(key).arg(value))?;
+
+        to_ok(reply)
+    }
+
+    /// SET key value EX seconds.
+    pub fn set_ex<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: V,
+        seconds: u64,
+    ) -> Result<(), RedisError> {
+        let cmd = Command::new("SET")
+            .arg(key)
+            .arg(value)
+            .arg("EX")
+            .arg(seconds.to_string());
+
+        to_ok(self.execute(&cmd)?)
+    }
+
+    /// SET key value NX. Return false if key already exists.
+    pub fn set_nx<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<bool, RedisError> {
+        match self.execute(&Command::new("SET").arg(key).arg(value).arg("NX"))? {
+            RedisValue::Nil | RedisValue::Null => Ok(false),
+            reply => to_ok(reply).map(|_| true),
+        }
+    }
+
+    /// MGET key [key ...].
+    pub fn mget<K: AsRef<[u8]>>(&mut self, keys: &[K]) -> Result<Vec<Option<Vec<u8>>>, RedisError> {
+        let reply = self.execute(&Command::new("MGET").args(keys))?;
+
+        to_list(reply)?.into_iter().map(to_bulk).collect()
+    }
+
+    /// MSET key value [key value ...].
+    pub fn mset<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        pairs: &[(K, V)],
+    ) -> Result<(), RedisError> {
+        let mut cmd = Command::new("MSET");
+
+        for (key, value) in pairs {
+            cmd = cmd.arg(key).arg(value);
+        }
+
+        to_ok(self.execute(&cmd)?)
+    }
+
+    /// DEL key [key ...]. Return number of keys removed.
+    pub fn del<K: AsRef<[u8]>>(&mut self, keys: &[K]) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("DEL").args(keys))?)
+    }
+
+    /// EXISTS key [key ...]. Return number of existing keys.
+    pub fn exists<K: AsRef<[u8]>>(&mut self, keys: &[K]) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("EXISTS").args(keys))?)
+    }
+
+    /// INCRBY key increment. Return new value.
+    pub fn incr_by<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        increment: isize,
+    ) -> Result<isize, RedisError> {
+        let cmd = Command::new("INCRBY").arg(key).arg(increment.to_string());
+
+        to_integer(self.execute(&cmd)?)
+    }
+
+    /// INCR key. Return new value.
+    pub fn incr<K: AsRef<[u8]>>(&mut self, key: K) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("INCR").arg(key))?)
+    }
+
+    /// DECR key. Return new value.
+    pub fn decr<K: AsRef<[u8]>>(&mut self, key: K) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("DECR").arg(key))?)
+    }
+
+    /// HGET key field.
+    pub fn hget<K: AsRef<[u8]>, F: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        field: F,
+    ) -> Result<Option<Vec<u8>>, RedisError> {
+        to_bulk(self.execute(&Command::new("HGET").arg(key).arg(field))?)
+    }
+
+    /// HSET key field value. Return number of fields added.
+    pub fn hset<K: AsRef<[u8]>, F: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        field: F,
+        value: V,
+    ) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("HSET").arg(key).arg(field).arg(value))?)
+    }
+
+    /// HDEL key field [field ...]. Return number of fields removed.
+    pub fn hdel<K: AsRef<[u8]>, F: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        fields: &[F],
+    ) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("HDEL").arg(key).args(fields))?)
+    }
+
+    /// HEXISTS key field.
+    pub fn hexists<K: AsRef<[u8]>, F: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        field: F,
+    ) -> Result<bool, RedisError> {
+        to_bool(self.execute(&Command::new("HEXISTS").arg(key).arg(field))?)
+    }
+
+    /// HGETALL key. Return fields and values.
+    pub fn hgetall<K: AsRef<[u8]>>(&mut self, key: K) -> Result<BulkPairs, RedisError> {
+        to_bulk_pairs(self.execute(&Command::new("HGETALL").arg(key))?)
+    }
+
+    /// HINCRBY key field increment. Return new value.
+    pub fn hincr_by<K: AsRef<[u8]>, F: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        field: F,
+        increment: isize,
+    ) -> Result<isize, RedisError> {
+        let cmd = Command::new("HINCRBY")
+            .arg(key)
+            .arg(field)
+            .arg(increment.to_string());
+
+        to_integer(self.execute(&cmd)?)
+    }
+
+    /// LPUSH key value [value ...]. Return length of list.
+    pub fn lpush<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        values: &[V],
+    ) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("LPUSH").arg(key).args(values))?)
+    }
+
+    /// RPUSH key value [value ...]. Return length of list.
+    pub fn rpush<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        values: &[V],
+    ) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("RPUSH").arg(key).args(values))?)
+    }
+
+    /// LPOP key.
+    pub fn lpop<K: AsRef<[u8]>>(&mut self, key: K) -> Result<Option<Vec<u8>>, RedisError> {
+        to_bulk(self.execute(&Command::new("LPOP").arg(key))?)
+    }
+
+    /// RPOP key.
+    pub fn rpop<K: AsRef<[u8]>>(&mut self, key: K) -> Result<Option<Vec<u8>>, RedisError> {
+        to_bulk(self.execute(&Command::new("RPOP").arg(key))?)
+    }
+
+    /// LLEN key.
+    pub fn llen<K: AsRef<[u8]>>(&mut self, key: K) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("LLEN").arg(key))?)
+    }
+
+    /// LRANGE key start stop.
+    pub fn lrange<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        start: isize,
+        stop: isize,
+    ) -> Result<Vec<Vec<u8>>, RedisError> {
+        let cmd = Command::new("LRANGE")
+            .arg(key)
+            .arg(start.to_string())
+            .arg(stop.to_string());
+
+        to_bulk_list(self.execute(&cmd)?)
+    }
+
+    /// SADD key member [member ...]. Return number of members added.
+    pub fn sadd<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        members: &[M],
+    ) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("SADD").arg(key).args(members))?)
+    }
+
+    /// SREM key member [member ...]. Return number of members removed.
+    pub fn srem<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        members: &[M],
+    ) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("SREM").arg(key).args(members))?)
+    }
+
+    /// SMEMBERS key.
+    pub fn smembers<K: AsRef<[u8]>>(&mut self, key: K) -> Result<Vec<Vec<u8>>, RedisError> {
+        to_bulk_list(self.execute(&Command::new("SMEMBERS").arg(key))?)
+    }
+
+    /// SISMEMBER key member.
+    pub fn sismember<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        member: M,
+    ) -> Result<bool, RedisError> {
+        to_bool(self.execute(&Command::new("SISMEMBER").arg(key).arg(member))?)
+    }
+
+    /// SCARD key.
+    pub fn scard<K: AsRef<[u8]>>(&mut self, key: K) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("SCARD").arg(key))?)
+    }
+
+    /// ZADD key score member. Return number of members added.
+    pub fn zadd<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        score: f64,
+        member: M,
+    ) -> Result<isize, RedisError> {
+        let cmd = Command::new("ZADD")
+            .arg(key)
+            .arg(score.to_string())
+            .arg(member);
+
+        to_integer(self.execute(&cmd)?)
+    }
+
+    /// ZREM key member [member ...]. Return number of members removed.
+    pub fn zrem<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        members: &[M],
+    ) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("ZREM").arg(key).args(members))?)
+    }
+
+    /// ZSCORE key member.
+    pub fn zscore<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        member: M,
+    ) -> Result<Option<f64>, RedisError> {
+        match self.execute(&Command::new("ZSCORE").arg(key).arg(member))? {
+            RedisValue::Nil | RedisValue::Null => Ok(None),
+            reply => to_double(reply).map(Some),
+        }
+    }
+
+    /// ZCARD key.
+    pub fn zcard<K: AsRef<[u8]>>(&mut self, key: K) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("ZCARD").arg(key))?)
+    }
+
+    /// ZRANGE key start stop.
+    pub fn zrange<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        start: isize,
+        stop: isize,
+    ) -> Result<Vec<Vec<u8>>, RedisError> {
+        let cmd = Command::new("ZRANGE")
+            .arg(key)
+            .arg(start.to_string())
+            .arg(stop.to_string());
+
+        to_bulk_list(self.execute(&cmd)?)
+    }
+
+    /// EXPIRE key seconds. Return false if key doesn't exist.
+    pub fn expire<K: AsRef<[u8]>>(&mut self, key: K, seconds: u64) -> Result<bool, RedisError> {
+        to_bool(self.execute(&Command::new("EXPIRE").arg(key).arg(seconds.to_string()))?)
+    }
+
+    /// PEXPIRE key milliseconds. Return false if key doesn't exist.
+    pub fn pexpire<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        milliseconds: u64,
+    ) -> Result<bool, RedisError> {
+        to_bool(
+            self.execute(
+                &Command::new("PEXPIRE")
+                    .arg(key)
+                    .arg(milliseconds.to_string()),
+            )?,
+        )
+    }
+
+    /// PERSIST key. Return false if key has no expiry.
+    pub fn persist<K: AsRef<[u8]>>(&mut self, key: K) -> Result<bool, RedisError> {
+        to_bool(self.execute(&Command::new("PERSIST").arg(key))?)
+    }
+
+    /// TTL key. Return -2 if key doesn't exist, -1 if it has no expiry.
+    pub fn ttl<K: AsRef<[u8]>>(&mut self, key: K) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("TTL").arg(key))?)
+    }
+
+    /// PTTL key. Return -2 if key doesn't exist, -1 if it has no expiry.
+    pub fn pttl<K: AsRef<[u8]>>(&mut self, key: K) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("PTTL").arg(key))?)
+    }
+
+    /// INFO [section].
+    pub fn info(&mut self, section: Option<&str>) -> Result<String, RedisError> {
+        let mut cmd = Command::new("INFO");
+
+        if let Some(section) = section {
+            cmd = cmd.arg(section);
+        }
+
+        to_text(self.execute(&cmd)?)
+    }
+
+    /// ROLE. Return role of server: master, slave or sentinel.
+    pub fn role(&mut self) -> Result<String, RedisError> {
+        let reply = self.execute(&Command::new("ROLE"))?;
+
+        match to_list(reply)?.into_iter().next() {
+            Some(role) => to_text(role),
+            None => Err(RedisError::from_unexpected_reply(&RedisValue::Array(
+                Vec::new(),
+            ))),
+        }
+    }
+
+    /// CLIENT ID.
+    pub fn client_id(&mut self) -> Result<isize, RedisError> {
+        to_integer(self.execute(&Command::new("CLIENT").arg("ID"))?)
+    }
+
+    /// CLIENT SETNAME name.
+    pub fn client_setname(&mut self, name: &str) -> Result<(), RedisError> {
+        to_ok(self.execute(&Command::new("CLIENT").arg("SETNAME").arg(name))?)
+    }
+
+    /// CLIENT GETNAME.
+    pub fn client_getname(&mut self) -> Result<Option<String>, RedisError> {
+        match self.execute(&Command::new("CLIENT").arg("GETNAME"))? {
+            RedisValue::Nil | RedisValue::Null => Ok(None),
+            reply => to_text(reply).map(Some),
+        }
+    }
+
+    /// CLIENT LIST.
+    pub fn client_list(&mut self) -> Result<String, RedisError> {
+        to_text(self.execute(&Command::new("CLIENT").arg("LIST"))?)
+    }
+
+    /// CONFIG GET parameter. Return parameters and values.
+    pub fn config_get(&mut self, parameter: &str) -> Result<Vec<(String, String)>, RedisError> {
+        let reply = self.execute(&Command::new("CONFIG").arg("GET").arg(parameter))?;
+
+        Ok(to_bulk_pairs(reply)?
+            .into_iter()
+            .map(|(k, v)| {
+                (
+                    String::from_utf8_lossy(&k).to_string(),
+                    String::from_utf8_lossy(&v).to_string(),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Command to ask master address of group to a sentinel.
+pub fn master_addr_command(group_name: &str) -> Command {
+    Command::new("SENTINEL")
+        .arg("GET-MASTER-ADDR-BY-NAME")
+        .arg(group_name)
+}
+
+/// Convert reply of SENTINEL GET-MASTER-ADDR-BY-NAME to "ip:port".
+pub fn to_master_addr(reply: RedisValue) -> Result<String, RedisError> {
+    match reply {
+        RedisValue::Array(d) if d.len() == 2 => Ok(format!(
+            "{}:{}",
+            convert_to_string(&d[0])?,
+            convert_to_string(&d[1])?
+        )),
+        RedisValue::NilArray | RedisValue::Null => {
+            Err(RedisError::from_message("Master group not found"))
+        }
+        reply => Err(RedisError::from_unexpected_reply(&reply)),
+    }
+}
+
+/// Check reply is OK.
+fn to_ok(reply: RedisValue) -> Result<(), RedisError> {
+    match reply {
+        RedisValue::String(s) if s == "OK" => Ok(()),
+        reply => Err(RedisError::from_unexpected_reply(&reply)),
+    }
+}
+
+/// Convert reply to integer.
+fn to_integer(reply: RedisValue) -> Result<isize, RedisError> {
+    match reply {
+        RedisValue::Integer(i) => Ok(i),
+        reply => Err(RedisError::from_unexpected_reply(&reply)),
+    }
+}
+
+/// Convert reply to boolean (integer 0/1 in RESP2).
+fn to_bool(reply: RedisValue) -> Result<bool, RedisError> {
+    match reply {
+        RedisValue::Integer(i) => Ok(i != 0),
+        RedisValue::Boolean(b) => Ok(b),
+        reply => Err(RedisError::from_unexpected_reply(&reply)),
+    }
+}
+
+/// Convert reply to double (bulk string in RESP2).
+fn to_double(reply: RedisValue) -> Result<f64, RedisError> {
+    let text = match &reply {
+        RedisValue::BulkString(s) => String::from_utf8_lossy(s).to_string(),
+        RedisValue::Double(s) => s.clone(),
+        _ => return Err(RedisError::from_unexpected_reply(&reply)),
+    };
+
+    text.parse()
+        .map_err(|_| RedisError::from_unexpected_reply(&reply))
+}
+
+/// Convert reply to optional bulk string.
+fn to_bulk(reply: RedisValue) -> Result<Option<Vec<u8>>, RedisError> {
+    match reply {
+        RedisValue::BulkString(s) => Ok(Some(s)),
+        RedisValue::VerbatimString(_, s) => Ok(Some(s)),
+        RedisValue::Nil | RedisValue::Null => Ok(None),
+        reply => Err(RedisError::from_unexpected_reply(&reply)),
+    }
+}
+
+/// Convert reply to text.
+fn to_text(reply: RedisValue) -> Result<String, RedisError> {
+    match reply {
+        RedisValue::String(s) => Ok(s),
+        RedisValue::BulkString(s) | RedisValue::VerbatimString(_, s) => {
+            Ok(String::from_utf8_lossy(&s).to_string())
+        }
+        reply => Err(RedisError::from_unexpected_reply(&reply)),
+    }
+}
+
+/// Convert reply to list of values (array or RESP3 set).
+fn to_list(reply: RedisValue) -> Result<Vec<RedisValue>, RedisError> {
+    match reply {
+        RedisValue::Array(values) | RedisValue::Set(values) => Ok(values),
+        reply => Err(RedisError::from_unexpected_reply(&reply)),
+    }
+}
+
+/// Convert reply to list of bulk strings.
+fn to_bulk_list(reply: RedisValue) -> Result<Vec<Vec<u8>>, RedisError> {
+    to_list(reply)?
+        .into_iter()
+        .map(|value| match value {
+            RedisValue::BulkString(s) => Ok(s),
+            value => Err(RedisError::from_unexpected_reply(&value)),
+        })
+        .collect()
+}
+
+/// Convert reply to pairs (flat array in RESP2, map in RESP3).
+fn to_bulk_pairs(reply: RedisValue) -> Result<BulkPairs, RedisError> {
+    let pairs = match reply {
+        RedisValue::Map(pairs) => pairs,
+        RedisValue::Array(values) if values.len() % 2 == 0 => {
+            let mut pairs = Vec::new();
+            let mut values = values.into_iter();
+
+            while let (Some(k), Some(v)) = (values.next(), values.next()) {
+                pairs.push((k, v));
+            }
+
+            pairs
+        }
+        reply => return Err(RedisError::from_unexpected_reply(&reply)),
+    };
+
+    pairs
+        .into_iter()
+        .map(|(k, v)| match (to_bulk(k)?, to_bulk(v)?) {
+            (Some(k), Some(v)) => Ok((k, v)),
+            _ => Err(RedisError::from_unexpected_reply(&RedisValue::Nil)),
+        })
+        .collect()
 }
 
 /// Convert string or return error.
@@ -106,7 +690,6 @@ pub fn convert_to_string(value: &RedisValue) -> Result<String, RedisError> {
 }
 
 /// Convert string or return error.
-#[allow(dead_code)]
 pub fn convert_to_integer(value: &RedisValue) -> Result<isize, RedisError> {
     match value {
         RedisValue::Integer(s) => Ok(s.clone()),
@@ -0,0 +1,241 @@
+//! This module contain routine to isolate clients by adding a prefix to keys.
+//!
+//! Keys of command are found with command table. Prefix is removed from replies that return
+//! key names (KEYS, SCAN, RANDOMKEY, BLPOP, LMPOP, XREAD...) and from pub/sub channel names. Commands not in table and commands
+//! that act on whole keyspace (FLUSHALL, CONFIG...) are refused, they would reach keys of other
+//! namespaces.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::redis::command::{command_name, key_positions, lookup};
+use crate::redis::types::{RedisError, RedisValue};
+
+/// Commands that act on whole keyspace or server, refused when prefix is set.
+const CROSS_KEYSPACE_COMMANDS: [&str; 6] =
+    ["FLUSHALL", "FLUSHDB", "CONFIG", "DEBUG", "SWAPDB", "DBSIZE"];
+
+/// What to do with reply of a command when prefix is set.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplyRewrite {
+    /// Reply doesn't contain key.
+    None,
+    /// Reply is an array of keys (KEYS).
+    KeyList,
+    /// Reply is a cursor and an array of keys (SCAN).
+    Scan,
+    /// Reply is a key of whole keyspace (RANDOMKEY), null if key is outside namespace.
+    Key,
+    /// Reply is an array and first element is a key (BLPOP, BZPOPMIN, LMPOP, ZMPOP...).
+    FirstKey,
+    /// Reply is an array of key and entries, or a map in RESP3 (XREAD, XREADGROUP).
+    Streams,
+}
+
+/// Escape glob-style characters of prefix to use it in pattern.
+fn escape_pattern(prefix: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(prefix.len());
+
+    for c in prefix {
+        if matches!(c, b'*' | b'?' | b'[' | b']' | b'\\') {
+            escaped.push(b'\\');
+        }
+
+        escaped.push(*c);
+    }
+
+    escaped
+}
+
+/// Concat prefix and value.
+fn with_prefix(prefix: &[u8], value: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(prefix.len() + value.len());
+
+    data.extend_from_slice(prefix);
+    data.extend_from_slice(value);
+
+    data
+}
+
+/// Add prefix to keys (and channels) of command.
+/// Return how reply must be rewrite, or error if command can't be restricted to namespace.
+pub fn add_prefix(args: &mut Vec<Vec<u8>>, prefix: &[u8]) -> Result<ReplyRewrite, RedisError> {
+    let name = command_name(args);
+
+    if CROSS_KEYSPACE_COMMANDS.contains(&name.as_str()) {
+        return Err(RedisError::from_redis(
+            "ERR",
+            &format!("command '{}' is not allowed in namespace", name),
+        ));
+    }
+
+    if lookup(&name).is_none() {
+        return Err(RedisError::from_redis(
+            "ERR",
+            &format!("unknown command '{}' in namespace", name),
+        ));
+    }
+
+    let rewrite = match name.as_str() {
+        "KEYS" => {
+            if let Some(pattern) = args.get_mut(1) {
+                *pattern = with_prefix(&escape_pattern(prefix), pattern);
+            }
+
+            ReplyRewrite::KeyList
+        }
+        "SCAN" => {
+            let escaped = escape_pattern(prefix);
+            let position = args
+                .iter()
+                .skip(2)
+                .position(|a| a.eq_ignore_ascii_case(b"MATCH"))
+                .map(|p| p + 3);
+
+            match position {
+                Some(p) if p < args.len() => args[p] = with_prefix(&escaped, &args[p]),
+                _ => {
+                    // No MATCH, restrict scan to namespace
+                    args.push(b"MATCH".to_vec());
+                    args.push(with_prefix(&escaped, b"*"));
+                }
+            }
+
+            ReplyRewrite::Scan
+        }
+        "PSUBSCRIBE" | "PUNSUBSCRIBE" => {
+            let escaped = escape_pattern(prefix);
+
+            for pattern in args.iter_mut().skip(1) {
+                *pattern = with_prefix(&escaped, pattern);
+            }
+
+            ReplyRewrite::None
+        }
+        _ => {
+            for position in key_positions(args)? {
+                args[position] = with_prefix(prefix, &args[position]);
+            }
+
+            key_reply_rewrite(&name)
+        }
+    };
+
+    Ok(rewrite)
+}
+
+/// How rewrite reply of a command with keys.
+fn key_reply_rewrite(name: &str) -> ReplyRewrite {
+    match name {
+        "RANDOMKEY" => ReplyRewrite::Key,
+        "BLPOP" | "BRPOP" | "BZPOPMIN" | "BZPOPMAX" | "LMPOP" | "BLMPOP" | "ZMPOP" | "BZMPOP" => {
+            ReplyRewrite::FirstKey
+        }
+        "XREAD" | "XREADGROUP" => ReplyRewrite::Streams,
+        _ => ReplyRewrite::None,
+    }
+}
+
+/// Remove prefix of a key if present.
+fn strip(value: RedisValue, prefix: &[u8]) -> RedisValue {
+    match value {
+        RedisValue::BulkString(s) if s.starts_with(prefix) => {
+            RedisValue::BulkString(s[prefix.len()..].to_vec())
+        }
+        v => v,
+    }
+}
+
+/// Remove prefix of each key of array.
+fn strip_array(value: RedisValue, prefix: &[u8]) -> RedisValue {
+    match value {
+        RedisValue::Array(keys) => {
+            RedisValue::Array(keys.into_iter().map(|k| strip(k, prefix)).collect())
+        }
+        v => v,
+    }
+}
+
+/// Remove prefix from reply.
+pub fn remove_prefix(value: RedisValue, rewrite: &ReplyRewrite, prefix: &[u8]) -> RedisValue {
+    match rewrite {
+        ReplyRewrite::None => value,
+        ReplyRewrite::KeyList => strip_array(value, prefix),
+        ReplyRewrite::Scan => match value {
+            RedisValue::Array(mut data) if data.len() == 2 => {
+                let keys = data.pop().unwrap();
+                data.push(strip_array(keys, prefix));
+
+                RedisValue::Array(data)
+            }
+            v => v,
+        },
+        ReplyRewrite::Key => match value {
+            RedisValue::BulkString(s) if s.starts_with(prefix) => {
+                RedisValue::BulkString(s[prefix.len()..].to_vec())
+            }
+            // Key of another namespace is not visible
+            RedisValue::BulkString(_) => RedisValue::Nil,
+            v => v,
+        },
+        ReplyRewrite::FirstKey => match value {
+            RedisValue::Array(mut data) if !data.is_empty() => {
+                data[0] = strip(std::mem::replace(&mut data[0], RedisValue::Nil), prefix);
+
+                RedisValue::Array(data)
+            }
+            v => v,
+        },
+        ReplyRewrite::Streams => match value {
+            RedisValue::Array(streams) => RedisValue::Array(
+                streams
+                    .into_iter()
+                    .map(|s| remove_prefix(s, &ReplyRewrite::FirstKey, prefix))
+                    .collect(),
+            ),
+            RedisValue::Map(streams) => RedisValue::Map(
+                streams
+                    .into_iter()
+                    .map(|(key, entries)| (strip(key, prefix), entries))
+                    .collect(),
+            ),
+            v => v,
+        },
+    }
+}
+
+/// Remove prefix from pub/sub message.
+/// `message`, `subscribe`... have channel at position 1,
+/// `pmessage` has pattern at position 1 and channel at position 2.
+/// Patterns have escaped prefix.
+/// RESP3 messages are push instead of array.
+pub fn remove_prefix_from_message(value: RedisValue, prefix: &[u8]) -> RedisValue {
+    match value {
+        RedisValue::Array(data) => RedisValue::Array(strip_message(data, prefix)),
+        RedisValue::Push(data) => RedisValue::Push(strip_message(data, prefix)),
+        v => v,
+    }
+}
+
+/// Remove prefix from channel (and pattern) of message.
+fn strip_message(data: Vec<RedisValue>, prefix: &[u8]) -> Vec<RedisValue> {
+    let kind = match data.first() {
+        Some(RedisValue::BulkString(k)) => k.clone(),
+        _ => Vec::new(),
+    };
+    let is_pattern = matches!(
+        kind.as_slice(),
+        b"pmessage" | b"psubscribe" | b"punsubscribe"
+    );
+    let escaped = escape_pattern(prefix);
+
+    data.into_iter()
+        .enumerate()
+        .map(|(index, v)| match index {
+            1 if is_pattern => strip(v, &escaped),
+            1 => strip(v, prefix),
+            2 if kind == b"pmessage" => strip(v, prefix),
+            _ => v,
+        })
+        .collect()
+}
@@ -0,0 +1,226 @@
+use crate::redis::namespace::{
+    add_prefix, remove_prefix, remove_prefix_from_message, ReplyRewrite,
+};
+use crate::redis::types::RedisValue;
+
+fn args(data: &[&str]) -> Vec<Vec<u8>> {
+    data.iter().map(|a| a.as_bytes().to_vec()).collect()
+}
+
+fn bulk(data: &str) -> RedisValue {
+    RedisValue::BulkString(data.as_bytes().to_vec())
+}
+
+#[test]
+fn add_prefix_to_keys() {
+    let mut cmd = args(&["MSET", "a", "1", "b", "2"]);
+
+    assert_eq!(add_prefix(&mut cmd, b"app:").ok(), Some(ReplyRewrite::None));
+    assert_eq!(cmd, args(&["MSET", "app:a", "1", "app:b", "2"]));
+}
+
+#[test]
+fn add_prefix_to_channels() {
+    let mut cmd = args(&["SUBSCRIBE", "news", "sport"]);
+
+    assert!(add_prefix(&mut cmd, b"app:").is_ok());
+    assert_eq!(cmd, args(&["SUBSCRIBE", "app:news", "app:sport"]));
+}
+
+#[test]
+fn add_prefix_to_keys_pattern() {
+    let mut cmd = args(&["KEYS", "user:*"]);
+
+    assert_eq!(
+        add_prefix(&mut cmd, b"a*b:").ok(),
+        Some(ReplyRewrite::KeyList)
+    );
+    assert_eq!(cmd, args(&["KEYS", "a\\*b:user:*"]));
+}
+
+#[test]
+fn add_prefix_to_scan() {
+    let mut cmd = args(&["SCAN", "0", "match", "user:*", "COUNT", "10"]);
+
+    assert_eq!(add_prefix(&mut cmd, b"app:").ok(), Some(ReplyRewrite::Scan));
+    assert_eq!(
+        cmd,
+        args(&["SCAN", "0", "match", "app:user:*", "COUNT", "10"])
+    );
+
+    let mut cmd = args(&["SCAN", "0"]);
+
+    assert!(add_prefix(&mut cmd, b"app:").is_ok());
+    assert_eq!(cmd, args(&["SCAN", "0", "MATCH", "app:*"]));
+}
+
+#[test]
+fn add_prefix_to_patterns() {
+    let mut cmd = args(&["PSUBSCRIBE", "news:*"]);
+
+    assert!(add_prefix(&mut cmd, b"a*b:").is_ok());
+    assert_eq!(cmd, args(&["PSUBSCRIBE", "a\\*b:news:*"]));
+}
+
+#[test]
+fn add_prefix_refuse_commands() {
+    for name in ["FLUSHALL", "CONFIG", "SWAPDB", "UNKNOWN"] {
+        let mut cmd = args(&[name, "a"]);
+
+        assert!(add_prefix(&mut cmd, b"app:").is_err());
+        assert_eq!(cmd, args(&[name, "a"]));
+    }
+
+    let mut cmd = args(&["EVAL", "return 1", "18446744073709551615", "a"]);
+    assert!(add_prefix(&mut cmd, b"app:").is_err());
+}
+
+#[test]
+fn remove_prefix_from_replies() {
+    let keys = RedisValue::Array(vec![bulk("app:a"), bulk("other")]);
+
+    assert_eq!(
+        remove_prefix(keys, &ReplyRewrite::KeyList, b"app:"),
+        RedisValue::Array(vec![bulk("a"), bulk("other")])
+    );
+
+    let scan = RedisValue::Array(vec![bulk("12"), RedisValue::Array(vec![bulk("app:a")])]);
+
+    assert_eq!(
+        remove_prefix(scan, &ReplyRewrite::Scan, b"app:"),
+        RedisValue::Array(vec![bulk("12"), RedisValue::Array(vec![bulk("a")])])
+    );
+}
+
+#[test]
+fn add_prefix_to_commands_returning_keys() {
+    for (cmd, rewrite) in [
+        (vec!["RANDOMKEY"], ReplyRewrite::Key),
+        (vec!["BLPOP", "a", "0"], ReplyRewrite::FirstKey),
+        (vec!["BZPOPMAX", "a", "0"], ReplyRewrite::FirstKey),
+        (vec!["LMPOP", "1", "a", "LEFT"], ReplyRewrite::FirstKey),
+        (vec!["BZMPOP", "0", "1", "a", "MIN"], ReplyRewrite::FirstKey),
+        (vec!["XREAD", "STREAMS", "a", "0"], ReplyRewrite::Streams),
+        (
+            vec!["XREADGROUP", "GROUP", "g", "c", "STREAMS", "a", ">"],
+            ReplyRewrite::Streams,
+        ),
+    ] {
+        let mut cmd = args(&cmd);
+
+        assert_eq!(add_prefix(&mut cmd, b"app:").ok(), Some(rewrite));
+    }
+}
+
+#[test]
+fn remove_prefix_from_key_replies() {
+    assert_eq!(
+        remove_prefix(bulk("app:a"), &ReplyRewrite::Key, b"app:"),
+        bulk("a")
+    );
+    assert_eq!(
+        remove_prefix(bulk("other:a"), &ReplyRewrite::Key, b"app:"),
+        RedisValue::Nil
+    );
+    assert_eq!(
+        remove_prefix(RedisValue::Nil, &ReplyRewrite::Key, b"app:"),
+        RedisValue::Nil
+    );
+
+    // BLPOP, BZPOPMIN
+    assert_eq!(
+        remove_prefix(
+            RedisValue::Array(vec![bulk("app:a"), bulk("v")]),
+            &ReplyRewrite::FirstKey,
+            b"app:"
+        ),
+        RedisValue::Array(vec![bulk("a"), bulk("v")])
+    );
+    assert_eq!(
+        remove_prefix(
+            RedisValue::Array(vec![bulk("app:a"), bulk("m"), bulk("1")]),
+            &ReplyRewrite::FirstKey,
+            b"app:"
+        ),
+        RedisValue::Array(vec![bulk("a"), bulk("m"), bulk("1")])
+    );
+    // LMPOP, ZMPOP
+    assert_eq!(
+        remove_prefix(
+            RedisValue::Array(vec![bulk("app:a"), RedisValue::Array(vec![bulk("v")])]),
+            &ReplyRewrite::FirstKey,
+            b"app:"
+        ),
+        RedisValue::Array(vec![bulk("a"), RedisValue::Array(vec![bulk("v")])])
+    );
+    assert_eq!(
+        remove_prefix(RedisValue::NilArray, &ReplyRewrite::FirstKey, b"app:"),
+        RedisValue::NilArray
+    );
+}
+
+#[test]
+fn remove_prefix_from_streams() {
+    let entries = RedisValue::Array(vec![RedisValue::Array(vec![
+        bulk("1-0"),
+        RedisValue::Array(vec![bulk("f"), bulk("app:v")]),
+    ])]);
+
+    assert_eq!(
+        remove_prefix(
+            RedisValue::Array(vec![RedisValue::Array(vec![
+                bulk("app:s"),
+                entries.clone()
+            ])]),
+            &ReplyRewrite::Streams,
+            b"app:"
+        ),
+        RedisValue::Array(vec![RedisValue::Array(vec![bulk("s"), entries.clone()])])
+    );
+    assert_eq!(
+        remove_prefix(
+            RedisValue::Map(vec![(bulk("app:s"), entries.clone())]),
+            &ReplyRewrite::Streams,
+            b"app:"
+        ),
+        RedisValue::Map(vec![(bulk("s"), entries)])
+    );
+}
+
+#[test]
+fn remove_prefix_from_pubsub_message() {
+    let message = RedisValue::Array(vec![bulk("message"), bulk("app:news"), bulk("app:data")]);
+
+    assert_eq!(
+        remove_prefix_from_message(message, b"app:"),
+        RedisValue::Array(vec![bulk("message"), bulk("news"), bulk("app:data")])
+    );
+
+    let message = RedisValue::Array(vec![
+        bulk("pmessage"),
+        bulk("app:n*"),
+        bulk("app:news"),
+        bulk("data"),
+    ]);
+
+    assert_eq!(
+        remove_prefix_from_message(message, b"app:"),
+        RedisValue::Array(vec![
+            bulk("pmessage"),
+            bulk("n*"),
+            bulk("news"),
+            bulk("data")
+        ])
+    );
+
+    let message = RedisValue::Array(vec![
+        bulk("psubscribe"),
+        bulk("a\\*b:n*"),
+        RedisValue::Integer(1),
+    ]);
+
+    assert_eq!(
+        remove_prefix_from_message(message, b"a*b:"),
+        RedisValue::Array(vec![bulk("psubscribe"), bulk("n*"), RedisValue::Integer(1)])
+    );
+}
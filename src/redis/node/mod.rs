@@ -2,18 +2,48 @@
 //!
 use crate::redis::stream::network::NetworkStream;
 use crate::redis::types::RedisError;
-use std::net::TcpStream;
-
-/// Create a a network stream in blocking mode.
-pub fn create_redis_stream_connection_blocking(address: &str) -> Result<NetworkStream, RedisError> {
-    create_redis_stream_param(address, false)
-}
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
 
 /// Create a a network stream in non blocking mode.
 pub fn create_redis_stream_connection(address: &str) -> Result<NetworkStream, RedisError> {
     create_redis_stream_param(address, true)
 }
 
+/// Create a network stream in blocking mode, connect and read can't take more than timeout.
+pub fn create_redis_stream_connection_timeout(
+    address: &str,
+    timeout: Duration,
+) -> Result<NetworkStream, RedisError> {
+    let socket_addr = match address.to_socket_addrs() {
+        Ok(mut addrs) => match addrs.next() {
+            Some(a) => a,
+            None => {
+                return Err(RedisError::from_message(&format!(
+                    "Can't resolve {}",
+                    address
+                )))
+            }
+        },
+        Err(e) => return Err(RedisError::from_io_error(e)),
+    };
+
+    let tcp_stream = match TcpStream::connect_timeout(&socket_addr, timeout) {
+        Ok(s) => s,
+        Err(e) => return Err(RedisError::from_io_error(e)),
+    };
+
+    if let Err(e) = tcp_stream.set_read_timeout(Some(timeout)) {
+        return Err(RedisError::from_io_error(e));
+    }
+
+    if let Err(e) = tcp_stream.set_write_timeout(Some(timeout)) {
+        return Err(RedisError::from_io_error(e));
+    }
+
+    Ok(NetworkStream::new(tcp_stream))
+}
+
 /// Create redis stream.
 fn create_redis_stream_param(address: &str, blocking: bool) -> Result<NetworkStream, RedisError> {
     let tcp_stream = match TcpStream::connect(address) {
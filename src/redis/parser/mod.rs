@@ -1,5 +1,9 @@
 //! This module contain parse basic routine.
 //!
+//! Parser works on byte slices: it returns a complete frame and number of bytes consumed, or
+//! None if more data is needed. Data are never lost when a frame is incomplete, so parser can be
+//! used on nonblocking streams.
+//!
 
 use crate::redis::stream::RedisStream;
 use crate::redis::types::REDIS_TYPE_ARRAY;
@@ -7,10 +11,24 @@ use crate::redis::types::REDIS_TYPE_BULK_STRING;
 use crate::redis::types::REDIS_TYPE_ERROR;
 use crate::redis::types::REDIS_TYPE_INTEGER;
 use crate::redis::types::{RedisError, RedisValue, REDIS_TYPE_STRING};
+use crate::redis::types::{
+    REDIS_TYPE_ATTRIBUTE, REDIS_TYPE_BIG_NUMBER, REDIS_TYPE_BLOB_ERROR, REDIS_TYPE_BOOLEAN,
+    REDIS_TYPE_DOUBLE, REDIS_TYPE_MAP, REDIS_TYPE_NULL, REDIS_TYPE_PUSH, REDIS_TYPE_SET,
+    REDIS_TYPE_VERBATIM_STRING,
+};
 
 #[cfg(test)]
 pub mod tests;
 
+/// Max size of a bulk string, like Redis (proto-max-bulk-len).
+const MAX_BULK_LENGTH: isize = 512 * 1024 * 1024;
+/// Max elements of an aggregate, like Redis containers.
+const MAX_AGGREGATE_LENGTH: isize = u32::MAX as isize;
+/// Max arguments of a command sent by client, like Redis.
+const MAX_COMMAND_ARGUMENTS: isize = 1024 * 1024;
+/// Max size of an inline command or a line of header, like Redis.
+const MAX_INLINE_LENGTH: usize = 64 * 1024;
+
 /// Redis type get from redis.
 #[derive(Debug, PartialEq)]
 enum RedisType {
@@ -19,20 +37,98 @@ enum RedisType {
     BulkString,
     Array,
     Error,
+    Null,
+    Double,
+    Boolean,
+    BlobError,
+    VerbatimString,
+    BigNumber,
+    Map,
+    Set,
+    Attribute,
+    Push,
 }
 
-/// Get one byte. If none, raise error.
-#[inline(always)]
-fn get_byte(stream: &mut Box<dyn RedisStream>) -> Result<u8, RedisError> {
-    match stream.get() {
-        Ok(c) => match c {
-            Some(c) => Ok(c),
-            None => Err(RedisError::from_no_data()),
-        },
-        Err(e) => Err(RedisError::from_io_error(e)),
+/// Frame decoded from a buffer.
+/// Data are borrowed from buffer, nothing is copied.
+#[derive(Debug, PartialEq)]
+pub enum RedisFrame<'a> {
+    SimpleString(&'a [u8]),
+    Error(&'a [u8]),
+    Integer(isize),
+    BulkString(&'a [u8]),
+    /// Null bulk string (RESP2).
+    Nil,
+    /// Null array (RESP2).
+    NilArray,
+    Array(Vec<RedisFrame<'a>>),
+    Null,
+    Double(&'a [u8]),
+    Boolean(bool),
+    BlobError(&'a [u8]),
+    /// Format and data.
+    VerbatimString(&'a [u8], &'a [u8]),
+    BigNumber(&'a [u8]),
+    Map(Vec<(RedisFrame<'a>, RedisFrame<'a>)>),
+    Set(Vec<RedisFrame<'a>>),
+    /// Attributes and the frame they describe.
+    Attribute(Vec<(RedisFrame<'a>, RedisFrame<'a>)>, Box<RedisFrame<'a>>),
+    Push(Vec<RedisFrame<'a>>),
+}
+
+impl<'a> RedisFrame<'a> {
+    /// Convert frame to value (copy data).
+    /// Error sent by Redis is converted to RedisError.
+    pub fn to_value(&self) -> Result<RedisValue, RedisError> {
+        let value = match self {
+            RedisFrame::SimpleString(s) => RedisValue::String(to_string(s)),
+            RedisFrame::Error(e) | RedisFrame::BlobError(e) => {
+                return Err(error_from_message(to_string(e)))
+            }
+            RedisFrame::Integer(i) => RedisValue::Integer(*i),
+            RedisFrame::BulkString(s) => RedisValue::BulkString(s.to_vec()),
+            RedisFrame::Nil => RedisValue::Nil,
+            RedisFrame::NilArray => RedisValue::NilArray,
+            RedisFrame::Array(frames) => RedisValue::Array(to_values(frames)?),
+            RedisFrame::Null => RedisValue::Null,
+            RedisFrame::Double(d) => RedisValue::Double(to_string(d)),
+            RedisFrame::Boolean(b) => RedisValue::Boolean(*b),
+            RedisFrame::VerbatimString(format, data) => {
+                RedisValue::VerbatimString(to_string(format), data.to_vec())
+            }
+            RedisFrame::BigNumber(n) => RedisValue::BigNumber(to_string(n)),
+            RedisFrame::Map(pairs) => RedisValue::Map(to_pairs(pairs)?),
+            RedisFrame::Set(frames) => RedisValue::Set(to_values(frames)?),
+            RedisFrame::Attribute(pairs, frame) => {
+                RedisValue::Attribute(to_pairs(pairs)?, Box::new(frame.to_value()?))
+            }
+            RedisFrame::Push(frames) => RedisValue::Push(to_values(frames)?),
+        };
+
+        Ok(value)
     }
 }
 
+/// Convert bytes to string.
+fn to_string(data: &[u8]) -> String {
+    String::from_utf8_lossy(data).to_string()
+}
+
+/// Convert frames to values.
+fn to_values(frames: &[RedisFrame]) -> Result<Vec<RedisValue>, RedisError> {
+    frames.iter().map(|f| f.to_value()).collect()
+}
+
+/// Convert pairs of frames to pairs of values.
+fn to_pairs(
+    pairs: &[(RedisFrame, RedisFrame)],
+) -> Result<Vec<(RedisValue, RedisValue)>, RedisError> {
+    pairs
+        .iter()
+        .map(|(k, v)| Ok((k.to_value()?, v.to_value()?)))
+        .collect()
+}
+
 /// Return type of data.
 fn get_type(data: u8) -> Result<RedisType, RedisError> {
     match data {
@@ -41,237 +137,615 @@ fn get_type(data: u8) -> Result<RedisType, RedisError> {
         REDIS_TYPE_ARRAY => Ok(RedisType::Array),
         REDIS_TYPE_ERROR => Ok(RedisType::Error),
         REDIS_TYPE_INTEGER => Ok(RedisType::Integer),
+        REDIS_TYPE_NULL => Ok(RedisType::Null),
+        REDIS_TYPE_DOUBLE => Ok(RedisType::Double),
+        REDIS_TYPE_BOOLEAN => Ok(RedisType::Boolean),
+        REDIS_TYPE_BLOB_ERROR => Ok(RedisType::BlobError),
+        REDIS_TYPE_VERBATIM_STRING => Ok(RedisType::VerbatimString),
+        REDIS_TYPE_BIG_NUMBER => Ok(RedisType::BigNumber),
+        REDIS_TYPE_MAP => Ok(RedisType::Map),
+        REDIS_TYPE_SET => Ok(RedisType::Set),
+        REDIS_TYPE_ATTRIBUTE => Ok(RedisType::Attribute),
+        REDIS_TYPE_PUSH => Ok(RedisType::Push),
         e => Err(RedisError::from_message(&format!("Unknow type '{}'", e))),
     }
 }
 
-/// Read byte until "\r\n" and convert to string.
-fn read_string_from_stream(stream: &mut Box<dyn RedisStream>) -> Result<String, RedisError> {
-    let data = match stream.get_until("\r\n".as_bytes()) {
-        Ok(a) => a,
-        Err(e) => return Err(RedisError::from_io_error(e)),
-    };
-
-    let value = unsafe { std::str::from_utf8_unchecked(&data[..data.len() - 2]) };
+/// Return type of data.
+fn what_is(data: &[u8]) -> String {
+    match data[0] {
+        REDIS_TYPE_STRING => String::from("String"),
+        REDIS_TYPE_BULK_STRING => String::from("BulkString"),
+        REDIS_TYPE_ARRAY => String::from("Array"),
+        REDIS_TYPE_ERROR => String::from("Error"),
+        REDIS_TYPE_INTEGER => String::from("Integer"),
+        REDIS_TYPE_NULL => String::from("Null"),
+        REDIS_TYPE_DOUBLE => String::from("Double"),
+        REDIS_TYPE_BOOLEAN => String::from("Boolean"),
+        REDIS_TYPE_BLOB_ERROR => String::from("BlobError"),
+        REDIS_TYPE_VERBATIM_STRING => String::from("VerbatimString"),
+        REDIS_TYPE_BIG_NUMBER => String::from("BigNumber"),
+        REDIS_TYPE_MAP => String::from("Map"),
+        REDIS_TYPE_SET => String::from("Set"),
+        REDIS_TYPE_ATTRIBUTE => String::from("Attribute"),
+        REDIS_TYPE_PUSH => String::from("Push"),
+        e => format!("Unknow '0x{}'", e),
+    }
+}
 
-    Ok(String::from(value))
+/// Read a complete value from stream.
+/// If set, value must be of expected type (or an error sent by Redis).
+fn read_value(
+    stream: &mut Box<dyn RedisStream>,
+    expected: Option<(u8, &str)>,
+) -> Result<RedisValue, RedisError> {
+    read_frame_with(stream, expected, |frame| frame.to_value())
 }
 
-/// Read only array size.
-fn read_array_size(stream: &mut Box<dyn RedisStream>) -> Result<isize, RedisError> {
-    let header = check_error(stream)?;
+/// Read a complete frame from stream and convert it.
+/// If set, frame must be of expected type (or an error sent by Redis).
+/// Data are consumed only when frame is complete: if no data are available in the middle of a
+/// frame, NoDataAvailable is returned and next call resumes reading.
+fn read_frame_with<T, F>(
+    stream: &mut Box<dyn RedisStream>,
+    expected: Option<(u8, &str)>,
+    convert: F,
+) -> Result<T, RedisError>
+where
+    F: Fn(&RedisFrame) -> Result<T, RedisError>,
+{
+    loop {
+        if let (Some(&header), Some((expected, what))) = (stream.buffer().first(), expected) {
+            if header != REDIS_TYPE_ERROR && header != REDIS_TYPE_BLOB_ERROR && header != expected {
+                return Err(RedisError::from_message(&format!(
+                    "Not {} but a {}",
+                    what,
+                    what_is(&[header])
+                )));
+            }
+        }
+
+        if let Some((frame, size)) = parse_frame(stream.buffer())? {
+            let value = convert(&frame);
+
+            stream.consume(size);
+
+            return value;
+        }
 
-    // First char must be '*'
-    if header != REDIS_TYPE_ARRAY {
-        return Err(RedisError::from_message(&format!(
-            "Not an array but a {}",
-            what_is(&[header])
-        )));
+        match stream.fill() {
+            Ok(0) => return Err(RedisError::from_no_data()),
+            Ok(_) => {}
+            Err(e) => return Err(RedisError::from_io_error(e)),
+        }
     }
+}
 
-    let size = read_string_from_stream(stream)?;
+/// Read strict string, not bulk string.
+/// Must contain '\r\n' at end (but not include in result).
+pub fn read_strict_string(stream: &mut Box<dyn RedisStream>) -> Result<String, RedisError> {
+    match read_value(stream, Some((REDIS_TYPE_STRING, "a string")))? {
+        RedisValue::String(s) => Ok(s),
+        v => Err(RedisError::from_message(&format!("Not a string: {:?}", v))),
+    }
+}
 
-    match size.parse::<isize>() {
-        Ok(i) => Ok(i),
-        Err(e) => Err(RedisError::from_message(&format!(
-            "Invalid integer: {} in '{}'",
-            e, size
+/// Read integer value.
+pub fn read_integer(stream: &mut Box<dyn RedisStream>) -> Result<isize, RedisError> {
+    match read_value(stream, Some((REDIS_TYPE_INTEGER, "an integer")))? {
+        RedisValue::Integer(i) => Ok(i),
+        v => Err(RedisError::from_message(&format!(
+            "Not an integer: {:?}",
+            v
         ))),
     }
 }
 
-/// Return type of data.
-fn what_is(data: &[u8]) -> String {
-    match data[0] {
-        REDIS_TYPE_STRING => String::from("String"),
-        REDIS_TYPE_BULK_STRING => String::from("BulkString"),
-        REDIS_TYPE_ARRAY => String::from("Array"),
-        REDIS_TYPE_ERROR => String::from("Error"),
-        REDIS_TYPE_INTEGER => String::from("Integer"),
-        e => format!("Unknow '0x{}'", e),
+/// Read bulk string.
+/// Bulk string can contain non printable char.
+pub fn read_bulk_string(stream: &mut Box<dyn RedisStream>) -> Result<Option<Vec<u8>>, RedisError> {
+    match read_value(stream, Some((REDIS_TYPE_BULK_STRING, "a bulk string")))? {
+        RedisValue::BulkString(s) => Ok(Some(s)),
+        RedisValue::Nil => Ok(None),
+        v => Err(RedisError::from_message(&format!(
+            "Not a bulk string: {:?}",
+            v
+        ))),
     }
 }
 
-/// Read error message.
-fn read_error_from_stream(stream: &mut Box<dyn RedisStream>) -> RedisError {
-    let mut message = match read_string_from_stream(stream) {
-        Ok(m) => m,
-        Err(e) => return e,
-    };
-
-    let position = match message.find(' ') {
-        Some(p) => p,
-        None => return RedisError::from_message(&message),
-    };
+/// Read an array.
+pub fn read_array(stream: &mut Box<dyn RedisStream>) -> Result<RedisValue, RedisError> {
+    read_value(stream, Some((REDIS_TYPE_ARRAY, "an array")))
+}
 
-    let explain = message.split_off(position);
+/// Reply of EXEC: result of each command, or None if transaction was aborted by WATCH.
+pub type TransactionReply = Option<Vec<Result<RedisValue, RedisError>>>;
 
-    RedisError::from_redis(&message, &explain)
+/// Read reply of EXEC.
+/// Error of a command doesn't fail other commands of transaction.
+pub fn read_transaction_reply(
+    stream: &mut Box<dyn RedisStream>,
+) -> Result<TransactionReply, RedisError> {
+    read_frame_with(stream, None, |frame| match frame {
+        RedisFrame::Array(frames) => Ok(Some(frames.iter().map(RedisFrame::to_value).collect())),
+        RedisFrame::NilArray | RedisFrame::Null => Ok(None),
+        frame => Err(RedisError::from_unexpected_reply(&frame.to_value()?)),
+    })
 }
 
-/// Check if message contains error.
-/// If no error return Ok(u8) otherwise return Err(RedisError).
-/// The u8 is character checked to be error.
-fn check_error(stream: &mut Box<dyn RedisStream>) -> Result<u8, RedisError> {
-    // First char must be '$'
-    let c = get_byte(stream)?;
+/// Read a reply of any type.
+/// Error sent by Redis is returned as error.
+pub fn read_reply(stream: &mut Box<dyn RedisStream>) -> Result<RedisValue, RedisError> {
+    read_value(stream, None)
+}
 
-    if c != REDIS_TYPE_ERROR {
-        return Ok(c);
+/// Search "\r\n" in buffer from start.
+/// Return position of '\r' or None if line is incomplete.
+fn find_end_of_line(buf: &[u8], start: usize) -> Option<usize> {
+    if start >= buf.len() {
+        return None;
     }
 
-    Err(read_error_from_stream(stream))
+    buf[start..]
+        .windows(2)
+        .position(|w| w == b"\r\n")
+        .map(|p| start + p)
 }
 
-/// Read byte until "\r\n" and convert to integer.
-fn read_integer_from_stream(stream: &mut Box<dyn RedisStream>) -> Result<isize, RedisError> {
-    let size = read_string_from_stream(stream)?;
+/// Read a line from buffer and convert it to integer.
+/// Return integer and position after "\r\n".
+fn parse_integer_line(buf: &[u8], start: usize) -> Result<Option<(isize, usize)>, RedisError> {
+    let end = match find_end_of_line(buf, start) {
+        Some(e) => e,
+        None => return Ok(None),
+    };
+
+    let value = String::from_utf8_lossy(&buf[start..end]);
 
-    match size.parse::<isize>() {
-        Ok(i) => Ok(i),
+    match value.parse::<isize>() {
+        Ok(i) => Ok(Some((i, end + 2))),
         Err(e) => Err(RedisError::from_message(&format!(
             "Invalid integer: {} in '{}'",
-            e, size
+            e, value
         ))),
     }
 }
 
-/// Read all byte and convert to [u8].
-fn read_bulk_string_from_stream(
-    stream: &mut Box<dyn RedisStream>,
-) -> Result<Option<Vec<u8>>, RedisError> {
-    // Get first part: the size
-    let size = read_string_from_stream(stream)?;
-
-    let size = match size.parse::<isize>() {
-        Ok(i) => i,
-        Err(e) => {
-            return Err(RedisError::from_message(&format!(
-                "Invalid integer: {} in '{}'",
-                e, size
-            )))
-        }
+/// Check length read from buffer.
+/// Return None for null (-1), error if length is negative or greater than max.
+fn check_length(size: isize, max: isize) -> Result<Option<usize>, RedisError> {
+    match size {
+        -1 => Ok(None),
+        s if (0..=max).contains(&s) => Ok(Some(s as usize)),
+        s => Err(RedisError::from_message(&format!(
+            "Protocol error: invalid length {}",
+            s
+        ))),
+    }
+}
+
+/// Check count of pairs (map, attributes) and return count of frames: keys, values and extra
+/// frames (e.g. value described by attributes).
+fn check_pairs_length(size: isize, extra: usize) -> Result<usize, RedisError> {
+    match check_length(size, MAX_AGGREGATE_LENGTH)? {
+        Some(size) => size
+            .checked_mul(2)
+            .and_then(|s| s.checked_add(extra))
+            .ok_or_else(|| {
+                RedisError::from_message(&format!("Protocol error: invalid length {}", size))
+            }),
+        None => Err(RedisError::from_message(
+            "Protocol error: invalid length -1",
+        )),
+    }
+}
+
+/// Read a line from buffer.
+/// Return line and position after "\r\n".
+fn parse_line(buf: &[u8], start: usize) -> Option<(&[u8], usize)> {
+    find_end_of_line(buf, start).map(|end| (&buf[start..end], end + 2))
+}
+
+/// Data (None if null) and position after data.
+type ParsedBlob<'a> = (Option<&'a [u8]>, usize);
+
+/// Frames and position after last frame.
+type ParsedFrames<'a> = (Vec<RedisFrame<'a>>, usize);
+
+/// Pairs of key/value and position after last value.
+type ParsedPairs<'a> = (Vec<(RedisFrame<'a>, RedisFrame<'a>)>, usize);
+
+/// Read size and data (bulk string, verbatim string, blob error).
+fn parse_blob(buf: &[u8], start: usize) -> Result<Option<ParsedBlob<'_>>, RedisError> {
+    let (size, next) = match parse_integer_line(buf, start)? {
+        Some(s) => s,
+        None => return Ok(None),
     };
 
     // Null string
-    if size < 0 {
+    let size = match check_length(size, MAX_BULK_LENGTH)? {
+        Some(s) => s,
+        None => return Ok(Some((None, next))),
+    };
+
+    let end = next + size;
+
+    // Data and '\r\n'
+    if buf.len() < end + 2 {
         return Ok(None);
     }
 
-    let data = match stream.get_data(size as usize) {
-        Ok(data) => data,
-        Err(e) => return Err(RedisError::from_io_error(e)),
+    Ok(Some((Some(&buf[next..end]), end + 2)))
+}
+
+/// Read count frames.
+fn parse_frames(
+    buf: &[u8],
+    start: usize,
+    count: usize,
+) -> Result<Option<ParsedFrames<'_>>, RedisError> {
+    // Count comes from buffer, capacity is bounded by bytes left
+    let mut result: Vec<RedisFrame> =
+        Vec::with_capacity(count.min(buf.len().saturating_sub(start)));
+    let mut next = start;
+
+    for _ in 0..count {
+        match parse_frame_at(buf, next)? {
+            Some((frame, n)) => {
+                result.push(frame);
+                next = n;
+            }
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some((result, next)))
+}
+
+/// Read pairs of key/value (map, attributes).
+fn parse_pairs(buf: &[u8], start: usize) -> Result<Option<ParsedPairs<'_>>, RedisError> {
+    let (size, next) = match parse_integer_line(buf, start)? {
+        Some(s) => s,
+        None => return Ok(None),
     };
 
-    // Skip last '\r\n'
-    if let Err(e) = stream.get_data(2) {
-        return Err(RedisError::from_io_error(e));
+    let (frames, next) = match parse_frames(buf, next, check_pairs_length(size, 0)?)? {
+        Some(f) => f,
+        None => return Ok(None),
+    };
+
+    let mut pairs = Vec::with_capacity(frames.len() / 2);
+    let mut frames = frames.into_iter();
+
+    while let (Some(key), Some(value)) = (frames.next(), frames.next()) {
+        pairs.push((key, value));
     }
 
-    Ok(Some(data))
+    Ok(Some((pairs, next)))
 }
 
-/// Read an array.
-fn read_array_from_stream(
-    stream: &mut Box<dyn RedisStream>,
-    array_size: usize,
-) -> Result<RedisValue, RedisError> {
-    let mut result: Vec<RedisValue> = Vec::with_capacity(array_size);
+/// Convert error message sent by Redis ("CODE explain") to error.
+fn error_from_message(message: String) -> RedisError {
+    match message.split_once(' ') {
+        Some((code, explain)) => RedisError::from_redis(code, explain),
+        None => RedisError::from_message(&message),
+    }
+}
 
-    for _ in 0..array_size {
-        let data_type = get_byte(stream)?;
-        let data_type = get_type(data_type)?;
+/// Read a frame from buffer at start position.
+/// Return frame and position after frame, or None if buffer doesn't contain complete frame.
+fn parse_frame_at(buf: &[u8], start: usize) -> Result<Option<(RedisFrame<'_>, usize)>, RedisError> {
+    let data_type = match buf.get(start) {
+        Some(c) => get_type(*c)?,
+        None => return Ok(None),
+    };
 
-        match data_type {
-            RedisType::Integer => {
-                let s = read_integer_from_stream(stream)?;
-                result.push(RedisValue::Integer(s));
-            }
-            RedisType::BulkString => {
-                let s = read_bulk_string_from_stream(stream)?;
+    let start = start + 1;
 
-                match s {
-                    Some(s) => result.push(RedisValue::BulkString(s)),
-                    None => result.push(RedisValue::Nil),
-                }
+    let frame = match data_type {
+        RedisType::Integer => {
+            return Ok(
+                parse_integer_line(buf, start)?.map(|(i, next)| (RedisFrame::Integer(i), next))
+            )
+        }
+        RedisType::String => {
+            parse_line(buf, start).map(|(s, next)| (RedisFrame::SimpleString(s), next))
+        }
+        RedisType::Error => parse_line(buf, start).map(|(s, next)| (RedisFrame::Error(s), next)),
+        RedisType::Double => parse_line(buf, start).map(|(s, next)| (RedisFrame::Double(s), next)),
+        RedisType::BigNumber => {
+            parse_line(buf, start).map(|(s, next)| (RedisFrame::BigNumber(s), next))
+        }
+        RedisType::Null => parse_line(buf, start).map(|(_, next)| (RedisFrame::Null, next)),
+        RedisType::Boolean => match parse_line(buf, start) {
+            Some((b"t", next)) => Some((RedisFrame::Boolean(true), next)),
+            Some((b"f", next)) => Some((RedisFrame::Boolean(false), next)),
+            Some((b, _)) => {
+                return Err(RedisError::from_message(&format!(
+                    "Invalid boolean: '{}'",
+                    to_string(b)
+                )))
             }
-            RedisType::String => {
-                let s = read_string_from_stream(stream)?;
-                result.push(RedisValue::String(s));
+            None => None,
+        },
+        RedisType::BulkString => parse_blob(buf, start)?.map(|(data, next)| match data {
+            Some(data) => (RedisFrame::BulkString(data), next),
+            None => (RedisFrame::Nil, next),
+        }),
+        RedisType::BlobError => parse_blob(buf, start)?
+            .map(|(data, next)| (RedisFrame::BlobError(data.unwrap_or_default()), next)),
+        RedisType::VerbatimString => match parse_blob(buf, start)? {
+            // Format is 3 bytes followed by ':'
+            Some((Some(data), next)) if data.len() >= 4 && data[3] == b':' => {
+                Some((RedisFrame::VerbatimString(&data[..3], &data[4..]), next))
             }
-            RedisType::Array => {
-                let size = read_integer_from_stream(stream)?;
+            Some(_) => return Err(RedisError::from_message("Invalid verbatim string")),
+            None => None,
+        },
+        RedisType::Array | RedisType::Set | RedisType::Push => {
+            let (size, next) = match parse_integer_line(buf, start)? {
+                Some(s) => s,
+                None => return Ok(None),
+            };
+
+            // Null array
+            let size = match check_length(size, MAX_AGGREGATE_LENGTH)? {
+                Some(s) => s,
+                None => return Ok(Some((RedisFrame::NilArray, next))),
+            };
+
+            parse_frames(buf, next, size)?.map(|(frames, next)| {
+                let frame = match data_type {
+                    RedisType::Set => RedisFrame::Set(frames),
+                    RedisType::Push => RedisFrame::Push(frames),
+                    _ => RedisFrame::Array(frames),
+                };
+
+                (frame, next)
+            })
+        }
+        RedisType::Map => {
+            parse_pairs(buf, start)?.map(|(pairs, next)| (RedisFrame::Map(pairs), next))
+        }
+        RedisType::Attribute => {
+            let (pairs, next) = match parse_pairs(buf, start)? {
+                Some(p) => p,
+                None => return Ok(None),
+            };
+
+            // Attributes are followed by frame they describe
+            parse_frame_at(buf, next)?
+                .map(|(frame, next)| (RedisFrame::Attribute(pairs, Box::new(frame)), next))
+        }
+    };
 
-                if size < 0 {
-                    result.push(RedisValue::Nil);
-                } else {
-                    result.push(read_array_from_stream(stream, array_size as usize)?);
-                }
-            }
-            // Normally, never happen
-            RedisType::Error => return Err(read_error_from_stream(stream)),
+    Ok(frame)
+}
+
+/// Decode first frame of buffer without copy.
+/// Return frame and size of frame in buffer, or None if buffer doesn't contain complete frame
+/// (need more data).
+pub fn parse_frame(buf: &[u8]) -> Result<Option<(RedisFrame<'_>, usize)>, RedisError> {
+    parse_frame_at(buf, 0)
+}
+
+/// Return size of first value in buffer, without decode it.
+/// Error sent by Redis is a value like other.
+/// Return None if buffer doesn't contain complete value.
+pub fn value_length(buf: &[u8]) -> Result<Option<usize>, RedisError> {
+    value_length_at(buf, 0)
+}
+
+/// Return position after count values.
+fn values_length_at(buf: &[u8], start: usize, count: usize) -> Result<Option<usize>, RedisError> {
+    let mut next = start;
+
+    for _ in 0..count {
+        match value_length_at(buf, next)? {
+            Some(n) => next = n,
+            None => return Ok(None),
         }
     }
 
-    Ok(RedisValue::Array(result))
+    Ok(Some(next))
 }
 
-/// Read strict string, not bulk string.
-/// Must contain '\r\n' at end (but not include in result).
-pub fn read_strict_string(stream: &mut Box<dyn RedisStream>) -> Result<String, RedisError> {
-    let header = check_error(stream)?;
+/// Return position after value start at start position.
+fn value_length_at(buf: &[u8], start: usize) -> Result<Option<usize>, RedisError> {
+    let data_type = match buf.get(start) {
+        Some(c) => get_type(*c)?,
+        None => return Ok(None),
+    };
 
-    if header != REDIS_TYPE_STRING {
-        return Err(RedisError::from_message(&format!(
-            "Not a string but a {}",
-            what_is(&[header])
-        )));
+    let start = start + 1;
+
+    match data_type {
+        RedisType::Integer
+        | RedisType::String
+        | RedisType::Error
+        | RedisType::Null
+        | RedisType::Double
+        | RedisType::Boolean
+        | RedisType::BigNumber => Ok(find_end_of_line(buf, start).map(|end| end + 2)),
+        RedisType::BulkString | RedisType::BlobError | RedisType::VerbatimString => {
+            Ok(parse_blob(buf, start)?.map(|(_, next)| next))
+        }
+        RedisType::Array | RedisType::Set | RedisType::Push => {
+            match parse_integer_line(buf, start)? {
+                Some((size, next)) => match check_length(size, MAX_AGGREGATE_LENGTH)? {
+                    Some(size) => values_length_at(buf, next, size),
+                    None => Ok(Some(next)),
+                },
+                None => Ok(None),
+            }
+        }
+        RedisType::Map => match parse_integer_line(buf, start)? {
+            Some((size, next)) => values_length_at(buf, next, check_pairs_length(size, 0)?),
+            None => Ok(None),
+        },
+        RedisType::Attribute => match parse_integer_line(buf, start)? {
+            // Attributes and value they describe
+            Some((size, next)) => values_length_at(buf, next, check_pairs_length(size, 1)?),
+            None => Ok(None),
+        },
     }
+}
 
-    read_string_from_stream(stream)
+/// Decode first value of buffer.
+/// Return value and size of value in buffer, or None if buffer doesn't contain complete value.
+pub fn parse_value(buf: &[u8]) -> Result<Option<(RedisValue, usize)>, RedisError> {
+    match parse_frame(buf)? {
+        Some((frame, size)) => Ok(Some((frame.to_value()?, size))),
+        None => Ok(None),
+    }
 }
 
-/// Read integer value.
-#[allow(dead_code)]
-pub fn read_integer(stream: &mut Box<dyn RedisStream>) -> Result<isize, RedisError> {
-    let header = check_error(stream)?;
-
-    // First char must be ':'
-    if header != REDIS_TYPE_INTEGER {
-        return Err(RedisError::from_message(&format!(
-            "Not an integer but a {}",
-            what_is(&[header])
-        )));
+/// Arguments of a command and size of command in buffer.
+pub type ParsedCommand = (Vec<Vec<u8>>, usize);
+
+/// Progress of a command partially received from client.
+/// Elements already read are kept, so they are not parsed again when more data arrive.
+#[derive(Debug, Default)]
+pub struct CommandCursor {
+    /// Arguments already read.
+    args: Vec<Vec<u8>>,
+    /// Count of arguments of array, None if header is not read yet.
+    count: Option<usize>,
+    /// Position after last element read, or bytes searched for end of inline command.
+    position: usize,
+}
+
+/// Read one command sent by client.
+/// Command can be an array of bulk string or an inline command (e.g. with telnet).
+/// Return arguments and size of command in buffer, or None if buffer doesn't contain complete
+/// command.
+pub fn parse_command(buf: &[u8]) -> Result<Option<ParsedCommand>, RedisError> {
+    parse_command_from(buf, &mut CommandCursor::default())
+}
+
+/// Read one command sent by client, starting from progress of previous call.
+/// Buffer must start with same command than previous call, cursor is reset when command is
+/// complete or invalid.
+pub fn parse_command_from(
+    buf: &[u8],
+    cursor: &mut CommandCursor,
+) -> Result<Option<ParsedCommand>, RedisError> {
+    let result = match buf.first() {
+        None => return Ok(None),
+        Some(&REDIS_TYPE_ARRAY) => parse_multibulk_command(buf, cursor),
+        Some(_) => parse_inline_command(buf, cursor),
+    };
+
+    if !matches!(result, Ok(None)) {
+        *cursor = CommandCursor::default();
     }
 
-    read_integer_from_stream(stream)
+    result
 }
 
-/// Read bulk string.
-/// Bulk string can contain non printable char.
-#[allow(dead_code)]
-pub fn read_bulk_string(stream: &mut Box<dyn RedisStream>) -> Result<Option<Vec<u8>>, RedisError> {
-    let header = check_error(stream)?;
-
-    // First char must be '$'
-    if header != REDIS_TYPE_BULK_STRING {
-        return Err(RedisError::from_message(&format!(
-            "Not a bulk string but a {}",
-            what_is(&[header])
-        )));
+/// Read a command sent as array of bulk string.
+fn parse_multibulk_command(
+    buf: &[u8],
+    cursor: &mut CommandCursor,
+) -> Result<Option<ParsedCommand>, RedisError> {
+    let count = match cursor.count {
+        Some(c) => c,
+        None => {
+            let (count, next) = match parse_integer_line(buf, 1)? {
+                Some(c) => c,
+                None => {
+                    check_line_length(buf, 0, "Protocol error: too big mbulk count string")?;
+                    return Ok(None);
+                }
+            };
+
+            // Check count before read arguments, like Redis
+            let count = match count {
+                -1 | 0 => return Ok(Some((Vec::new(), next))),
+                c if (1..=MAX_COMMAND_ARGUMENTS).contains(&c) => c as usize,
+                _ => {
+                    return Err(RedisError::from_message(
+                        "Protocol error: invalid multibulk length",
+                    ))
+                }
+            };
+
+            cursor.args = Vec::with_capacity(count.min(buf.len() - next));
+            cursor.count = Some(count);
+            cursor.position = next;
+
+            count
+        }
+    };
+
+    while cursor.args.len() < count {
+        match buf.get(cursor.position) {
+            None => return Ok(None),
+            Some(&REDIS_TYPE_BULK_STRING) => {}
+            Some(c) => {
+                return Err(RedisError::from_message(&format!(
+                    "Protocol error: expected '$', got '{}'",
+                    *c as char
+                )))
+            }
+        }
+
+        match parse_blob(buf, cursor.position + 1)? {
+            Some((Some(data), next)) => {
+                cursor.args.push(data.to_vec());
+                cursor.position = next;
+            }
+            Some((None, _)) => {
+                return Err(RedisError::from_message(
+                    "Protocol error: invalid bulk length",
+                ))
+            }
+            None => {
+                check_line_length(
+                    buf,
+                    cursor.position,
+                    "Protocol error: too big bulk count string",
+                )?;
+                return Ok(None);
+            }
+        }
     }
 
-    read_bulk_string_from_stream(stream)
+    Ok(Some((std::mem::take(&mut cursor.args), cursor.position)))
 }
 
-/// Read an array.
-#[allow(dead_code)]
-pub fn read_array(stream: &mut Box<dyn RedisStream>) -> Result<RedisValue, RedisError> {
-    let array_size = read_array_size(stream)?;
+/// Read an inline command, only new data are searched for end of line.
+fn parse_inline_command(
+    buf: &[u8],
+    cursor: &mut CommandCursor,
+) -> Result<Option<ParsedCommand>, RedisError> {
+    match buf[cursor.position..].iter().position(|c| *c == b'\n') {
+        Some(end) => {
+            let end = cursor.position + end;
+            let args = buf[..end]
+                .split(|c| c.is_ascii_whitespace())
+                .filter(|a| !a.is_empty())
+                .map(|a| a.to_vec())
+                .collect();
+
+            Ok(Some((args, end + 1)))
+        }
+        None if buf.len() > MAX_INLINE_LENGTH => Err(RedisError::from_message(
+            "Protocol error: too big inline request",
+        )),
+        None => {
+            cursor.position = buf.len();
+            Ok(None)
+        }
+    }
+}
 
-    if array_size < 0 {
-        Ok(RedisValue::Nil)
+/// Return error if line starting at start is incomplete and already too long, like Redis.
+fn check_line_length(buf: &[u8], start: usize, message: &str) -> Result<(), RedisError> {
+    if buf.len() - start > MAX_INLINE_LENGTH && find_end_of_line(buf, start).is_none() {
+        Err(RedisError::from_message(message))
     } else {
-        read_array_from_stream(stream, array_size as usize)
+        Ok(())
     }
 }
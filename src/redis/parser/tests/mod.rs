@@ -1,4 +1,7 @@
-use crate::redis::parser::{read_array, read_bulk_string, read_integer, read_strict_string};
+use crate::redis::parser::{
+    parse_command, parse_command_from, parse_frame, parse_value, read_array, read_bulk_string,
+    read_integer, read_strict_string, value_length, CommandCursor, RedisFrame,
+};
 use crate::redis::stream::tests::TestRedisStream;
 use crate::redis::stream::RedisStream;
 use crate::redis::types::{
@@ -198,7 +201,7 @@ fn read_integer_error() {
         Ok(_) => panic!("Must be return error!"),
         Err(e) => {
             assert_eq!(e.kind(), ErrorKind::ResponseError);
-            assert_eq!(e.message(), " Hello");
+            assert_eq!(e.message(), "Hello");
         }
     }
 }
@@ -312,7 +315,7 @@ fn read_bulk_string_error() {
         Ok(_) => panic!("Must be return error!"),
         Err(e) => {
             assert_eq!(e.kind(), ErrorKind::ExecAbortError);
-            assert_eq!(e.message(), " Hello");
+            assert_eq!(e.message(), "Hello");
         }
     }
 }
@@ -432,7 +435,7 @@ fn read_array_error() {
         Ok(_) => panic!("Must be return error!"),
         Err(e) => {
             assert_eq!(e.kind(), ErrorKind::BusyLoadingError);
-            assert_eq!(e.message(), " Hello");
+            assert_eq!(e.message(), "Hello");
         }
     }
 }
@@ -464,7 +467,236 @@ fn read_array_error2() {
         Ok(_) => panic!("Must be return error!"),
         Err(e) => {
             assert_eq!(e.kind(), ErrorKind::NoScriptError);
-            assert_eq!(e.message(), " Hello");
+            assert_eq!(e.message(), "Hello");
         }
     }
 }
+
+#[test]
+fn parse_value_incomplete() -> Result<(), RedisError> {
+    assert_eq!(parse_value(b"")?, None);
+    assert_eq!(parse_value(b"$5\r\nHel")?, None);
+    assert_eq!(parse_value(b"*2\r\n:1\r\n")?, None);
+
+    Ok(())
+}
+
+#[test]
+fn parse_value_ok() -> Result<(), RedisError> {
+    assert_eq!(
+        parse_value(b"*3\r\n$5\r\nHello\r\n:12\r\n$-1\r\n+OK")?,
+        Some((
+            RedisValue::Array(vec![
+                RedisValue::BulkString(b"Hello".to_vec()),
+                RedisValue::Integer(12),
+                RedisValue::Nil,
+            ]),
+            25
+        ))
+    );
+    assert_eq!(parse_value(b"*-1\r\n")?, Some((RedisValue::NilArray, 5)));
+
+    Ok(())
+}
+
+#[test]
+fn value_length_ok() -> Result<(), RedisError> {
+    assert_eq!(value_length(b"-ERR bad\r\n:1\r\n")?, Some(10));
+    assert_eq!(value_length(b"*2\r\n*1\r\n+OK\r\n$2\r\nab\r\n")?, Some(21));
+    assert_eq!(value_length(b"*2\r\n*1\r\n+OK\r\n$2\r\na")?, None);
+
+    Ok(())
+}
+
+#[test]
+fn parse_command_ok() -> Result<(), RedisError> {
+    assert_eq!(
+        parse_command(b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n")?,
+        Some((vec![b"GET".to_vec(), b"a".to_vec()], 20))
+    );
+    assert_eq!(
+        parse_command(b"SET  a b\r\nGET")?,
+        Some((vec![b"SET".to_vec(), b"a".to_vec(), b"b".to_vec()], 10))
+    );
+    assert_eq!(parse_command(b"GET a")?, None);
+
+    Ok(())
+}
+
+#[test]
+fn parse_command_from_cursor() -> Result<(), RedisError> {
+    let mut cursor = CommandCursor::default();
+    let command = b"*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\nb\r\n";
+
+    for end in 1..command.len() {
+        assert_eq!(parse_command_from(&command[..end], &mut cursor)?, None);
+    }
+
+    assert_eq!(
+        parse_command_from(command, &mut cursor)?,
+        Some((vec![b"SET".to_vec(), b"a".to_vec(), b"b".to_vec()], 27))
+    );
+    // Cursor is reset for next command
+    assert_eq!(parse_command_from(b"GET a", &mut cursor)?, None);
+    assert_eq!(
+        parse_command_from(b"GET a\r\n", &mut cursor)?,
+        Some((vec![b"GET".to_vec(), b"a".to_vec()], 7))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn parse_command_invalid() {
+    assert!(parse_command(b"*1\r\n:1\r\n").is_err());
+    assert!(parse_command(b"*1\r\n$-1\r\n").is_err());
+    assert!(parse_command(&vec![b'a'; 64 * 1024 + 1]).is_err());
+
+    let mut header = b"*1\r\n$".to_vec();
+    header.resize(64 * 1024 + 10, b'1');
+    assert!(parse_command(&header).is_err());
+}
+
+#[test]
+fn parse_invalid_length() -> Result<(), RedisError> {
+    assert!(parse_command(b"*9223372036854775807\r\n").is_err());
+    assert!(parse_command(b"*1048577\r\n").is_err());
+    assert!(parse_value(b"*9223372036854775807\r\n").is_err());
+    assert!(parse_value(b"$9223372036854775807\r\n").is_err());
+    assert!(parse_value(b"*-2\r\n").is_err());
+    assert!(value_length(b"*9223372036854775807\r\n").is_err());
+    assert!(parse_value(b"%9223372036854775807\r\n").is_err());
+    assert!(value_length(b"%9223372036854775807\r\n").is_err());
+    assert!(value_length(b"|4611686018427387904\r\n").is_err());
+    assert!(parse_value(b"%-2\r\n").is_err());
+    // Count is valid but frames are not received yet
+    assert_eq!(parse_value(b"*1048576\r\n:1\r\n")?, None);
+
+    Ok(())
+}
+
+#[test]
+fn parse_value_resp3() -> Result<(), RedisError> {
+    assert_eq!(parse_value(b"_\r\n")?, Some((RedisValue::Null, 3)));
+    assert_eq!(
+        parse_value(b",3.14\r\n")?,
+        Some((RedisValue::Double(String::from("3.14")), 7))
+    );
+    assert_eq!(
+        parse_value(b"#t\r\n")?,
+        Some((RedisValue::Boolean(true), 4))
+    );
+    assert_eq!(
+        parse_value(b"(12345678901234567890\r\n")?,
+        Some((
+            RedisValue::BigNumber(String::from("12345678901234567890")),
+            23
+        ))
+    );
+    assert_eq!(
+        parse_value(b"=7\r\ntxt:abc\r\n")?,
+        Some((
+            RedisValue::VerbatimString(String::from("txt"), b"abc".to_vec()),
+            13
+        ))
+    );
+    assert_eq!(
+        parse_value(b"%1\r\n+a\r\n:1\r\n")?,
+        Some((
+            RedisValue::Map(vec![(
+                RedisValue::String(String::from("a")),
+                RedisValue::Integer(1)
+            )]),
+            12
+        ))
+    );
+    assert_eq!(
+        parse_value(b"~1\r\n:1\r\n")?,
+        Some((RedisValue::Set(vec![RedisValue::Integer(1)]), 8))
+    );
+    assert_eq!(
+        parse_value(b">2\r\n+a\r\n:1\r\n")?,
+        Some((
+            RedisValue::Push(vec![
+                RedisValue::String(String::from("a")),
+                RedisValue::Integer(1)
+            ]),
+            12
+        ))
+    );
+    assert_eq!(
+        parse_value(b"|1\r\n+ttl\r\n:3\r\n:1\r\n")?,
+        Some((
+            RedisValue::Attribute(
+                vec![(
+                    RedisValue::String(String::from("ttl")),
+                    RedisValue::Integer(3)
+                )],
+                Box::new(RedisValue::Integer(1))
+            ),
+            18
+        ))
+    );
+
+    match parse_value(b"!13\r\nERR something\r\n") {
+        Err(e) => assert_eq!(e.kind(), ErrorKind::ResponseError),
+        Ok(v) => panic!("Expected error, got {:?}", v),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn value_length_resp3() -> Result<(), RedisError> {
+    assert_eq!(value_length(b"%1\r\n+a\r\n:1\r\n")?, Some(12));
+    assert_eq!(value_length(b"|1\r\n+ttl\r\n:3\r\n:1\r\n")?, Some(18));
+    assert_eq!(value_length(b"|1\r\n+ttl\r\n:3\r\n")?, None);
+    assert_eq!(value_length(b"!3\r\nERR\r\n")?, Some(9));
+
+    Ok(())
+}
+
+#[test]
+fn parse_frame_borrow_buffer() -> Result<(), RedisError> {
+    let buf = b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n+OK";
+
+    assert_eq!(
+        parse_frame(buf)?,
+        Some((
+            RedisFrame::Array(vec![
+                RedisFrame::BulkString(b"GET"),
+                RedisFrame::BulkString(b"a")
+            ]),
+            20
+        ))
+    );
+    assert_eq!(parse_frame(&buf[20..])?, None);
+    assert_eq!(
+        parse_frame(b"-ERR bad\r\n")?,
+        Some((RedisFrame::Error(b"ERR bad"), 10))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn read_array_resume_after_no_data() -> Result<(), RedisError> {
+    let stream = TestRedisStream::with_chunks(vec![b"*2\r\n$5\r\nHel", b"", b"lo\r\n:12\r\n"]);
+    let mut box_stream: Box<dyn RedisStream> = Box::new(stream);
+
+    // Frame is incomplete, nothing is lost
+    match read_array(&mut box_stream) {
+        Ok(v) => panic!("Must be return error, got {:?}", v),
+        Err(e) => assert_eq!(e.kind(), ErrorKind::NoDataAvailable),
+    }
+
+    assert_eq!(
+        read_array(&mut box_stream)?,
+        RedisValue::Array(vec![
+            RedisValue::BulkString(b"Hello".to_vec()),
+            RedisValue::Integer(12)
+        ])
+    );
+
+    Ok(())
+}
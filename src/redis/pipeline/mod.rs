@@ -0,0 +1,74 @@
+//! This module contain pipeline of commands.
+//!
+//! Commands are written in one batch, then replies are read in order. That avoid a round trip
+//! per command.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::redis::encoder::Command;
+use crate::redis::types::{RedisError, RedisValue};
+
+/// Reply of each command of pipeline, in order.
+pub type PipelineReply = Vec<Result<RedisValue, RedisError>>;
+
+/// Commands sent in one batch.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pipeline {
+    commands: Vec<Command>,
+    /// Wrap commands in MULTI/EXEC.
+    transaction: bool,
+}
+
+impl Pipeline {
+    /// Create empty pipeline.
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Execute commands atomically (MULTI/EXEC).
+    pub fn atomic(mut self) -> Self {
+        self.transaction = true;
+        self
+    }
+
+    /// Add a command.
+    pub fn add(mut self, cmd: Command) -> Self {
+        self.commands.push(cmd);
+        self
+    }
+
+    /// Number of commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// True if no command.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// True if commands are wrapped in MULTI/EXEC.
+    pub fn is_atomic(&self) -> bool {
+        self.transaction
+    }
+
+    /// Encode all commands to Redis protocol.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        if self.transaction {
+            output.extend(Command::new("MULTI").encode());
+        }
+
+        for cmd in &self.commands {
+            output.extend(cmd.encode());
+        }
+
+        if self.transaction {
+            output.extend(Command::new("EXEC").encode());
+        }
+
+        output
+    }
+}
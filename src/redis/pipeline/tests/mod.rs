@@ -0,0 +1,25 @@
+use crate::redis::encoder::Command;
+use crate::redis::pipeline::Pipeline;
+
+#[test]
+fn pipeline_encode_commands() {
+    let pipeline = Pipeline::new()
+        .add(Command::new("PING"))
+        .add(Command::new("GET").arg("a"));
+
+    assert_eq!(pipeline.len(), 2);
+    assert_eq!(
+        pipeline.encode(),
+        b"*1\r\n$4\r\nPING\r\n*2\r\n$3\r\nGET\r\n$1\r\na\r\n".to_vec()
+    );
+}
+
+#[test]
+fn pipeline_encode_transaction() {
+    let pipeline = Pipeline::new().atomic().add(Command::new("INCR").arg("a"));
+
+    assert_eq!(
+        pipeline.encode(),
+        b"*1\r\n$5\r\nMULTI\r\n*2\r\n$4\r\nINCR\r\n$1\r\na\r\n*1\r\n$4\r\nEXEC\r\n".to_vec()
+    );
+}
@@ -0,0 +1,139 @@
+//! This module contains live list of sentinels.
+//!
+//! Seeds come from config and never expire. Other sentinels are discovered with
+//! `SENTINEL SENTINELS` and `+sentinel` events, and removed when they are not seen since expire
+//! time.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::redis::types::RedisValue;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+/// Sentinels known by watcher.
+#[derive(Debug)]
+pub struct SentinelList {
+    /// Sentinels from config.
+    seeds: Vec<String>,
+    /// Discovered sentinels and last time they were seen.
+    discovered: BTreeMap<String, Instant>,
+    /// Time after which a discovered sentinel not seen is removed.
+    expire: Duration,
+}
+
+impl SentinelList {
+    pub fn new(seeds: Vec<String>, expire: Duration) -> Self {
+        SentinelList {
+            seeds,
+            discovered: BTreeMap::new(),
+            expire,
+        }
+    }
+
+    /// Sentinel is alive. Return true if it's a new sentinel.
+    pub fn seen(&mut self, addr: &str, now: Instant) -> bool {
+        if self.seeds.iter().any(|s| s == addr) {
+            return false;
+        }
+
+        self.discovered.insert(String::from(addr), now).is_none()
+    }
+
+    /// Remove discovered sentinels not seen since expire time.
+    /// Return removed sentinels.
+    pub fn expire(&mut self, now: Instant) -> Vec<String> {
+        let expire = self.expire;
+        let mut removed = Vec::new();
+
+        self.discovered.retain(|addr, seen| {
+            if now.duration_since(*seen) < expire {
+                return true;
+            }
+
+            removed.push(addr.clone());
+            false
+        });
+
+        removed
+    }
+
+    /// All sentinels: seeds first, then discovered.
+    pub fn addresses(&self) -> Vec<String> {
+        self.seeds
+            .iter()
+            .chain(self.discovered.keys())
+            .cloned()
+            .collect()
+    }
+
+    /// First sentinel not tried yet, in order of `addresses()`.
+    /// Sentinels can be discovered or expired between two calls without skip one.
+    pub fn next_address(&self, tried: &BTreeSet<String>) -> Option<String> {
+        self.seeds
+            .iter()
+            .chain(self.discovered.keys())
+            .find(|addr| !tried.contains(*addr))
+            .cloned()
+    }
+}
+
+/// Get address of a field in reply of SENTINEL SENTINELS or SENTINEL MASTERS.
+fn get_field<'a>(fields: &'a [(RedisValue, RedisValue)], name: &str) -> Option<&'a [u8]> {
+    fields.iter().find_map(|(k, v)| match (k, v) {
+        (RedisValue::BulkString(k), RedisValue::BulkString(v)) if k == name.as_bytes() => {
+            Some(v.as_slice())
+        }
+        _ => None,
+    })
+}
+
+/// Return fields of an instance (flat array in RESP2, map in RESP3).
+fn get_fields(instance: &RedisValue) -> Vec<(RedisValue, RedisValue)> {
+    match instance {
+        RedisValue::Map(pairs) => pairs.clone(),
+        RedisValue::Array(values) => values
+            .chunks(2)
+            .filter(|pair| pair.len() == 2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Return addresses of instances in reply of SENTINEL SENTINELS or SENTINEL REPLICAS.
+pub fn parse_sentinels_reply(reply: &RedisValue) -> Vec<String> {
+    let instances = match reply {
+        RedisValue::Array(instances) => instances,
+        _ => return Vec::new(),
+    };
+
+    instances
+        .iter()
+        .filter_map(|instance| {
+            let fields = get_fields(instance);
+
+            match (get_field(&fields, "ip"), get_field(&fields, "port")) {
+                (Some(ip), Some(port)) => Some(format!(
+                    "{}:{}",
+                    String::from_utf8_lossy(ip),
+                    String::from_utf8_lossy(port)
+                )),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Return address of sentinel in a `+sentinel` event of group.
+/// Format: "sentinel <name> <ip> <port> @ <group> <master ip> <master port>".
+pub fn parse_sentinel_event(message: &str, group_name: &str) -> Option<String> {
+    let parts: Vec<&str> = message.split(' ').collect();
+
+    match parts.as_slice() {
+        ["sentinel", _, ip, port, "@", group, ..] if *group == group_name => {
+            Some(format!("{}:{}", ip, port))
+        }
+        _ => None,
+    }
+}
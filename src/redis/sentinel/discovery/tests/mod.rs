@@ -0,0 +1,101 @@
+use crate::redis::sentinel::discovery::{
+    parse_sentinel_event, parse_sentinels_reply, SentinelList,
+};
+use crate::redis::types::RedisValue;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+fn bulk(data: &str) -> RedisValue {
+    RedisValue::BulkString(data.as_bytes().to_vec())
+}
+
+#[test]
+fn sentinel_list_discover_and_expire() {
+    let now = Instant::now();
+    let mut list = SentinelList::new(
+        vec![String::from("10.0.0.1:26379")],
+        Duration::from_secs(60),
+    );
+
+    assert!(!list.seen("10.0.0.1:26379", now));
+    assert!(list.seen("10.0.0.2:26379", now));
+    assert!(!list.seen("10.0.0.2:26379", now));
+    assert!(list.seen("10.0.0.3:26379", now + Duration::from_secs(30)));
+
+    assert_eq!(
+        list.expire(now + Duration::from_secs(61)),
+        vec![String::from("10.0.0.2:26379")]
+    );
+    assert_eq!(
+        list.addresses(),
+        vec![
+            String::from("10.0.0.1:26379"),
+            String::from("10.0.0.3:26379")
+        ]
+    );
+}
+
+#[test]
+fn sentinel_list_next_address() {
+    let now = Instant::now();
+    let mut list = SentinelList::new(
+        vec![String::from("10.0.0.1:26379")],
+        Duration::from_secs(60),
+    );
+    let mut tried = BTreeSet::new();
+
+    list.seen("10.0.0.2:26379", now);
+    list.seen("10.0.0.3:26379", now + Duration::from_secs(30));
+
+    let addr = list.next_address(&tried).unwrap();
+    assert_eq!(addr, "10.0.0.1:26379");
+    tried.insert(addr);
+
+    let addr = list.next_address(&tried).unwrap();
+    assert_eq!(addr, "10.0.0.2:26379");
+    tried.insert(addr);
+
+    // Tried sentinel expires: next one is not skipped
+    list.expire(now + Duration::from_secs(61));
+
+    let addr = list.next_address(&tried).unwrap();
+    assert_eq!(addr, "10.0.0.3:26379");
+    tried.insert(addr);
+
+    assert_eq!(list.next_address(&tried), None);
+}
+
+#[test]
+fn parse_sentinels() {
+    let reply = RedisValue::Array(vec![RedisValue::Array(vec![
+        bulk("name"),
+        bulk("7f3a"),
+        bulk("ip"),
+        bulk("10.0.0.2"),
+        bulk("port"),
+        bulk("26379"),
+    ])]);
+
+    assert_eq!(
+        parse_sentinels_reply(&reply),
+        vec![String::from("10.0.0.2:26379")]
+    );
+}
+
+#[test]
+fn parse_sentinel_events() {
+    assert_eq!(
+        parse_sentinel_event(
+            "sentinel 7f3a 10.0.0.4 26379 @ cluster_1 10.0.0.1 6379",
+            "cluster_1"
+        ),
+        Some(String::from("10.0.0.4:26379"))
+    );
+    assert_eq!(
+        parse_sentinel_event(
+            "sentinel 7f3a 10.0.0.4 26379 @ other 10.0.0.1 6379",
+            "cluster_1"
+        ),
+        None
+    );
+}
@@ -0,0 +1,198 @@
+//! This module contains events published by sentinels.
+//!
+//! Events are parsed from Sentinel Pub/Sub messages (`+sdown`, `+odown`, `+tilt`...) and sent to
+//! main loop. State built from events is shown by admin interface.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+/// Instance described by an event.
+/// Format: "<type> <name> <ip> <port> @ <group> <master ip> <master port>".
+/// For a master, "@ ..." part is missing and group is name of master.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentinelInstance {
+    /// master, slave or sentinel.
+    pub kind: String,
+    pub name: String,
+    /// Addresse: "ww.xx.yy.zz:ppppp".
+    pub addr: String,
+    /// Name of redis group.
+    pub group_name: String,
+}
+
+/// Event published by a sentinel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SentinelEvent {
+    /// +sdown: instance is subjectively down.
+    SubjectivelyDown(SentinelInstance),
+    /// -sdown: instance is no more subjectively down.
+    SubjectivelyUp(SentinelInstance),
+    /// +odown: instance is objectively down (quorum of sentinels agree).
+    ObjectivelyDown(SentinelInstance),
+    /// -odown: instance is no more objectively down.
+    ObjectivelyUp(SentinelInstance),
+    /// +failover-state-*: new state of failover (e.g. "select-slave").
+    FailoverState(String, SentinelInstance),
+    /// -failover-abort-*: failover aborted (e.g. "no-good-slave").
+    FailoverAbort(String, SentinelInstance),
+    /// +slave: new replica detected.
+    Replica(SentinelInstance),
+    /// +reboot: instance was restarted.
+    Reboot(SentinelInstance),
+    /// +tilt: sentinel enters tilt mode.
+    TiltEntered,
+    /// -tilt: sentinel exits tilt mode.
+    TiltExited,
+}
+
+impl SentinelEvent {
+    /// Return true if event concerns group (tilt concerns all groups).
+    pub fn is_about(&self, group_name: &str) -> bool {
+        match self.instance() {
+            Some(instance) => instance.group_name == group_name,
+            None => true,
+        }
+    }
+
+    /// Instance described by event.
+    pub fn instance(&self) -> Option<&SentinelInstance> {
+        match self {
+            SentinelEvent::SubjectivelyDown(i)
+            | SentinelEvent::SubjectivelyUp(i)
+            | SentinelEvent::ObjectivelyDown(i)
+            | SentinelEvent::ObjectivelyUp(i)
+            | SentinelEvent::FailoverState(_, i)
+            | SentinelEvent::FailoverAbort(_, i)
+            | SentinelEvent::Replica(i)
+            | SentinelEvent::Reboot(i) => Some(i),
+            SentinelEvent::TiltEntered | SentinelEvent::TiltExited => None,
+        }
+    }
+}
+
+/// Parse instance details of event.
+fn parse_instance(message: &str) -> Option<SentinelInstance> {
+    let parts: Vec<&str> = message.split(' ').collect();
+
+    match parts.as_slice() {
+        [kind, name, ip, port, "@", group_name, ..] => Some(SentinelInstance {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            addr: format!("{}:{}", ip, port),
+            group_name: group_name.to_string(),
+        }),
+        ["master", name, ip, port, ..] => Some(SentinelInstance {
+            kind: String::from("master"),
+            name: name.to_string(),
+            addr: format!("{}:{}", ip, port),
+            group_name: name.to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Parse message published by sentinel on channel.
+/// Return None for channels that are not typed events.
+pub fn parse_event(channel: &str, message: &str) -> Option<SentinelEvent> {
+    match channel {
+        "+tilt" => return Some(SentinelEvent::TiltEntered),
+        "-tilt" => return Some(SentinelEvent::TiltExited),
+        _ => {}
+    }
+
+    let instance = parse_instance(message)?;
+
+    let event = match channel {
+        "+sdown" => SentinelEvent::SubjectivelyDown(instance),
+        "-sdown" => SentinelEvent::SubjectivelyUp(instance),
+        "+odown" => SentinelEvent::ObjectivelyDown(instance),
+        "-odown" => SentinelEvent::ObjectivelyUp(instance),
+        "+slave" => SentinelEvent::Replica(instance),
+        "+reboot" => SentinelEvent::Reboot(instance),
+        c => {
+            if let Some(state) = c.strip_prefix("+failover-state-") {
+                SentinelEvent::FailoverState(String::from(state), instance)
+            } else if let Some(reason) = c.strip_prefix("-failover-abort-") {
+                SentinelEvent::FailoverAbort(String::from(reason), instance)
+            } else {
+                return None;
+            }
+        }
+    };
+
+    Some(event)
+}
+
+/// State of group seen by sentinels.
+#[derive(Debug, Default, PartialEq)]
+pub struct SentinelState {
+    /// Master is objectively down, failover will start.
+    pub master_down: bool,
+    /// Sentinel is in tilt mode.
+    pub tilt: bool,
+    /// Current failover state, if a failover is running.
+    pub failover_state: Option<String>,
+    /// Instances subjectively down.
+    pub down_instances: BTreeSet<String>,
+    /// Number of events received.
+    pub events: u64,
+}
+
+impl SentinelState {
+    const fn new() -> Self {
+        SentinelState {
+            master_down: false,
+            tilt: false,
+            failover_state: None,
+            down_instances: BTreeSet::new(),
+            events: 0,
+        }
+    }
+
+    /// Update state with event.
+    pub fn apply(&mut self, event: &SentinelEvent) {
+        self.events += 1;
+
+        match event {
+            SentinelEvent::SubjectivelyDown(i) => {
+                self.down_instances.insert(i.addr.clone());
+            }
+            SentinelEvent::SubjectivelyUp(i) => {
+                self.down_instances.remove(&i.addr);
+            }
+            SentinelEvent::ObjectivelyDown(i) if i.kind == "master" => self.master_down = true,
+            SentinelEvent::ObjectivelyUp(i) if i.kind == "master" => self.master_down = false,
+            SentinelEvent::FailoverState(state, _) => self.failover_state = Some(state.clone()),
+            SentinelEvent::FailoverAbort(_, _) => self.failover_state = None,
+            SentinelEvent::TiltEntered => self.tilt = true,
+            SentinelEvent::TiltExited => self.tilt = false,
+            _ => {}
+        }
+    }
+
+    /// Master switched: failover is over.
+    pub fn master_changed(&mut self) {
+        self.master_down = false;
+        self.failover_state = None;
+    }
+
+    /// Return state like Redis INFO command: "name:value\r\n".
+    pub fn render(&self) -> String {
+        let down_instances: Vec<&str> = self.down_instances.iter().map(|s| s.as_str()).collect();
+
+        format!(
+            "# Sentinel\r\nmaster_down:{}\r\ntilt:{}\r\nfailover_state:{}\r\ndown_instances:{}\r\nsentinel_events:{}\r\n",
+            self.master_down as u8,
+            self.tilt as u8,
+            self.failover_state.as_deref().unwrap_or("none"),
+            down_instances.join(","),
+            self.events
+        )
+    }
+}
+
+/// State of group, updated by main loop and read by admin interface.
+pub static SENTINEL_STATE: Mutex<SentinelState> = Mutex::new(SentinelState::new());
@@ -0,0 +1,74 @@
+use crate::redis::sentinel::events::{parse_event, SentinelEvent, SentinelInstance, SentinelState};
+
+fn master() -> SentinelInstance {
+    SentinelInstance {
+        kind: String::from("master"),
+        name: String::from("cluster_1"),
+        addr: String::from("10.0.0.1:6379"),
+        group_name: String::from("cluster_1"),
+    }
+}
+
+#[test]
+fn parse_sentinel_events() {
+    assert_eq!(
+        parse_event("+odown", "master cluster_1 10.0.0.1 6379 #quorum 2/2"),
+        Some(SentinelEvent::ObjectivelyDown(master()))
+    );
+    assert_eq!(
+        parse_event(
+            "+failover-state-select-slave",
+            "master cluster_1 10.0.0.1 6379"
+        ),
+        Some(SentinelEvent::FailoverState(
+            String::from("select-slave"),
+            master()
+        ))
+    );
+    assert_eq!(
+        parse_event(
+            "+sdown",
+            "slave 10.0.0.2:6379 10.0.0.2 6379 @ cluster_1 10.0.0.1 6379"
+        ),
+        Some(SentinelEvent::SubjectivelyDown(SentinelInstance {
+            kind: String::from("slave"),
+            name: String::from("10.0.0.2:6379"),
+            addr: String::from("10.0.0.2:6379"),
+            group_name: String::from("cluster_1"),
+        }))
+    );
+    assert_eq!(
+        parse_event("+tilt", "#tilt mode entered"),
+        Some(SentinelEvent::TiltEntered)
+    );
+    assert_eq!(parse_event("+new-epoch", "12"), None);
+}
+
+#[test]
+fn sentinel_event_about_group() {
+    let event = SentinelEvent::ObjectivelyDown(master());
+
+    assert!(event.is_about("cluster_1"));
+    assert!(!event.is_about("cluster_2"));
+    assert!(SentinelEvent::TiltExited.is_about("cluster_2"));
+}
+
+#[test]
+fn sentinel_state_from_events() {
+    let mut state = SentinelState::default();
+
+    state.apply(&SentinelEvent::ObjectivelyDown(master()));
+    state.apply(&SentinelEvent::FailoverState(
+        String::from("wait-start"),
+        master(),
+    ));
+
+    assert!(state.master_down);
+    assert!(state.render().contains("failover_state:wait-start\r\n"));
+
+    state.master_changed();
+
+    assert!(!state.master_down);
+    assert_eq!(state.failover_state, None);
+    assert_eq!(state.events, 2);
+}
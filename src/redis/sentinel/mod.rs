@@ -1,15 +1,133 @@
 //! This module contains routine to watch sentinels.
 //!
+#[cfg(test)]
+pub mod tests;
+
+pub mod discovery;
+pub mod events;
+
 use crate::app::messages::MainLoopEvent;
-use crate::config::Config;
+use crate::config::{Config, ConfigTimeout, Sentinels};
+use crate::redis::encoder::Command;
+use crate::redis::sentinel::discovery::{
+    parse_sentinel_event, parse_sentinels_reply, SentinelList,
+};
+use crate::redis::sentinel::events::parse_event;
 use crate::redis::subscription::RedisSubscription;
 use crate::redis::types::{ErrorKind, RedisError, RedisValue};
-use crate::redis::{convert_to_string, RedisConnector};
-use crate::redis::node::{create_redis_stream_connection, create_redis_stream_connection_blocking};
-use std::sync::mpsc::Sender;
+use crate::redis::pipeline::Pipeline;
+use crate::redis::{convert_to_string, master_addr_command, to_master_addr, RedisConnector};
+use crate::redis::node::{create_redis_stream_connection, create_redis_stream_connection_timeout};
+use crate::metrics;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::time::Instant;
 use std::{thread, time};
 use log::{error, info, debug, warn};
 
+/// Channels watcher needs to follow master and sentinels.
+const REQUIRED_CHANNELS: [&str; 2] = ["+switch-master", "+sentinel"];
+
+/// Time to wait between two ROLE checks of new master.
+const ROLE_CHECK_RETRY: time::Duration = time::Duration::from_millis(100);
+
+/// Max time of one ROLE check, so an unreachable node doesn't hold whole check.
+const ROLE_CHECK_ATTEMPT: time::Duration = time::Duration::from_secs(1);
+
+/// New config of sentinels, applied by watcher at next check.
+static SENTINELS_RELOAD: Mutex<Option<(Sentinels, ConfigTimeout)>> = Mutex::new(None);
+
+/// Last master notified to main loop, by watcher or role checker.
+static CURRENT_MASTER: Mutex<String> = Mutex::new(String::new());
+
+/// Return last master notified to main loop (empty before first notification).
+fn current_master() -> String {
+    CURRENT_MASTER
+        .lock()
+        .map(|master| master.clone())
+        .unwrap_or_default()
+}
+
+/// Update last master notified to main loop.
+fn set_current_master(addr: &str) {
+    if let Ok(mut master) = CURRENT_MASTER.lock() {
+        *master = String::from(addr);
+    }
+}
+
+/// Sentinels known by watcher (from config and discovered), for other checks of group.
+static KNOWN_SENTINELS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Return sentinels known by watcher, empty before watcher gets first reply.
+pub fn known_sentinels() -> Vec<String> {
+    KNOWN_SENTINELS
+        .lock()
+        .map(|sentinels| sentinels.clone())
+        .unwrap_or_default()
+}
+
+/// Share sentinel list of watcher.
+fn publish_sentinel_list(sentinel_list: &SentinelList) {
+    if let Ok(mut sentinels) = KNOWN_SENTINELS.lock() {
+        *sentinels = sentinel_list.addresses();
+    }
+}
+
+/// Ask watcher to use new sentinels and timeouts.
+pub fn reload_sentinels(sentinels: Sentinels, timeout: ConfigTimeout) {
+    if let Ok(mut reload) = SENTINELS_RELOAD.lock() {
+        *reload = Some((sentinels, timeout));
+    }
+}
+
+/// Return new config of sentinels, if any.
+fn take_sentinels_reload() -> Option<(Sentinels, ConfigTimeout)> {
+    SENTINELS_RELOAD.lock().ok().and_then(|mut reload| reload.take())
+}
+
+/// Apply new config of sentinels if reloaded, sentinel list starts again from config.
+/// Return true if config changed.
+fn apply_sentinels_reload(
+    sentinels: &mut Sentinels,
+    query_timeout: &mut time::Duration,
+    watcher: &mut SentinelWatcher,
+) -> bool {
+    let (new_sentinels, new_timeout) = match take_sentinels_reload() {
+        Some(reload) => reload,
+        None => return false,
+    };
+
+    *sentinels = new_sentinels;
+    *query_timeout = time::Duration::from_millis(new_timeout.sentinels);
+    watcher.role_check_timeout = new_timeout.master_role_check;
+    watcher.sentinel_list = SentinelList::new(
+        sentinels.address.clone(),
+        time::Duration::from_millis(sentinels.discovery_expire),
+    );
+    publish_sentinel_list(&watcher.sentinel_list);
+
+    true
+}
+
+/// State of sentinel watcher.
+struct SentinelWatcher {
+    tx_master_change: Sender<MainLoopEvent>,
+    group_name: String,
+    /// Max time (ms) to wait new master reports role master.
+    role_check_timeout: u64,
+    /// Sentinels from config and discovered.
+    sentinel_list: SentinelList,
+    /// New masters announced by +switch-master, checked outside of subscription loop.
+    tx_role_check: Sender<RoleCheckRequest>,
+}
+
+/// New master to check and max time (ms) to wait it reports role master.
+type RoleCheckRequest = (MasterChangeNotification, u64);
+
+/// Sentinels of group and master address reported by a sentinel.
+type SentinelReplies = (Result<Vec<String>, RedisError>, Result<String, RedisError>);
+
 /// Struct to communicate a master change ip address.
 #[derive(Debug)]
 pub struct MasterChangeNotification {
@@ -21,15 +139,34 @@ pub struct MasterChangeNotification {
     pub group_name: String,
 }
 
-/// Create redis_subscription to switch master.
+/// Return patterns to subscribe: events from config and channels needed by watcher.
+/// Patterns must not overlap, otherwise a message is received several times.
+pub fn subscription_patterns(events: &[String]) -> Vec<String> {
+    if events.iter().any(|e| e == "*") {
+        return vec![String::from("*")];
+    }
+
+    let mut patterns = events.to_vec();
+
+    for channel in REQUIRED_CHANNELS {
+        if !patterns.iter().any(|p| p == channel) {
+            patterns.push(String::from(channel));
+        }
+    }
+
+    patterns
+}
+
+/// Create redis_subscription to sentinel events.
 fn create_redis_subscription_switch_master(
     redis_sentinel_addr: &str,
+    patterns: Vec<String>,
 ) -> Result<RedisSubscription, RedisError> {
     // Create new sentinel connection for subscribe.
     let sentinel_stream = create_redis_stream_connection(redis_sentinel_addr)?;
     // Subscribe to Sentinel to notify when master change
     let mut sentinel_subscription =
-        RedisSubscription::new(Box::new(sentinel_stream), String::from("+switch-master"));
+        RedisSubscription::with_patterns(Box::new(sentinel_stream), patterns);
 
     sentinel_subscription.subscribe()?;
 
@@ -44,16 +181,28 @@ fn manage_redis_subscription_error(error: RedisError) -> Result<(), RedisError>
     }
 }
 
+/// Return element of subscription message or error if message is too short.
+fn subscription_element(data: &[RedisValue], index: usize) -> Result<&RedisValue, RedisError> {
+    data.get(index).ok_or_else(|| {
+        RedisError::from_message(&format!(
+            "Subscription message has no element {}: {:?}",
+            index, data
+        ))
+    })
+}
+
 /// If we receive message.
 fn manage_subscription_data(
     data: RedisValue,
-    tx_master_change: &Sender<MainLoopEvent>,
+    watcher: &mut SentinelWatcher,
 ) -> Result<(), RedisError> {
     match data {
         RedisValue::Array(data) => {
-            let msg_type = convert_to_string(data.get(0).unwrap())?;
-            let channel = convert_to_string(data.get(1).unwrap())?;
-            let data = data.get(2).unwrap();
+            let msg_type = convert_to_string(subscription_element(&data, 0)?)?;
+            // Only pmessage has pattern as second element, psubscribe has same layout as subscribe
+            let index = if msg_type == "pmessage" { 2 } else { 1 };
+            let channel = convert_to_string(subscription_element(&data, index)?)?;
+            let data = subscription_element(&data, index + 1)?;
 
             debug!(
                 "Receive message type: '{}' from channel: '{}' with data: '{:?}'",
@@ -62,7 +211,7 @@ fn manage_subscription_data(
                 data
             );
 
-            manage_subscription_message(&msg_type, &channel, &data, tx_master_change)
+            manage_subscription_message(&msg_type, &channel, data, watcher)
         }
         _ => Err(RedisError::from_message(
             "Impossible, subscription don't return array!",
@@ -75,13 +224,11 @@ fn manage_subscription_message(
     msg_type: &str,
     channel: &str,
     data: &RedisValue,
-    tx_master_change: &Sender<MainLoopEvent>,
+    watcher: &mut SentinelWatcher,
 ) -> Result<(), RedisError> {
     match msg_type {
-        "subscribe" => manage_subscription_message_type_subscribe(channel, data),
-        "message" => {
-            manage_subscription_message_type_message(channel, data, tx_master_change)
-        }
+        "subscribe" | "psubscribe" => manage_subscription_message_type_subscribe(channel, data),
+        "message" | "pmessage" => manage_subscription_message_type_message(channel, data, watcher),
         e => {
             warn!("Unknow message type '{}'!", e);
             Ok(())
@@ -93,10 +240,14 @@ fn manage_subscription_message(
 fn manage_subscription_message_type_message(
     channel: &str,
     data: &RedisValue,
-    tx_master_change: &Sender<MainLoopEvent>,
+    watcher: &mut SentinelWatcher,
 ) -> Result<(), RedisError> {
+    if channel == "+sentinel" {
+        return manage_subscription_message_new_sentinel(data, watcher);
+    }
+
     if channel != "+switch-master" {
-        return Ok(());
+        return manage_subscription_message_event(channel, data, watcher);
     }
 
     /*
@@ -126,12 +277,160 @@ fn manage_subscription_message_type_message(
         String::from(old_master_port)
     );
 
-    send_notification(
-        &new_master_addr,
-        &old_master_addr,
-        group_name,
-        tx_master_change,
-    )
+    let change = MasterChangeNotification {
+        new: new_master_addr,
+        old: old_master_addr,
+        group_name: String::from(group_name),
+    };
+
+    // ROLE check can last role_check_timeout, other events must not wait it
+    let _ = watcher
+        .tx_role_check
+        .send((change, watcher.role_check_timeout));
+
+    Ok(())
+}
+
+/// Check new masters in order in a new thread, and notify main loop of those that report role
+/// master. Thread stops when returned sender is dropped.
+fn start_role_checker(tx_master_change: Sender<MainLoopEvent>) -> Sender<RoleCheckRequest> {
+    let (tx_role_check, rx_role_check) = mpsc::channel::<RoleCheckRequest>();
+
+    thread::spawn(move || {
+        for (change, role_check_timeout) in rx_role_check {
+            let _ = send_notification(
+                &change.new,
+                &change.old,
+                &change.group_name,
+                &tx_master_change,
+                role_check_timeout,
+            );
+        }
+    });
+
+    tx_role_check
+}
+
+/// When sentinel publishes an event, send it to main loop if it concerns group.
+fn manage_subscription_message_event(
+    channel: &str,
+    data: &RedisValue,
+    watcher: &mut SentinelWatcher,
+) -> Result<(), RedisError> {
+    let message = convert_to_string(data)?;
+
+    match parse_event(channel, &message) {
+        Some(event) if event.is_about(&watcher.group_name) => {
+            info!("Sentinel event {} {}", channel, message);
+
+            watcher
+                .tx_master_change
+                .send(MainLoopEvent::sentinel_event(event))
+                .unwrap();
+        }
+        _ => debug!("Ignore sentinel event {} {}", channel, message),
+    }
+
+    Ok(())
+}
+
+/// When a new sentinel is added to group.
+fn manage_subscription_message_new_sentinel(
+    data: &RedisValue,
+    watcher: &mut SentinelWatcher,
+) -> Result<(), RedisError> {
+    let message = convert_to_string(data)?;
+
+    if let Some(sentinel_addr) = parse_sentinel_event(&message, &watcher.group_name) {
+        if watcher.sentinel_list.seen(&sentinel_addr, Instant::now()) {
+            info!("Discover sentinel {}.", sentinel_addr);
+            publish_sentinel_list(&watcher.sentinel_list);
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask other sentinels of group to a sentinel, and forget sentinels not seen since a while.
+fn discover_sentinels(
+    redis_sentinel_addr: &str,
+    watcher: &mut SentinelWatcher,
+    timeout: time::Duration,
+) {
+    let sentinels = ask_sentinels(redis_sentinel_addr, &watcher.group_name, timeout);
+
+    update_sentinel_list(redis_sentinel_addr, watcher, sentinels);
+}
+
+/// Add sentinels reported by a sentinel, and forget sentinels not seen since a while.
+fn update_sentinel_list(
+    redis_sentinel_addr: &str,
+    watcher: &mut SentinelWatcher,
+    sentinels: Result<Vec<String>, RedisError>,
+) {
+    let now = Instant::now();
+
+    match sentinels {
+        Ok(sentinels) => {
+            watcher.sentinel_list.seen(redis_sentinel_addr, now);
+
+            for sentinel_addr in sentinels {
+                if watcher.sentinel_list.seen(&sentinel_addr, now) {
+                    info!("Discover sentinel {}.", sentinel_addr);
+                }
+            }
+        }
+        Err(e) => warn!("Can't get sentinels from {}: {}", redis_sentinel_addr, e),
+    }
+
+    for sentinel_addr in watcher.sentinel_list.expire(now) {
+        info!("Forget sentinel {} not seen since a while.", sentinel_addr);
+    }
+
+    publish_sentinel_list(&watcher.sentinel_list);
+}
+
+/// Command to ask other sentinels of group.
+fn sentinels_command(group_name: &str) -> Command {
+    Command::new("SENTINEL").arg("SENTINELS").arg(group_name)
+}
+
+/// Run SENTINEL SENTINELS on a sentinel.
+fn ask_sentinels(
+    redis_sentinel_addr: &str,
+    group_name: &str,
+    timeout: time::Duration,
+) -> Result<Vec<String>, RedisError> {
+    let sentinel_stream = create_redis_stream_connection_timeout(redis_sentinel_addr, timeout)?;
+    let mut sentinel_connector = RedisConnector::new(Box::new(sentinel_stream));
+    let reply = sentinel_connector.execute(&sentinels_command(group_name))?;
+
+    Ok(parse_sentinels_reply(&reply))
+}
+
+/// Ask other sentinels of group and master address to a sentinel, in one round trip.
+fn ask_sentinels_and_master_addr(
+    redis_sentinel_addr: &str,
+    group_name: &str,
+    timeout: time::Duration,
+) -> Result<SentinelReplies, RedisError> {
+    let sentinel_stream = create_redis_stream_connection_timeout(redis_sentinel_addr, timeout)?;
+    let mut sentinel_connector = RedisConnector::new(Box::new(sentinel_stream));
+    let pipeline = Pipeline::new()
+        .add(sentinels_command(group_name))
+        .add(master_addr_command(group_name));
+
+    let mut replies = sentinel_connector.execute_pipeline(&pipeline)?.into_iter();
+    let sentinels = replies
+        .next()
+        .unwrap_or(Ok(RedisValue::Nil))
+        .map(|reply| parse_sentinels_reply(&reply));
+    let master_addr = replies
+        .next()
+        .unwrap_or(Ok(RedisValue::Nil))
+        .and_then(to_master_addr);
+
+    Ok((sentinels, master_addr))
 }
 
 /// When receive a message type subscribe from subscription.
@@ -158,88 +457,293 @@ fn manage_subscription_message_type_subscribe(
     Ok(())
 }
 
+/// Ask ROLE to Redis.
+fn get_role(redis_addr: &str, timeout: time::Duration) -> Result<String, RedisError> {
+    let stream = create_redis_stream_connection_timeout(redis_addr, timeout)?;
+    let mut connector = RedisConnector::new(Box::new(stream));
+
+    connector.role()
+}
+
+/// Check that Redis reports role master.
+/// Retry until timeout (ms) cause Sentinel can send address of a replica not yet promoted.
+fn check_master_role(redis_addr: &str, timeout: u64) -> bool {
+    let deadline = Instant::now() + time::Duration::from_millis(timeout);
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        if remaining.is_zero() {
+            return false;
+        }
+
+        match get_role(redis_addr, remaining.min(ROLE_CHECK_ATTEMPT)) {
+            Ok(role) if role == "master" => {
+                info!("ROLE of new master {} is '{}'", redis_addr, role);
+                return true;
+            }
+            Ok(role) => warn!("ROLE of new master {} is '{}', retry", redis_addr, role),
+            Err(e) => warn!("Can't get ROLE of new master {}: {}", redis_addr, e),
+        }
+
+        thread::sleep(ROLE_CHECK_RETRY.min(deadline.saturating_duration_since(Instant::now())));
+    }
+}
+
 /// Create notification of master change.
+/// Return false if new master is ignored because it doesn't report role master.
 fn send_notification(
     new_redis_master_addr: &str,
     old_redis_master_addr: &str,
     group_name: &str,
     tx_master_change: &Sender<MainLoopEvent>,
-) -> Result<(), RedisError> {
+    role_check_timeout: u64,
+) -> Result<bool, RedisError> {
+    if !check_master_role(new_redis_master_addr, role_check_timeout) {
+        metrics::MASTER_ROLE_CHECK_FAILED.increment();
+
+        error!(
+            "New master {} of {} never reported role master in {}ms, keep master {}!",
+            new_redis_master_addr, group_name, role_check_timeout, old_redis_master_addr
+        );
+
+        return Ok(false);
+    }
+
     let msg = MasterChangeNotification {
         new: String::from(new_redis_master_addr),
         old: String::from(old_redis_master_addr),
         group_name: String::from(group_name),
     };
 
-    // TODO check if master role
-
+    set_current_master(new_redis_master_addr);
     tx_master_change.send(MainLoopEvent::master_change(msg)).unwrap();
 
-    Ok(())
+    Ok(true)
+}
+/// Ask master address to a sentinel.
+fn ask_master_addr(
+    redis_sentinel_addr: &str,
+    group_name: &str,
+    timeout: time::Duration,
+) -> Result<String, RedisError> {
+    let sentinel_stream = create_redis_stream_connection_timeout(redis_sentinel_addr, timeout)?;
+    let mut sentinel_connector = RedisConnector::new(Box::new(sentinel_stream));
+
+    sentinel_connector.get_master_addr(group_name)
+}
+
+/// Ask master address to all sentinels in parallel.
+/// Return address reported by at least quorum sentinels.
+fn get_master_addr_with_quorum(
+    sentinels_list: &[String],
+    group_name: &str,
+    quorum: usize,
+    timeout: time::Duration,
+) -> Option<String> {
+    let replies: Vec<(String, Result<String, RedisError>)> = thread::scope(|scope| {
+        let handles: Vec<_> = sentinels_list
+            .iter()
+            .map(|addr| {
+                scope.spawn(move || (addr.clone(), ask_master_addr(addr, group_name, timeout)))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().ok())
+            .collect()
+    });
+
+    elect_master_addr(&replies, quorum)
 }
+
+/// Return master address reported by at least quorum sentinels.
+/// Sentinels that disagree with elected address are logged.
+pub fn elect_master_addr(
+    replies: &[(String, Result<String, RedisError>)],
+    quorum: usize,
+) -> Option<String> {
+    let mut votes: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for (_, reply) in replies {
+        if let Ok(master_addr) = reply {
+            *votes.entry(master_addr.as_str()).or_default() += 1;
+        }
+    }
+
+    let elected = votes
+        .iter()
+        .max_by_key(|(_, count)| **count)
+        .filter(|(_, count)| **count >= quorum)
+        .map(|(addr, _)| String::from(*addr));
+
+    for (sentinel_addr, reply) in replies {
+        match (reply, &elected) {
+            (Ok(master_addr), Some(elected)) if master_addr == elected => {}
+            (Ok(master_addr), _) => warn!(
+                "Sentinel {} reports master {} of {} sentinels (quorum {})",
+                sentinel_addr,
+                master_addr,
+                votes[master_addr.as_str()],
+                quorum
+            ),
+            (Err(e), _) => warn!("Sentinel {} doesn't report master: {}", sentinel_addr, e),
+        }
+    }
+
+    elected
+}
+
 /// Main loop to watch sentinel.
 fn watch_sentinel_loop(
     tx_master_change: Sender<MainLoopEvent>,
-    sentinels_list: Vec<String>,
+    mut sentinels: Sentinels,
     group_name: String,
-    check_freqency: u64
+    timeout: ConfigTimeout
 ) -> Result<(), RedisError> {
-    let mut redis_master_addr = String::new();
-    let duration = time::Duration::from_millis(check_freqency);
-
-    // Iterate on sentinel list in case of lost sentinel
-    for redis_sentinel_addr in sentinels_list {
-        let sentinel_stream = create_redis_stream_connection_blocking(&redis_sentinel_addr)?;
-        let mut sentinel_connector = RedisConnector::new(Box::new(sentinel_stream));
-        let result_new_redis_master_addr = sentinel_connector.get_master_addr(&group_name);
-
-        if let Err(e) = result_new_redis_master_addr {
-            error!("Master group not found or network connection issue.");
-            return Err(e);
-        }
+    let mut duration = time::Duration::from_millis(sentinels.check_freqency);
+    let mut query_timeout = time::Duration::from_millis(timeout.sentinels);
+    let mut watcher = SentinelWatcher {
+        tx_role_check: start_role_checker(tx_master_change.clone()),
+        tx_master_change,
+        group_name,
+        role_check_timeout: timeout.master_role_check,
+        sentinel_list: SentinelList::new(
+            sentinels.address.clone(),
+            time::Duration::from_millis(sentinels.discovery_expire),
+        ),
+    };
+    let mut tried_sentinels = BTreeSet::new();
+
+    // Iterate on sentinel list (from config and discovered) in case of lost sentinel.
+    // Iterate by address, list can change during pass (discovery, expire).
+    loop {
+        let redis_sentinel_addr = match watcher.sentinel_list.next_address(&tried_sentinels) {
+            Some(addr) => addr,
+            None => {
+                error!("No sentinel available, retry all sentinels in {}ms.", duration.as_millis());
 
-        let new_redis_master_addr = result_new_redis_master_addr.unwrap();
+                thread::sleep(duration);
+
+                if apply_sentinels_reload(&mut sentinels, &mut query_timeout, &mut watcher) {
+                    info!("Sentinels config reloaded.");
+                    duration = time::Duration::from_millis(sentinels.check_freqency);
+                }
+
+                tried_sentinels.clear();
+                continue;
+            }
+        };
+
+        tried_sentinels.insert(redis_sentinel_addr.clone());
+
+        let (sentinels_reply, master_addr_reply) = match ask_sentinels_and_master_addr(
+            &redis_sentinel_addr,
+            &watcher.group_name,
+            query_timeout,
+        ) {
+            Ok(replies) => replies,
+            Err(e) => {
+                warn!("Can't query sentinel {}: {}", &redis_sentinel_addr, e);
+                continue;
+            }
+        };
+
+        update_sentinel_list(&redis_sentinel_addr, &mut watcher, sentinels_reply);
+
+        // Role checker can change master beside this loop
+        let redis_master_addr = current_master();
+        let new_redis_master_addr = if sentinels.quorum > 0 {
+            match get_master_addr_with_quorum(
+                &watcher.sentinel_list.addresses(),
+                &watcher.group_name,
+                sentinels.quorum,
+                query_timeout,
+            ) {
+                Some(addr) => addr,
+                None => {
+                    error!(
+                        "No master address reported by {} sentinels, keep master '{}'.",
+                        sentinels.quorum, &redis_master_addr
+                    );
+                    redis_master_addr.clone()
+                }
+            }
+        } else {
+            match master_addr_reply {
+                Ok(addr) => addr,
+                Err(e) => {
+                    error!(
+                        "Sentinel {} doesn't report master of {}: {}",
+                        &redis_sentinel_addr, &watcher.group_name, e
+                    );
+                    continue;
+                }
+            }
+        };
 
         // If master change, create notification.
         if new_redis_master_addr != redis_master_addr {
             send_notification(
                 &new_redis_master_addr,
                 &redis_master_addr,
-                &group_name,
-                &tx_master_change,
+                &watcher.group_name,
+                &watcher.tx_master_change,
+                watcher.role_check_timeout,
             )?;
-
-            redis_master_addr = new_redis_master_addr;
         }
 
         info!("Connect to new sentinel {}.", &redis_sentinel_addr);
 
         let mut sentinel_subscription =
-            create_redis_subscription_switch_master(&redis_sentinel_addr)?;
+            match create_redis_subscription_switch_master(
+                &redis_sentinel_addr,
+                subscription_patterns(&sentinels.events),
+            ) {
+                Ok(subscription) => subscription,
+                Err(e) => {
+                    warn!("Can't subscribe to sentinel {}: {}", &redis_sentinel_addr, e);
+                    continue;
+                }
+            };
+        let mut last_discovery = Instant::now();
 
         'sentinel_pool: loop {
             match sentinel_subscription.pool() {
-                Ok(data) => manage_subscription_data(data, &tx_master_change)?,
-                Err(e) => {
-                    if let ErrorKind::IoError = e.kind() {
-                        if let std::io::ErrorKind::BrokenPipe = e.io_error_kind().unwrap() {
-                            warn!(
-                                "Lost connection with sentinel {}!", &redis_sentinel_addr
-                            );
-
-                            break 'sentinel_pool;
-                        }
+                Ok(data) => {
+                    if let Err(e) = manage_subscription_data(data, &mut watcher) {
+                        warn!("Invalid message from sentinel {}: {}", &redis_sentinel_addr, e);
                     }
+                }
+                Err(e) => {
+                    if let Err(e) = manage_redis_subscription_error(e) {
+                        warn!(
+                            "Lost connection with sentinel {}: {}", &redis_sentinel_addr, e
+                        );
 
-                    manage_redis_subscription_error(e)?;
+                        break 'sentinel_pool;
+                    }
                 }
             };
+
+            if last_discovery.elapsed() >= duration {
+                if apply_sentinels_reload(&mut sentinels, &mut query_timeout, &mut watcher) {
+                    info!("Sentinels config reloaded, reconnect to first sentinel.");
+
+                    duration = time::Duration::from_millis(sentinels.check_freqency);
+                    tried_sentinels.clear();
+
+                    break 'sentinel_pool;
+                }
+
+                discover_sentinels(&redis_sentinel_addr, &mut watcher, query_timeout);
+                last_discovery = Instant::now();
+            }
         }
-        
+
         thread::sleep(duration);
     }
-
-    Ok(())
 }
 
 /// Watch sentinel and send data to Redis or client.
@@ -268,13 +772,20 @@ pub fn watch_sentinel(
         return Err(RedisError::from_message("Sentinel list empty."));
     }
 
-    let check_freqency = sentinels.check_freqency;
-    let sentinels_list = sentinels.address.clone();
+    if sentinels.quorum > sentinels.address.len() {
+        error!("Sentinel quorum greater than number of sentinels.");
+        return Err(RedisError::from_message(
+            "Sentinel quorum greater than number of sentinels.",
+        ));
+    }
+
+    let sentinels = sentinels.clone();
+    let timeout = config.timeout.clone();
 
-    debug!("Check state of sentinel every {}ms", check_freqency);
+    debug!("Check state of sentinel every {}ms", sentinels.check_freqency);
 
     thread::spawn(move || {
-        let status = watch_sentinel_loop(tx_master_change, sentinels_list, group_name, check_freqency);
+        let status = watch_sentinel_loop(tx_master_change, sentinels, group_name, timeout);
 
         if let Err(e) = status {
             error!("Error when get sentinel status {}", e);
@@ -0,0 +1,77 @@
+use crate::app::messages::MainLoopEvent;
+use crate::redis::sentinel::discovery::SentinelList;
+use crate::redis::sentinel::{elect_master_addr, manage_subscription_data, SentinelWatcher};
+use crate::redis::types::{RedisError, RedisValue};
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn reply(sentinel: &str, master: &str) -> (String, Result<String, RedisError>) {
+    (String::from(sentinel), Ok(String::from(master)))
+}
+
+#[test]
+fn elect_master_addr_with_quorum() {
+    let replies = vec![
+        reply("s1:26379", "10.0.0.1:6379"),
+        reply("s2:26379", "10.0.0.2:6379"),
+        reply("s3:26379", "10.0.0.1:6379"),
+    ];
+
+    assert_eq!(
+        elect_master_addr(&replies, 2),
+        Some(String::from("10.0.0.1:6379"))
+    );
+}
+
+#[test]
+fn elect_master_addr_without_quorum() {
+    let replies = vec![
+        reply("s1:26379", "10.0.0.1:6379"),
+        reply("s2:26379", "10.0.0.2:6379"),
+        (
+            String::from("s3:26379"),
+            Err(RedisError::from_message("Connection refused")),
+        ),
+    ];
+
+    assert_eq!(elect_master_addr(&replies, 2), None);
+}
+
+fn bulk(value: &str) -> RedisValue {
+    RedisValue::BulkString(value.as_bytes().to_vec())
+}
+
+#[test]
+fn manage_psubscribe_confirmation_and_pmessage() {
+    let (tx, rx) = mpsc::channel::<MainLoopEvent>();
+    let mut watcher = SentinelWatcher {
+        tx_master_change: tx,
+        group_name: String::from("mymaster"),
+        role_check_timeout: 0,
+        sentinel_list: SentinelList::new(Vec::new(), Duration::from_secs(60)),
+        tx_role_check: mpsc::channel().0,
+    };
+
+    let confirmation = RedisValue::Array(vec![
+        bulk("psubscribe"),
+        bulk("+sdown"),
+        RedisValue::Integer(1),
+    ]);
+
+    assert!(manage_subscription_data(confirmation, &mut watcher).is_ok());
+    assert!(rx.try_recv().is_err());
+
+    let message = RedisValue::Array(vec![
+        bulk("pmessage"),
+        bulk("+sdown"),
+        bulk("+sdown"),
+        bulk("master mymaster 127.0.0.1 6379"),
+    ]);
+
+    assert!(manage_subscription_data(message, &mut watcher).is_ok());
+    assert!(rx.try_recv().unwrap().sentinel_event.is_some());
+
+    let truncated = RedisValue::Array(vec![bulk("pmessage"), bulk("+sdown")]);
+
+    assert!(manage_subscription_data(truncated, &mut watcher).is_err());
+}
@@ -0,0 +1,154 @@
+//! This module contains split brain detection.
+//!
+//! Role of every known node of group (master and replicas from Sentinel, or static nodes) is
+//! checked periodically. When more than one node claims master, an alert is raised and, depending
+//! on policy, write commands are refused until only one master remains.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::config::{Config, SplitBrainPolicy};
+use crate::metrics;
+use crate::redis::encoder::Command;
+use crate::redis::node::create_redis_stream_connection_timeout;
+use crate::redis::sentinel::discovery::parse_sentinels_reply;
+use crate::redis::sentinel::known_sentinels;
+use crate::redis::standalone::find_masters;
+use crate::redis::types::RedisError;
+use crate::redis::RedisConnector;
+use log::{debug, error, info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{thread, time};
+
+/// Several nodes claim master and policy refuses writes.
+static WRITES_REFUSED: AtomicBool = AtomicBool::new(false);
+
+/// Write commands must be refused.
+pub fn writes_refused() -> bool {
+    WRITES_REFUSED.load(Ordering::Relaxed)
+}
+
+/// Return true if more than one node claims master.
+pub fn is_split_brain(masters: &[String]) -> bool {
+    masters.len() > 1
+}
+
+/// Ask master and replicas of group to a sentinel.
+fn ask_group_nodes(
+    redis_sentinel_addr: &str,
+    group_name: &str,
+    timeout: time::Duration,
+) -> Result<Vec<String>, RedisError> {
+    let sentinel_stream = create_redis_stream_connection_timeout(redis_sentinel_addr, timeout)?;
+    let mut sentinel_connector = RedisConnector::new(Box::new(sentinel_stream));
+
+    let mut nodes = vec![sentinel_connector.get_master_addr(group_name)?];
+    let reply =
+        sentinel_connector.execute(&Command::new("SENTINEL").arg("REPLICAS").arg(group_name))?;
+
+    nodes.extend(parse_sentinels_reply(&reply));
+
+    Ok(nodes)
+}
+
+/// Return sentinels to ask nodes of group: live list of sentinel watcher (seeds and discovered),
+/// or seeds until watcher gets first reply.
+fn group_sentinels(config: &Config) -> Vec<String> {
+    match known_sentinels() {
+        sentinels if sentinels.is_empty() => config
+            .sentinels
+            .as_ref()
+            .map(|s| s.address.clone())
+            .unwrap_or_default(),
+        sentinels => sentinels,
+    }
+}
+
+/// Return all known nodes of group, from first sentinel that replies.
+pub fn get_group_nodes(
+    config: &Config,
+    sentinels: &[String],
+    timeout: time::Duration,
+) -> Vec<String> {
+    if config.sentinels.is_none() {
+        return config.nodes.clone().unwrap_or_default();
+    }
+
+    for redis_sentinel_addr in sentinels {
+        match ask_group_nodes(redis_sentinel_addr, &config.group_name, timeout) {
+            Ok(nodes) => return nodes,
+            Err(e) => warn!(
+                "Can't get nodes of group from sentinel {}: {}",
+                redis_sentinel_addr, e
+            ),
+        }
+    }
+
+    Vec::new()
+}
+
+/// Check role of nodes and update flag of refused writes (policy refuse_writes).
+/// `detected` is result of previous check. Return true if split brain is detected.
+pub fn check_split_brain(
+    nodes: &[String],
+    timeout: time::Duration,
+    policy: &SplitBrainPolicy,
+    detected: bool,
+    writes_refused: &AtomicBool,
+) -> bool {
+    let masters = find_masters(nodes, timeout);
+
+    debug!("Nodes {:?} have role master", masters);
+
+    if is_split_brain(&masters) {
+        metrics::SPLIT_BRAIN_DETECTED.increment();
+        error!(
+            "Split brain detected, several nodes claim master: {}",
+            masters.join(", ")
+        );
+    } else if detected {
+        info!("Split brain resolved, master is {}", masters.join(", "));
+    }
+
+    let detected = is_split_brain(&masters);
+
+    if *policy == SplitBrainPolicy::RefuseWrites {
+        writes_refused.store(detected, Ordering::Relaxed);
+    }
+
+    detected
+}
+
+/// Main loop to check role of nodes.
+fn watch_split_brain_loop(config: Config, check_freqency: u64, policy: SplitBrainPolicy) {
+    let duration = time::Duration::from_millis(check_freqency);
+    let timeout = time::Duration::from_millis(config.timeout.sentinels);
+    let mut detected = false;
+
+    loop {
+        thread::sleep(duration);
+
+        let nodes = get_group_nodes(&config, &group_sentinels(&config), timeout);
+
+        detected = check_split_brain(&nodes, timeout, &policy, detected, &WRITES_REFUSED);
+    }
+}
+
+/// Check role of nodes in a new thread, if enabled in config.
+pub fn watch_split_brain(config: &Config) {
+    let split_brain = match config.split_brain.as_ref() {
+        Some(split_brain) => split_brain.clone(),
+        None => return,
+    };
+
+    if config.sentinels.is_none() && config.nodes.is_none() {
+        warn!("Split brain detection needs sentinels or nodes");
+        return;
+    }
+
+    let config = config.clone();
+
+    thread::spawn(move || {
+        watch_split_brain_loop(config, split_brain.check_freqency, split_brain.policy)
+    });
+}
@@ -0,0 +1,129 @@
+use crate::config::{Config, SplitBrainPolicy};
+use crate::redis::splitbrain::{check_split_brain, get_group_nodes, is_split_brain};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+const MASTER: &[u8] = b"+PONG\r\n*3\r\n$6\r\nmaster\r\n:0\r\n*0\r\n";
+const REPLICA: &[u8] =
+    b"+PONG\r\n*5\r\n$5\r\nslave\r\n$9\r\n127.0.0.1\r\n:6379\r\n$9\r\nconnected\r\n:0\r\n";
+
+/// Start a node that sends one reply per request read, on each connection.
+fn fake_node(replies: Vec<&'static [u8]>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(s) => s,
+                Err(_) => return,
+            };
+            let mut buf = [0; 1024];
+
+            for reply in &replies {
+                if stream.read(&mut buf).unwrap_or(0) == 0 {
+                    break;
+                }
+
+                let _ = stream.write_all(reply);
+            }
+        }
+    });
+
+    addr
+}
+
+/// Address where nothing listens.
+fn unreachable_node() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+    listener.local_addr().unwrap().to_string()
+}
+
+#[test]
+fn split_brain_when_several_masters() {
+    let master = String::from("10.0.0.1:6379");
+    let other = String::from("10.0.0.2:6379");
+
+    assert!(!is_split_brain(&[]));
+    assert!(!is_split_brain(std::slice::from_ref(&master)));
+    assert!(is_split_brain(&[master, other]));
+}
+
+#[test]
+fn check_split_brain_refuse_writes_until_resolved() {
+    let writes_refused = AtomicBool::new(false);
+    let policy = SplitBrainPolicy::RefuseWrites;
+
+    let nodes = vec![fake_node(vec![MASTER]), fake_node(vec![MASTER])];
+
+    assert!(check_split_brain(
+        &nodes,
+        TIMEOUT,
+        &policy,
+        false,
+        &writes_refused
+    ));
+    assert!(writes_refused.load(Ordering::Relaxed));
+
+    // Old master is back as replica
+    let nodes = vec![fake_node(vec![MASTER]), fake_node(vec![REPLICA])];
+
+    assert!(!check_split_brain(
+        &nodes,
+        TIMEOUT,
+        &policy,
+        true,
+        &writes_refused
+    ));
+    assert!(!writes_refused.load(Ordering::Relaxed));
+}
+
+#[test]
+fn check_split_brain_ignore_unreachable_node() {
+    let writes_refused = AtomicBool::new(false);
+    let nodes = vec![fake_node(vec![MASTER]), unreachable_node()];
+
+    assert!(!check_split_brain(
+        &nodes,
+        TIMEOUT,
+        &SplitBrainPolicy::RefuseWrites,
+        false,
+        &writes_refused
+    ));
+    assert!(!writes_refused.load(Ordering::Relaxed));
+
+    // Policy alert never refuses writes
+    let nodes = vec![fake_node(vec![MASTER]), fake_node(vec![MASTER])];
+
+    assert!(check_split_brain(
+        &nodes,
+        TIMEOUT,
+        &SplitBrainPolicy::Alert,
+        false,
+        &writes_refused
+    ));
+    assert!(!writes_refused.load(Ordering::Relaxed));
+}
+
+#[test]
+fn group_nodes_from_next_sentinel() {
+    let config: Config = serde_yaml2::from_str(
+        "bind: 127.0.0.1:6578\ngroup_name: mymaster\nsentinels:\n  address: ['127.0.0.1:1']\n",
+    )
+    .unwrap();
+    // Discovered sentinel replies when seed is unreachable
+    let sentinel = fake_node(vec![
+        b"*2\r\n$8\r\n10.0.0.1\r\n$4\r\n6379\r\n",
+        b"*1\r\n*4\r\n$2\r\nip\r\n$8\r\n10.0.0.2\r\n$4\r\nport\r\n$4\r\n6379\r\n",
+    ]);
+
+    assert_eq!(
+        get_group_nodes(&config, &[unreachable_node(), sentinel], TIMEOUT),
+        vec![String::from("10.0.0.1:6379"), String::from("10.0.0.2:6379")]
+    );
+}
@@ -0,0 +1,211 @@
+//! This module contains routine to watch a static master or a list of nodes, without Sentinel.
+//!
+//! Master is checked periodically (PING and ROLE). When check fails, all nodes are resolved again
+//! from DNS and checked: if exactly one node has role master, clients follow it like after a
+//! failover. If several nodes claim to be master (split brain), current master is kept.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::app::messages::MainLoopEvent;
+use crate::config::Config;
+use crate::metrics;
+use crate::redis::encoder::Command;
+use crate::redis::node::create_redis_stream_connection_timeout;
+use crate::redis::pipeline::Pipeline;
+use crate::redis::sentinel::MasterChangeNotification;
+use crate::redis::types::{RedisError, RedisValue};
+use crate::redis::RedisConnector;
+use log::{debug, error, info, warn};
+use std::net::ToSocketAddrs;
+use std::sync::mpsc::Sender;
+use std::{thread, time};
+
+/// Resolve "host:port" to "ip:port".
+pub fn resolve_master_addr(master: &str) -> Result<String, RedisError> {
+    let mut addrs = master
+        .to_socket_addrs()
+        .map_err(RedisError::from_io_error)?;
+
+    match addrs.next() {
+        Some(addr) => Ok(addr.to_string()),
+        None => Err(RedisError::from_message(&format!(
+            "Can't resolve {}",
+            master
+        ))),
+    }
+}
+
+/// Check that master replies and has role master.
+fn check_master(redis_addr: &str, timeout: time::Duration) -> Result<(), RedisError> {
+    let stream = create_redis_stream_connection_timeout(redis_addr, timeout)?;
+    let mut connector = RedisConnector::new(Box::new(stream));
+    let pipeline = Pipeline::new()
+        .add(Command::new("PING"))
+        .add(Command::new("ROLE"));
+
+    let mut replies = connector.execute_pipeline(&pipeline)?.into_iter();
+
+    replies.next().unwrap_or(Ok(RedisValue::Nil))?;
+
+    match replies.next().unwrap_or(Ok(RedisValue::Nil))? {
+        RedisValue::Array(role)
+            if role.first() == Some(&RedisValue::BulkString(b"master".to_vec())) =>
+        {
+            Ok(())
+        }
+        reply => Err(RedisError::from_message(&format!(
+            "{} is not a master: {:?}",
+            redis_addr, reply
+        ))),
+    }
+}
+
+/// Return addresses of nodes that reply and have role master.
+pub fn find_masters(nodes: &[String], timeout: time::Duration) -> Vec<String> {
+    let mut masters: Vec<String> = Vec::new();
+
+    for node in nodes {
+        let addr = match resolve_master_addr(node) {
+            Ok(addr) => addr,
+            Err(e) => {
+                warn!("Can't resolve node {}: {}", node, e);
+                continue;
+            }
+        };
+
+        match check_master(&addr, timeout) {
+            Ok(()) => {
+                info!("Node {} ({}) has role master", node, addr);
+
+                if !masters.contains(&addr) {
+                    masters.push(addr);
+                }
+            }
+            Err(e) => debug!("Node {} ({}) is not master: {}", node, addr, e),
+        }
+    }
+
+    masters
+}
+
+/// Return master if exactly one node has role master.
+pub fn select_master(masters: &[String]) -> Result<String, RedisError> {
+    match masters {
+        [] => Err(RedisError::from_message("No node has role master")),
+        [master] => Ok(master.clone()),
+        _ => Err(RedisError::from_message(&format!(
+            "Several nodes have role master (split brain): {}",
+            masters.join(", ")
+        ))),
+    }
+}
+
+/// Main loop to watch master.
+fn watch_master_loop(
+    tx_master_change: Sender<MainLoopEvent>,
+    nodes: Vec<String>,
+    mut redis_master_addr: String,
+    group_name: String,
+    check_freqency: u64,
+    timeout: u64,
+) {
+    let duration = time::Duration::from_millis(check_freqency);
+    let timeout = time::Duration::from_millis(timeout);
+
+    loop {
+        thread::sleep(duration);
+
+        let error = match check_master(&redis_master_addr, timeout) {
+            Ok(()) => {
+                debug!("Master {} is healthy", redis_master_addr);
+                continue;
+            }
+            Err(e) => e,
+        };
+
+        metrics::MASTER_HEALTH_CHECK_FAILED.increment();
+        warn!(
+            "Health check of master {} failed: {}",
+            redis_master_addr, error
+        );
+
+        // Address may have changed (e.g. container restarted with new ip) or replica promoted.
+        let new_redis_master_addr = match select_master(&find_masters(&nodes, timeout)) {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!("Keep master {}: {}", redis_master_addr, e);
+                continue;
+            }
+        };
+
+        if new_redis_master_addr == redis_master_addr {
+            continue;
+        }
+
+        // Confirm role again just before switching.
+        if let Err(e) = check_master(&new_redis_master_addr, timeout) {
+            warn!("New master {} is not ready: {}", new_redis_master_addr, e);
+            continue;
+        }
+
+        info!(
+            "Master moves from {} to {}",
+            redis_master_addr, new_redis_master_addr
+        );
+
+        let msg = MasterChangeNotification {
+            new: new_redis_master_addr.clone(),
+            old: redis_master_addr,
+            group_name: group_name.clone(),
+        };
+
+        if tx_master_change
+            .send(MainLoopEvent::master_change(msg))
+            .is_err()
+        {
+            return;
+        }
+
+        redis_master_addr = new_redis_master_addr;
+    }
+}
+
+/// Find master and watch it in a new thread.
+/// With a static master, it's only resolved. With a list of nodes, exactly one must be master.
+/// Return address of master.
+pub fn watch_master(
+    config: &Config,
+    tx_master_change: Sender<MainLoopEvent>,
+) -> Result<String, RedisError> {
+    let timeout = config.timeout.sentinels;
+
+    let (nodes, redis_master_addr) = match (config.nodes.as_ref(), config.master.as_ref()) {
+        (Some(nodes), _) => {
+            let masters = find_masters(nodes, time::Duration::from_millis(timeout));
+
+            (nodes.clone(), select_master(&masters)?)
+        }
+        (None, Some(master)) => (vec![master.clone()], resolve_master_addr(master)?),
+        (None, None) => return Err(RedisError::from_message("No master or nodes in config")),
+    };
+
+    info!("Use master {} from {}", redis_master_addr, nodes.join(", "));
+
+    let check_freqency = config.master_check_freqency;
+    let group_name = String::from(&config.group_name);
+    let addr = redis_master_addr.clone();
+
+    thread::spawn(move || {
+        watch_master_loop(
+            tx_master_change,
+            nodes,
+            addr,
+            group_name,
+            check_freqency,
+            timeout,
+        )
+    });
+
+    Ok(redis_master_addr)
+}
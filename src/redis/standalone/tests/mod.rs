@@ -0,0 +1,20 @@
+use crate::redis::standalone::{resolve_master_addr, select_master};
+
+#[test]
+fn resolve_static_master() {
+    assert_eq!(
+        resolve_master_addr("127.0.0.1:6379").unwrap(),
+        String::from("127.0.0.1:6379")
+    );
+    assert!(resolve_master_addr("127.0.0.1").is_err());
+}
+
+#[test]
+fn select_master_from_nodes() {
+    let master = String::from("10.0.0.1:6379");
+    let other = String::from("10.0.0.2:6379");
+
+    assert_eq!(select_master(std::slice::from_ref(&master)).unwrap(), master);
+    assert!(select_master(&[]).is_err());
+    assert!(select_master(&[master, other]).is_err());
+}
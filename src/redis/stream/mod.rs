@@ -10,12 +10,16 @@ pub trait RedisStream {
     /// Write data on lib.redis.stream.network.
     fn write(&mut self, data: &[u8]) -> std::io::Result<()>;
 
-    /// Get byte from stream.
-    fn get(&mut self) -> std::io::Result<Option<u8>>;
-
     /// Get X byte from stream.
     fn get_data(&mut self, size: usize) -> std::io::Result<Vec<u8>>;
 
-    /// Search in stream pattern and return data until pattern (pattern included).
-    fn get_until(&mut self, pattern: &[u8]) -> std::io::Result<Vec<u8>>;
+    /// Read available data in internal buffer.
+    /// Return number of bytes read (0 if no data available).
+    fn fill(&mut self) -> std::io::Result<usize>;
+
+    /// Data read but not yet consumed.
+    fn buffer(&self) -> &[u8];
+
+    /// Remove size bytes from start of internal buffer.
+    fn consume(&mut self, size: usize);
 }
@@ -1,11 +1,14 @@
 //! This module contain abstract type of network.
 //!
+#[cfg(test)]
+pub mod tests;
 
 use crate::redis::stream::RedisStream;
 use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Write;
 use std::net::TcpStream;
+use std::os::unix::io::{AsRawFd, RawFd};
 
 const BUFFER_SIZE: usize = 2048;
 
@@ -16,6 +19,8 @@ pub struct NetworkStream {
     stream: TcpStream,
     /// Internal buffer.
     buf: Vec<u8>,
+    /// Data not yet written because socket is full (nonblocking).
+    output: Vec<u8>,
 }
 
 impl NetworkStream {
@@ -23,9 +28,41 @@ impl NetworkStream {
         NetworkStream {
             stream,
             buf: Vec::with_capacity(BUFFER_SIZE),
+            output: Vec::new(),
         }
     }
 
+    /// Write data waiting in output buffer, as much as socket accepts.
+    /// Blocking socket writes all data.
+    pub fn flush_output(&mut self) -> std::io::Result<()> {
+        let mut written = 0;
+
+        while written < self.output.len() {
+            match self.stream.write(&self.output[written..]) {
+                Ok(0) => {
+                    return Err(std::io::Error::new(
+                        ErrorKind::WriteZero,
+                        "Socket doesn't accept data",
+                    ))
+                }
+                Ok(len) => written += len,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                // Socket is full, retry later
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.output.drain(..written);
+
+        Ok(())
+    }
+
+    /// Some data are not yet written.
+    pub fn has_pending_output(&self) -> bool {
+        !self.output.is_empty()
+    }
+
     /// Read data from TcpStream and update buffer size.
     fn read(&mut self) -> std::io::Result<()> {
         let mut buf = [0; BUFFER_SIZE];
@@ -53,62 +90,31 @@ impl NetworkStream {
             }
         }
     }
+}
 
-    /// Search something in current buffer.
-    fn search_in_buffer(&self, start: usize, pattern: &[u8]) -> Option<usize> {
-        let mut pattern_index = 0;
-        let end = self.buf.len();
-
-        for index in start..end {
-            match self.buf.get(index) {
-                Some(c) => {
-                    if *c == pattern[pattern_index] {
-                        // That match, check next char
-                        pattern_index = pattern_index + 1;
-
-                        // Out of pattern, that means we find it !
-                        if pattern_index >= pattern.len() {
-                            return Some(start + index);
-                        }
-                    } else {
-                        pattern_index = 0;
-                    }
-                }
-                None => return None,
-            }
-        }
-
-        None
+impl AsRawFd for NetworkStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.stream.as_raw_fd()
     }
 }
 
 impl RedisStream for NetworkStream {
     fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
-        match self.stream.write(data) {
-            Ok(len) => {
-                if len != data.len() {
-                    Err(std::io::Error::new(
-                        ErrorKind::WriteZero,
-                        format!("Write only {} bytes on {}", len, data.len()),
-                    ))
-                } else {
-                    Ok(())
-                }
-            }
-            Err(e) => Err(e),
+        // Keep order: data are written after data waiting in output buffer
+        self.output.extend_from_slice(data);
+        self.flush_output()?;
+
+        // Blocking socket with timeout can't wait a retry
+        if self.has_pending_output() && matches!(self.stream.write_timeout(), Ok(Some(_))) {
+            self.output.clear();
+
+            return Err(std::io::Error::new(
+                ErrorKind::TimedOut,
+                "Write timeout reached",
+            ));
         }
-    }
 
-    fn get(&mut self) -> std::io::Result<Option<u8>> {
-        if self.buf.is_empty() {
-            self.read()?;
-        }
-
-        if self.buf.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(self.buf.remove(0)))
-        }
+        Ok(())
     }
 
     fn get_data(&mut self, size: usize) -> std::io::Result<Vec<u8>> {
@@ -139,28 +145,19 @@ impl RedisStream for NetworkStream {
         Ok(ret_buf)
     }
 
-    fn get_until(&mut self, pattern: &[u8]) -> std::io::Result<Vec<u8>> {
-        let mut index = 0;
-
-        loop {
-            match self.search_in_buffer(index, pattern) {
-                // pos = 2 pattern = '\r\n'
-                // That mean pos is '\r' we must substract 1 to have right size
-                Some(pos) => return self.get_data(pos + pattern.len() - 1),
-                None => {
-                    let old_buf_size = self.buf.len();
-
-                    // Try to get new data
-                    self.read()?;
-
-                    if old_buf_size == self.buf.len() {
-                        // No more data read, stop search.
-                        return Ok(Vec::new());
-                    } else {
-                        index = old_buf_size;
-                    }
-                }
-            }
-        }
+    fn fill(&mut self) -> std::io::Result<usize> {
+        let old_buf_size = self.buf.len();
+
+        self.read()?;
+
+        Ok(self.buf.len() - old_buf_size)
+    }
+
+    fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    fn consume(&mut self, size: usize) {
+        self.buf.drain(..size.min(self.buf.len()));
     }
 }
@@ -0,0 +1,34 @@
+use crate::redis::stream::network::NetworkStream;
+use crate::redis::stream::RedisStream;
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+
+#[test]
+fn keep_data_not_written_on_full_socket() -> std::io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let stream = TcpStream::connect(listener.local_addr()?)?;
+    let (mut peer, _) = listener.accept()?;
+
+    stream.set_nonblocking(true)?;
+
+    let mut stream = NetworkStream::new(stream);
+    let data = vec![b'a'; 16 * 1024 * 1024];
+
+    // Peer doesn't read, socket is full
+    stream.write(&data)?;
+    assert!(stream.has_pending_output());
+
+    let mut received = Vec::new();
+    let mut buf = vec![0; 1024 * 1024];
+
+    while received.len() < data.len() {
+        let len = peer.read(&mut buf)?;
+        received.extend_from_slice(&buf[..len]);
+        stream.flush_output()?;
+    }
+
+    assert!(!stream.has_pending_output());
+    assert_eq!(received, data);
+
+    Ok(())
+}
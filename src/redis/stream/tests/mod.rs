@@ -10,6 +10,8 @@ pub struct TestRedisStream {
     /// Internal buffer.
     read_buf: Vec<u8>,
     pub write_buf: Vec<u8>,
+    /// Data received by next calls of fill (empty is no data available).
+    chunks: Vec<Vec<u8>>,
 }
 
 impl TestRedisStream {
@@ -17,34 +19,17 @@ impl TestRedisStream {
         TestRedisStream {
             read_buf: buf,
             write_buf: Vec::new(),
+            chunks: Vec::new(),
         }
     }
 
-    /// Search something in current buffer.
-    fn search_in_buffer(&self, start: usize, pattern: &[u8]) -> Option<usize> {
-        let mut pattern_index = 0;
-        let end = self.read_buf.len();
-
-        for index in start..end {
-            match self.read_buf.get(index) {
-                Some(c) => {
-                    if *c == pattern[pattern_index] {
-                        // That match, check next char
-                        pattern_index = pattern_index + 1;
-
-                        // Out of pattern, that means we find it !
-                        if pattern_index >= pattern.len() {
-                            return Some(start + index);
-                        }
-                    } else {
-                        pattern_index = 0;
-                    }
-                }
-                None => return None,
-            }
+    /// Create stream that receives data in several parts, like a nonblocking socket.
+    pub fn with_chunks(chunks: Vec<&[u8]>) -> Self {
+        TestRedisStream {
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            chunks: chunks.iter().rev().map(|c| c.to_vec()).collect(),
         }
-
-        None
     }
 }
 
@@ -55,17 +40,6 @@ impl RedisStream for TestRedisStream {
         Ok(())
     }
 
-    fn get(&mut self) -> std::io::Result<Option<u8>> {
-        if self.read_buf.is_empty() {
-            return Err(std::io::Error::new(
-                ErrorKind::BrokenPipe,
-                "Server close socket",
-            ));
-        }
-
-        Ok(Some(self.read_buf.remove(0)))
-    }
-
     fn get_data(&mut self, size: usize) -> std::io::Result<Vec<u8>> {
         if self.read_buf.len() < size {
             if self.read_buf.is_empty() {
@@ -92,25 +66,24 @@ impl RedisStream for TestRedisStream {
         Ok(ret_buf)
     }
 
-    fn get_until(&mut self, pattern: &[u8]) -> std::io::Result<Vec<u8>> {
-        let mut index = 0;
-
-        loop {
-            match self.search_in_buffer(index, pattern) {
-                // pos = 2 pattern = '\r\n'
-                // That mean pos is '\r' we must substract 1 to have right size
-                Some(pos) => return self.get_data(pos + pattern.len() - 1),
-                None => {
-                    let old_buf_size = self.read_buf.len();
-
-                    if old_buf_size == self.read_buf.len() {
-                        // No more data read, stop search.
-                        return Ok(Vec::new());
-                    } else {
-                        index = old_buf_size;
-                    }
-                }
+    fn fill(&mut self) -> std::io::Result<usize> {
+        match self.chunks.pop() {
+            Some(chunk) => {
+                self.read_buf.extend_from_slice(&chunk);
+                Ok(chunk.len())
             }
+            None => Err(std::io::Error::new(
+                ErrorKind::BrokenPipe,
+                "Server close socket",
+            )),
         }
     }
+
+    fn buffer(&self) -> &[u8] {
+        &self.read_buf
+    }
+
+    fn consume(&mut self, size: usize) {
+        self.read_buf.drain(..size.min(self.read_buf.len()));
+    }
 }
@@ -3,28 +3,48 @@
 #[cfg(test)]
 pub mod tests;
 
+use crate::redis::encoder::Command;
 use crate::redis::parser::read_array;
 use crate::redis::stream::RedisStream;
 use crate::redis::types::{RedisError, RedisValue};
 use std::fmt::{Debug, Error, Formatter};
 
-/// Structure when you subscribe to channel.
+/// Structure when you subscribe to channels.
 pub struct RedisSubscription {
     stream: Box<dyn RedisStream>,
-    channel: String,
+    channels: Vec<String>,
+    /// Channels are patterns (PSUBSCRIBE).
+    pattern: bool,
 }
 
 impl<'a> RedisSubscription {
-    pub fn new(stream: Box<dyn RedisStream>, channel: String) -> Self {
-        RedisSubscription { stream, channel }
+    pub fn new(stream: Box<dyn RedisStream>, channels: Vec<String>) -> Self {
+        RedisSubscription {
+            stream,
+            channels,
+            pattern: false,
+        }
+    }
+
+    /// Subscribe to channels matching patterns.
+    pub fn with_patterns(stream: Box<dyn RedisStream>, patterns: Vec<String>) -> Self {
+        RedisSubscription {
+            stream,
+            channels: patterns,
+            pattern: true,
+        }
     }
 
     /// Start subscription.
     pub fn subscribe(&mut self) -> Result<(), RedisError> {
-        let cmd = format!("SUBSCRIBE {}\r\n", &self.channel);
-        let cmd = cmd.as_bytes();
+        let name = if self.pattern {
+            "PSUBSCRIBE"
+        } else {
+            "SUBSCRIBE"
+        };
+        let cmd = Command::new(name).args(&self.channels);
 
-        if let Err(e) = self.stream.write(cmd) {
+        if let Err(e) = self.stream.write(&cmd.encode()) {
             return Err(RedisError::from_io_error(e));
         }
 
@@ -39,6 +59,10 @@ impl<'a> RedisSubscription {
 
 impl<'a> Debug for RedisSubscription {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
-        write!(fmt, "RedisSubscription(channel='{}')", &self.channel)
+        write!(
+            fmt,
+            "RedisSubscription(channels='{}')",
+            self.channels.join(",")
+        )
     }
 }
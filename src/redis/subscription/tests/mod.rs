@@ -32,7 +32,7 @@ fn test_subscription() -> Result<(), RedisError> {
 
     let box_stream: Box<dyn RedisStream> = Box::new(stream);
 
-    let mut sub = RedisSubscription::new(box_stream, String::from("truc"));
+    let mut sub = RedisSubscription::new(box_stream, vec![String::from("truc")]);
 
     sub.subscribe()?;
 
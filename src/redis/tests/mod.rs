@@ -0,0 +1,216 @@
+use crate::redis::encoder::Command;
+use crate::redis::pipeline::Pipeline;
+use crate::redis::stream::tests::TestRedisStream;
+use crate::redis::types::{ErrorKind, RedisError, RedisValue};
+use crate::redis::RedisConnector;
+
+fn connector(replies: &[u8]) -> RedisConnector {
+    RedisConnector::new(Box::new(TestRedisStream::new(replies.to_vec())))
+}
+
+#[test]
+fn execute_any_command() -> Result<(), RedisError> {
+    let mut redis = connector(b"*2\r\n:1\r\n$1\r\na\r\n");
+
+    assert_eq!(
+        redis.execute(&Command::new("CUSTOM").arg("x"))?,
+        RedisValue::Array(vec![
+            RedisValue::Integer(1),
+            RedisValue::BulkString(b"a".to_vec())
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn typed_string_commands() -> Result<(), RedisError> {
+    let mut redis = connector(b"+OK\r\n$-1\r\n:3\r\n*2\r\n$1\r\n1\r\n$-1\r\n");
+
+    redis.set("my key", "value")?;
+    assert!(!redis.set_nx("my key", "other")?);
+    assert_eq!(redis.incr("counter")?, 3);
+    assert_eq!(redis.mget(&["a", "b"])?, vec![Some(b"1".to_vec()), None]);
+
+    Ok(())
+}
+
+#[test]
+fn typed_hash_and_sorted_set_commands() -> Result<(), RedisError> {
+    let mut redis = connector(b"*4\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$1\r\n2\r\n%1\r\n$1\r\nc\r\n$1\r\n3\r\n$3\r\n1.5\r\n,2.5\r\n");
+
+    assert_eq!(
+        redis.hgetall("h")?,
+        vec![
+            (b"a".to_vec(), b"1".to_vec()),
+            (b"b".to_vec(), b"2".to_vec())
+        ]
+    );
+    assert_eq!(redis.hgetall("h")?, vec![(b"c".to_vec(), b"3".to_vec())]);
+    assert_eq!(redis.zscore("z", "m")?, Some(1.5));
+    assert_eq!(redis.zscore("z", "m")?, Some(2.5));
+
+    Ok(())
+}
+
+#[test]
+fn typed_server_commands() -> Result<(), RedisError> {
+    let mut redis =
+        connector(b"*3\r\n$6\r\nmaster\r\n:0\r\n*0\r\n*2\r\n$7\r\ntimeout\r\n$1\r\n0\r\n");
+
+    assert_eq!(redis.role()?, "master");
+    assert_eq!(
+        redis.config_get("timeout")?,
+        vec![(String::from("timeout"), String::from("0"))]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn typed_command_errors() {
+    let mut redis =
+        connector(b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n:1\r\n");
+
+    let error = redis.llen("a string").unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::WrongTypeError);
+    assert_eq!(
+        error.message(),
+        "Operation against a key holding the wrong kind of value"
+    );
+
+    assert_eq!(redis.get("key").unwrap_err().kind(), ErrorKind::TypeError);
+}
+
+#[test]
+fn error_reply_keep_code() {
+    let mut redis = connector(b"-WRONGPASS invalid username-password pair\r\n");
+
+    let error = redis
+        .execute(&Command::new("AUTH").arg("user").arg("bad"))
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::AuthenticationFailed);
+    assert_eq!(
+        error.to_reply(),
+        b"-WRONGPASS invalid username-password pair\r\n"
+    );
+}
+
+#[test]
+fn pipeline_replies_in_order() -> Result<(), RedisError> {
+    let mut redis = connector(b"+PONG\r\n-ERR unknown command\r\n$1\r\na\r\n");
+
+    let pipeline = Pipeline::new()
+        .add(Command::new("PING"))
+        .add(Command::new("FOO"))
+        .add(Command::new("GET").arg("key"));
+
+    let replies = redis.execute_pipeline(&pipeline)?;
+
+    assert_eq!(replies.len(), 3);
+    assert_eq!(
+        replies[0].as_ref().unwrap(),
+        &RedisValue::String(String::from("PONG"))
+    );
+    assert_eq!(
+        replies[1].as_ref().unwrap_err().kind(),
+        ErrorKind::ResponseError
+    );
+    assert_eq!(
+        replies[2].as_ref().unwrap(),
+        &RedisValue::BulkString(b"a".to_vec())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pipeline_transaction() -> Result<(), RedisError> {
+    let mut redis = connector(
+        b"+OK\r\n+QUEUED\r\n+QUEUED\r\n*2\r\n:1\r\n-WRONGTYPE Operation against a key\r\n",
+    );
+
+    let pipeline = Pipeline::new()
+        .atomic()
+        .add(Command::new("INCR").arg("a"))
+        .add(Command::new("LPOP").arg("a"));
+
+    let replies = redis.execute_pipeline(&pipeline)?;
+
+    assert_eq!(replies[0].as_ref().unwrap(), &RedisValue::Integer(1));
+    assert_eq!(
+        replies[1].as_ref().unwrap_err().kind(),
+        ErrorKind::WrongTypeError
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pipeline_transaction_aborted() -> Result<(), RedisError> {
+    let mut redis = connector(b"+OK\r\n+QUEUED\r\n-ERR wrong number of arguments\r\n-EXECABORT Transaction discarded because of previous errors.\r\n");
+
+    let pipeline = Pipeline::new()
+        .atomic()
+        .add(Command::new("INCR").arg("a"))
+        .add(Command::new("GET"));
+
+    let replies = redis.execute_pipeline(&pipeline)?;
+
+    assert_eq!(
+        replies[0].as_ref().unwrap_err().kind(),
+        ErrorKind::ExecAbortError
+    );
+    assert_eq!(
+        replies[1].as_ref().unwrap_err().kind(),
+        ErrorKind::ResponseError
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pipeline_transaction_not_started() -> Result<(), RedisError> {
+    let mut redis = connector(
+        b"-NOAUTH Authentication required.\r\n-NOAUTH Authentication required.\r\n-NOAUTH Authentication required.\r\n+PONG\r\n",
+    );
+
+    let pipeline = Pipeline::new()
+        .atomic()
+        .add(Command::new("INCR").arg("a"));
+
+    let replies = redis.execute_pipeline(&pipeline)?;
+
+    assert_eq!(replies.len(), 1);
+    assert_eq!(
+        replies[0].as_ref().unwrap_err().kind(),
+        ErrorKind::AuthenticationFailed
+    );
+    // Reply of next command is not a stale reply of transaction
+    redis.ping()?;
+
+    Ok(())
+}
+
+#[test]
+fn pipeline_transaction_not_started_commands_run() -> Result<(), RedisError> {
+    // MULTI is refused but authentication succeeds before commands
+    let mut redis = connector(
+        b"-NOAUTH Authentication required.\r\n:1\r\n-ERR EXEC without MULTI\r\n+PONG\r\n",
+    );
+
+    let pipeline = Pipeline::new()
+        .atomic()
+        .add(Command::new("INCR").arg("a"));
+
+    let replies = redis.execute_pipeline(&pipeline)?;
+
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].as_ref().ok(), Some(&RedisValue::Integer(1)));
+    // Reply of next command is not a stale reply of transaction
+    redis.ping()?;
+
+    Ok(())
+}
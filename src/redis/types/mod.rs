@@ -7,16 +7,47 @@ pub const REDIS_TYPE_STRING: u8 = b'+';
 pub const REDIS_TYPE_ERROR: u8 = b'-';
 pub const REDIS_TYPE_BULK_STRING: u8 = b'$';
 pub const REDIS_TYPE_ARRAY: u8 = b'*';
+/// RESP3 types.
+pub const REDIS_TYPE_NULL: u8 = b'_';
+pub const REDIS_TYPE_DOUBLE: u8 = b',';
+pub const REDIS_TYPE_BOOLEAN: u8 = b'#';
+pub const REDIS_TYPE_BLOB_ERROR: u8 = b'!';
+pub const REDIS_TYPE_VERBATIM_STRING: u8 = b'=';
+pub const REDIS_TYPE_BIG_NUMBER: u8 = b'(';
+pub const REDIS_TYPE_MAP: u8 = b'%';
+pub const REDIS_TYPE_SET: u8 = b'~';
+pub const REDIS_TYPE_ATTRIBUTE: u8 = b'|';
+pub const REDIS_TYPE_PUSH: u8 = b'>';
 
 /// Redis value get from redis.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum RedisValue {
-    /// Redis can return null string and null array.
+    /// Null bulk string (RESP2).
     Nil,
+    /// Null array (RESP2), e.g. EXEC of an aborted transaction or timeout of BLPOP.
+    NilArray,
     Integer(isize),
     String(String),
     BulkString(Vec<u8>),
     Array(Vec<RedisValue>),
+    /// RESP3 null.
+    Null,
+    /// RESP3 double, kept as sent by Redis (e.g. "1.5", "inf", "nan").
+    Double(String),
+    /// RESP3 boolean.
+    Boolean(bool),
+    /// RESP3 big number, kept as decimal string.
+    BigNumber(String),
+    /// RESP3 verbatim string: format (e.g. "txt") and data.
+    VerbatimString(String, Vec<u8>),
+    /// RESP3 map.
+    Map(Vec<(RedisValue, RedisValue)>),
+    /// RESP3 set.
+    Set(Vec<RedisValue>),
+    /// RESP3 attributes and the value they describe.
+    Attribute(Vec<(RedisValue, RedisValue)>, Box<RedisValue>),
+    /// RESP3 push (out of band data like pub/sub messages).
+    Push(Vec<RedisValue>),
 }
 
 /// An enum of all error kinds.
@@ -30,6 +61,14 @@ pub enum ErrorKind {
     BusyLoadingError,
     /// A script that was requested does not actually exist.
     NoScriptError,
+    /// Operation against a key holding the wrong kind of value.
+    WrongTypeError,
+    /// Authentication is required or credentials are wrong.
+    AuthenticationFailed,
+    /// Write command sent to a read only replica.
+    ReadOnlyError,
+    /// Reply of Redis has not the type expected by command.
+    TypeError,
     /// This kind is returned if network error.
     IoError,
     /// An error not directly return by Redis.
@@ -47,6 +86,8 @@ pub struct RedisError {
     kind: ErrorKind,
     /// Message
     message: Option<String>,
+    /// Error code sent by Redis (e.g. "WRONGPASS").
+    code: Option<String>,
 }
 
 /// Redis error.
@@ -57,6 +98,7 @@ impl RedisError {
             io_error: Some(e),
             message: None,
             kind: ErrorKind::IoError,
+            code: None,
         }
     }
 
@@ -66,6 +108,7 @@ impl RedisError {
             io_error: None,
             message: Some(String::from("No data available!")),
             kind: ErrorKind::NoDataAvailable,
+            code: None,
         }
     }
 
@@ -75,6 +118,7 @@ impl RedisError {
             io_error: None,
             message: Some(String::from(e)),
             kind: ErrorKind::OtherError,
+            code: None,
         }
     }
 
@@ -85,6 +129,9 @@ impl RedisError {
             "EXECABORT" => ErrorKind::ExecAbortError,
             "LOADING" => ErrorKind::BusyLoadingError,
             "NOSCRIPT" => ErrorKind::NoScriptError,
+            "WRONGTYPE" => ErrorKind::WrongTypeError,
+            "NOAUTH" | "WRONGPASS" => ErrorKind::AuthenticationFailed,
+            "READONLY" => ErrorKind::ReadOnlyError,
             _ => ErrorKind::OtherError,
         };
 
@@ -92,6 +139,17 @@ impl RedisError {
             io_error: None,
             message: Some(String::from(message)),
             kind,
+            code: Some(String::from(code)),
+        }
+    }
+
+    /// Reply of unexpected type.
+    pub fn from_unexpected_reply(value: &RedisValue) -> Self {
+        RedisError {
+            io_error: None,
+            message: Some(format!("Unexpected reply: {:?}", value)),
+            kind: ErrorKind::TypeError,
+            code: None,
         }
     }
 
@@ -108,8 +166,27 @@ impl RedisError {
         }
     }
 
+    /// Encode error as Redis error reply (e.g. "-EXECABORT message").
+    /// Error sent by Redis keeps its code.
+    pub fn to_reply(&self) -> Vec<u8> {
+        if let Some(code) = self.code.as_ref() {
+            return format!("-{} {}\r\n", code, self.message()).into_bytes();
+        }
+
+        let code = match self.kind {
+            ErrorKind::ExecAbortError => "EXECABORT",
+            ErrorKind::BusyLoadingError => "LOADING",
+            ErrorKind::NoScriptError => "NOSCRIPT",
+            ErrorKind::WrongTypeError => "WRONGTYPE",
+            ErrorKind::AuthenticationFailed => "NOAUTH",
+            ErrorKind::ReadOnlyError => "READONLY",
+            _ => "ERR",
+        };
+
+        format!("-{} {}\r\n", code, self.message()).into_bytes()
+    }
+
     /// Return message if set.
-    #[allow(dead_code)]
     pub fn message(&self) -> String {
         match self.message.as_ref() {
             Some(s) => s.clone(),
@@ -142,6 +219,22 @@ impl std::fmt::Display for RedisError {
                 "Redis no script error: {}",
                 self.message.as_ref().unwrap()
             ),
+            ErrorKind::WrongTypeError => write!(
+                fmt,
+                "Redis wrong type error: {}",
+                self.message.as_ref().unwrap()
+            ),
+            ErrorKind::AuthenticationFailed => write!(
+                fmt,
+                "Redis authentication failed: {}",
+                self.message.as_ref().unwrap()
+            ),
+            ErrorKind::ReadOnlyError => write!(
+                fmt,
+                "Redis read only error: {}",
+                self.message.as_ref().unwrap()
+            ),
+            ErrorKind::TypeError => write!(fmt, "Type error: {}", self.message.as_ref().unwrap()),
             ErrorKind::OtherError => write!(
                 fmt,
                 "Error (not Redis error): {}",
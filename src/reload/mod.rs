@@ -0,0 +1,141 @@
+//! This module contains hot reload of configuration.
+//!
+//! On SIGHUP or admin `RELOAD`, config file is read again and compared to running config. Fields
+//! that can change live are applied by main loop, fields read when a client connects apply to new
+//! clients only, others are reported as needing a restart.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::config::Config;
+
+/// Fields of config changed by a reload.
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigChanges {
+    /// Fields applied without restart.
+    pub live: Vec<&'static str>,
+    /// Fields applied to new clients, connected clients keep running value.
+    pub new_connections: Vec<&'static str>,
+    /// Fields ignored until restart.
+    pub restart: Vec<&'static str>,
+}
+
+impl ConfigChanges {
+    /// Return report like Redis INFO command: "name:value\r\n".
+    pub fn render(&self) -> String {
+        format!(
+            "# Reload\r\napplied:{}\r\nnew_connections_only:{}\r\nrestart_required:{}\r\n",
+            self.live.join(","),
+            self.new_connections.join(","),
+            self.restart.join(",")
+        )
+    }
+}
+
+/// When a changed field is applied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Apply {
+    /// Field is applied by main loop.
+    Live,
+    /// Field is copied in each client when it connects.
+    NewConnections,
+    /// Field keeps running value until restart.
+    Restart,
+}
+
+/// Compare running config with new config.
+pub fn diff_config(old: &Config, new: &Config) -> ConfigChanges {
+    let mut changes = ConfigChanges::default();
+
+    let mut check = |name: &'static str, changed: bool, apply: Apply| match (changed, apply) {
+        (false, _) => {}
+        (true, Apply::Live) => changes.live.push(name),
+        (true, Apply::NewConnections) => changes.new_connections.push(name),
+        (true, Apply::Restart) => changes.restart.push(name),
+    };
+
+    check("bind", old.bind != new.bind, Apply::Restart);
+    check(
+        "group_name",
+        old.group_name != new.group_name,
+        Apply::Restart,
+    );
+    // Sentinels can change live, but not switch to standalone mode or back
+    check(
+        "sentinels",
+        old.sentinels != new.sentinels,
+        if old.sentinels.is_some() && new.sentinels.is_some() {
+            Apply::Live
+        } else {
+            Apply::Restart
+        },
+    );
+    check("master", old.master != new.master, Apply::Restart);
+    check("nodes", old.nodes != new.nodes, Apply::Restart);
+    check(
+        "master_check_freqency",
+        old.master_check_freqency != new.master_check_freqency,
+        Apply::Restart,
+    );
+    check("log", old.log != new.log, Apply::Live);
+    check("timeout", old.timeout != new.timeout, Apply::Live);
+    check("workers", old.workers != new.workers, Apply::Live);
+    check(
+        "namespace",
+        old.namespace != new.namespace,
+        Apply::NewConnections,
+    );
+    check(
+        "rate_limit",
+        old.rate_limit != new.rate_limit,
+        Apply::NewConnections,
+    );
+    check(
+        "max_clients",
+        old.max_clients != new.max_clients,
+        Apply::Live,
+    );
+    check(
+        "client_idle_timeout",
+        old.client_idle_timeout != new.client_idle_timeout,
+        Apply::Live,
+    );
+    check("admin", old.admin != new.admin, Apply::Restart);
+    check(
+        "blocking_failover",
+        old.blocking_failover != new.blocking_failover,
+        Apply::NewConnections,
+    );
+    check(
+        "split_brain",
+        old.split_brain != new.split_brain,
+        Apply::Restart,
+    );
+    check("upgrade", old.upgrade != new.upgrade, Apply::Restart);
+    check("processes", old.processes != new.processes, Apply::Restart);
+
+    changes
+}
+
+/// Return new config where fields that need a restart keep their running value.
+pub fn merge_config(old: &Config, new: Config) -> Config {
+    let sentinels = if old.sentinels.is_some() && new.sentinels.is_some() {
+        new.sentinels
+    } else {
+        old.sentinels.clone()
+    };
+
+    Config {
+        bind: old.bind.clone(),
+        group_name: old.group_name.clone(),
+        sentinels,
+        master: old.master.clone(),
+        nodes: old.nodes.clone(),
+        master_check_freqency: old.master_check_freqency,
+        admin: old.admin.clone(),
+        split_brain: old.split_brain.clone(),
+        upgrade: old.upgrade.clone(),
+        processes: old.processes,
+        ..new
+    }
+}
@@ -0,0 +1,49 @@
+use crate::config::Config;
+use crate::reload::{diff_config, merge_config};
+
+fn config(yaml: &str) -> Config {
+    serde_yaml2::from_str(yaml).unwrap()
+}
+
+#[test]
+fn diff_live_and_restart_fields() {
+    let old = config("bind: 127.0.0.1:6578\ngroup_name: mymaster\nmaster: redis:6379\n");
+    let new =
+        config("bind: 0.0.0.0:6578\ngroup_name: mymaster\nmaster: redis:6379\nmax_clients: 10\n");
+
+    let changes = diff_config(&old, &new);
+
+    assert_eq!(changes.live, vec!["max_clients"]);
+    assert_eq!(changes.restart, vec!["bind"]);
+    assert!(diff_config(&old, &old).live.is_empty());
+}
+
+#[test]
+fn diff_new_connections_fields() {
+    let old = config("bind: 127.0.0.1:6578\ngroup_name: mymaster\nmaster: redis:6379\n");
+    let new = config(
+        "bind: 127.0.0.1:6578\ngroup_name: mymaster\nmaster: redis:6379\nnamespace:\n  prefix: 'app:'\n",
+    );
+
+    let changes = diff_config(&old, &new);
+
+    assert!(changes.live.is_empty());
+    assert_eq!(changes.new_connections, vec!["namespace"]);
+    assert!(changes
+        .render()
+        .contains("new_connections_only:namespace\r\n"));
+}
+
+#[test]
+fn merge_keeps_restart_fields() {
+    let old = config("bind: 127.0.0.1:6578\ngroup_name: mymaster\nmaster: redis:6379\n");
+    let new = config(
+        "bind: 0.0.0.0:6578\ngroup_name: other\nmaster: redis:6379\nclient_idle_timeout: 30\n",
+    );
+
+    let merged = merge_config(&old, new);
+
+    assert_eq!(merged.bind, "127.0.0.1:6578");
+    assert_eq!(merged.group_name, "mymaster");
+    assert_eq!(merged.client_idle_timeout, 30);
+}
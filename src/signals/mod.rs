@@ -0,0 +1,47 @@
+//! This module contains handling of Unix signals.
+//!
+//! SIGHUP reloads config. SIGTERM and SIGINT stop gracefully: clients are drained, then process
+//! exits. A second SIGTERM or SIGINT exits immediately.
+//!
+use crate::app::messages::MainLoopEvent;
+use crate::redis::types::RedisError;
+use log::{error, info, warn};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+/// Send signals to main loop.
+/// Create a new thread for do this.
+pub fn watch_signals(tx_main_loop_message: Sender<MainLoopEvent>) -> Result<(), RedisError> {
+    let mut signals = Signals::new([SIGHUP, SIGTERM, SIGINT]).map_err(RedisError::from_io_error)?;
+
+    thread::spawn(move || {
+        let mut shutdown = false;
+
+        for signal in signals.forever() {
+            let event = match signal {
+                SIGHUP => {
+                    info!("SIGHUP received, reload config");
+                    MainLoopEvent::reload(None)
+                }
+                _ if shutdown => {
+                    warn!("Second stop signal received, exit now");
+                    std::process::exit(1);
+                }
+                _ => {
+                    info!("Stop signal received, shutdown gracefully");
+                    shutdown = true;
+                    MainLoopEvent::shutdown()
+                }
+            };
+
+            if tx_main_loop_message.send(event).is_err() {
+                error!("Can't send signal, main loop is stopped");
+                return;
+            }
+        }
+    });
+
+    Ok(())
+}
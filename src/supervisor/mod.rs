@@ -0,0 +1,280 @@
+//! This module contains multi-process mode.
+//!
+//! With `processes: N` (N > 1), parent process starts N copies of itself and restarts those that
+//! crash. Each child binds `bind` with SO_REUSEPORT, so kernel spreads clients between them, and
+//! runs its own sentinel watcher, workers and main loop. Parent forwards SIGHUP (reload) and
+//! SIGTERM/SIGINT (graceful shutdown) to children.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::config::Config;
+use log::{error, info, warn};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::os::unix::io::{FromRawFd, OwnedFd};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+use std::{env, io, mem, ptr, thread};
+
+/// Environment variable that gives index of child process.
+pub const PROCESS_ENV: &str = "REDCONCENTRATOR_PROCESS";
+/// How often children are checked.
+const SUPERVISE_CHECK: Duration = Duration::from_millis(500);
+/// Delay before restarting a crashed child.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+/// Size of queue of pending connections, like std.
+const LISTEN_BACKLOG: libc::c_int = 128;
+
+/// Child process slot.
+struct ChildProcess {
+    index: u8,
+    /// Running process.
+    process: Option<Child>,
+    /// Crashed, restart at this time.
+    restart_at: Option<Instant>,
+}
+
+/// Index of this process if started by supervisor.
+pub fn child_index() -> Option<u8> {
+    env::var(PROCESS_ENV).ok().and_then(|v| v.parse().ok())
+}
+
+/// Config of child process: admin interface of child N listens on port + N.
+pub fn child_config(mut config: Config, index: u8) -> Result<Config, String> {
+    if let Some(admin) = config.admin.as_mut() {
+        if let Ok(mut addr) = admin.bind.parse::<SocketAddr>() {
+            match addr.port().checked_add(u16::from(index)) {
+                Some(port) => addr.set_port(port),
+                None => return Err(format!("No admin port for process {} after {}", index, addr)),
+            }
+
+            admin.bind = addr.to_string();
+        }
+    }
+
+    Ok(config)
+}
+
+/// Child process receives SIGTERM if supervisor dies.
+pub fn stop_with_parent() {
+    // Safety: prctl with integer arguments only.
+    #[cfg(target_os = "linux")]
+    unsafe {
+        libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+    }
+}
+
+/// Return true if child must be restarted.
+pub fn must_restart(status: ExitStatus, stopping: bool) -> bool {
+    // Exit status 0 is a graceful shutdown
+    !stopping && !status.success()
+}
+
+/// Convert address to C socket address.
+fn to_sockaddr(addr: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    // Safety: sockaddr_storage is a plain C struct, big enough for any socket address.
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+
+    let len = match addr {
+        SocketAddr::V4(a) => {
+            // Safety: zeroed plain C struct
+            let mut sin: libc::sockaddr_in = unsafe { mem::zeroed() };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = a.port().to_be();
+            sin.sin_addr = libc::in_addr {
+                s_addr: u32::from_ne_bytes(a.ip().octets()),
+            };
+
+            // Safety: sockaddr_in fits in sockaddr_storage
+            unsafe { ptr::write(&mut storage as *mut _ as *mut libc::sockaddr_in, sin) };
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(a) => {
+            // Safety: zeroed plain C struct
+            let mut sin6: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = a.port().to_be();
+            sin6.sin6_flowinfo = a.flowinfo();
+            sin6.sin6_addr = libc::in6_addr {
+                s6_addr: a.ip().octets(),
+            };
+            sin6.sin6_scope_id = a.scope_id();
+
+            // Safety: sockaddr_in6 fits in sockaddr_storage
+            unsafe { ptr::write(&mut storage as *mut _ as *mut libc::sockaddr_in6, sin6) };
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    (storage, len as libc::socklen_t)
+}
+
+/// Return error of last system call if result is negative.
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+/// Listen address with SO_REUSEPORT, so several processes share it.
+pub fn bind_reuse_port(addr: &str) -> io::Result<TcpListener> {
+    let addr = match addr.to_socket_addrs()?.next() {
+        Some(a) => a,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Can't resolve {}", addr),
+            ))
+        }
+    };
+    let domain = match addr {
+        SocketAddr::V4(_) => libc::AF_INET,
+        SocketAddr::V6(_) => libc::AF_INET6,
+    };
+    let (storage, len) = to_sockaddr(&addr);
+    let one: libc::c_int = 1;
+
+    // Safety: descriptor is checked then owned by OwnedFd (closed on error), pointers passed to
+    // setsockopt() and bind() are valid for given lengths.
+    unsafe {
+        let fd = check(libc::socket(
+            domain,
+            libc::SOCK_STREAM | libc::SOCK_CLOEXEC,
+            0,
+        ))?;
+        let socket = OwnedFd::from_raw_fd(fd);
+
+        for option in [libc::SO_REUSEADDR, libc::SO_REUSEPORT] {
+            check(libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                option,
+                &one as *const libc::c_int as *const libc::c_void,
+                mem::size_of::<libc::c_int>() as libc::socklen_t,
+            ))?;
+        }
+
+        check(libc::bind(
+            fd,
+            &storage as *const _ as *const libc::sockaddr,
+            len,
+        ))?;
+        check(libc::listen(fd, LISTEN_BACKLOG))?;
+
+        Ok(TcpListener::from(socket))
+    }
+}
+
+/// Start a copy of this process with same arguments.
+fn spawn_child(index: u8) -> io::Result<Child> {
+    Command::new(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .env(PROCESS_ENV, index.to_string())
+        // Own process group: Ctrl-C reaches only supervisor, that forwards it once
+        .process_group(0)
+        .spawn()
+}
+
+/// Send signal to running children.
+fn forward_signal(children: &[ChildProcess], signal: libc::c_int) {
+    for child in children {
+        if let Some(process) = child.process.as_ref() {
+            // Safety: kill with integer arguments only.
+            unsafe { libc::kill(process.id() as libc::pid_t, signal) };
+        }
+    }
+}
+
+/// Start children and restart those that crash, until all children exit.
+pub fn supervise(processes: u8) -> Result<(), String> {
+    let mut signals = Signals::new([SIGHUP, SIGTERM, SIGINT]).map_err(|e| e.to_string())?;
+    let mut children: Vec<ChildProcess> = (0..processes)
+        .map(|index| ChildProcess {
+            index,
+            process: None,
+            restart_at: Some(Instant::now()),
+        })
+        .collect();
+    let mut stopping = false;
+
+    info!("Start {} processes", processes);
+
+    loop {
+        for signal in signals.pending() {
+            if signal == SIGHUP {
+                info!("SIGHUP received, reload config of processes");
+                forward_signal(&children, SIGHUP);
+            } else if !stopping {
+                info!("Stop signal received, stop processes");
+                stopping = true;
+                forward_signal(&children, SIGTERM);
+            } else {
+                warn!("Second stop signal received, kill processes");
+                forward_signal(&children, libc::SIGKILL);
+            }
+        }
+
+        for child in children.iter_mut() {
+            if let Some(process) = child.process.as_mut() {
+                match process.try_wait() {
+                    Ok(Some(status)) if must_restart(status, stopping) => {
+                        error!(
+                            "Process {} (pid {}) crashed ({}), restart it",
+                            child.index,
+                            process.id(),
+                            describe(status)
+                        );
+                        child.process = None;
+                        child.restart_at = Some(Instant::now() + RESTART_DELAY);
+                    }
+                    Ok(Some(status)) => {
+                        info!("Process {} exited ({})", child.index, describe(status));
+                        child.process = None;
+                    }
+                    Ok(None) => {}
+                    Err(e) => error!("Can't check process {}: {}", child.index, e),
+                }
+            }
+
+            match child.restart_at {
+                Some(_) if stopping => child.restart_at = None,
+                Some(at) if at <= Instant::now() => match spawn_child(child.index) {
+                    Ok(process) => {
+                        info!("Process {} started (pid {})", child.index, process.id());
+                        child.process = Some(process);
+                        child.restart_at = None;
+                    }
+                    Err(e) => {
+                        error!("Can't start process {}: {}", child.index, e);
+                        child.restart_at = Some(Instant::now() + RESTART_DELAY);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        if children
+            .iter()
+            .all(|c| c.process.is_none() && c.restart_at.is_none())
+        {
+            info!("All processes exited");
+            return Ok(());
+        }
+
+        thread::sleep(SUPERVISE_CHECK);
+    }
+}
+
+/// Describe exit status for logs.
+fn describe(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("status {}", code),
+        (None, Some(signal)) => format!("signal {}", signal),
+        _ => String::from("unknown"),
+    }
+}
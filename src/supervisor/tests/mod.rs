@@ -0,0 +1,54 @@
+use crate::config::Config;
+use crate::supervisor::{bind_reuse_port, child_config, must_restart};
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+#[test]
+fn processes_share_port() {
+    let first = bind_reuse_port("127.0.0.1:0").unwrap();
+    let addr = first.local_addr().unwrap().to_string();
+    let second = bind_reuse_port(&addr).unwrap();
+
+    assert_eq!(second.local_addr().unwrap(), first.local_addr().unwrap());
+}
+
+#[test]
+fn child_admin_port_follows_index() {
+    let config: Config = serde_yaml2::from_str(
+        "bind: 127.0.0.1:6578\ngroup_name: g\nmaster: redis:6379\nadmin:\n  bind: 127.0.0.1:6579\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        child_config(config.clone(), 0).unwrap().admin.unwrap().bind,
+        "127.0.0.1:6579"
+    );
+    assert_eq!(
+        child_config(config, 2).unwrap().admin.unwrap().bind,
+        "127.0.0.1:6581"
+    );
+}
+
+#[test]
+fn child_admin_port_overflow() {
+    let config: Config = serde_yaml2::from_str(
+        "bind: 127.0.0.1:6578\ngroup_name: g\nmaster: redis:6379\nadmin:\n  bind: 127.0.0.1:65535\n",
+    )
+    .unwrap();
+
+    assert!(child_config(config.clone(), 0).is_ok());
+    assert!(child_config(config, 1).is_err());
+}
+
+#[test]
+fn restart_crashed_child_only() {
+    // Raw wait status: exit code in high byte, signal in low bits
+    let success = ExitStatus::from_raw(0);
+    let failure = ExitStatus::from_raw(1 << 8);
+    let killed = ExitStatus::from_raw(libc::SIGSEGV);
+
+    assert!(!must_restart(success, false));
+    assert!(must_restart(failure, false));
+    assert!(must_restart(killed, false));
+    assert!(!must_restart(killed, true));
+}
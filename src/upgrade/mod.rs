@@ -0,0 +1,265 @@
+//! This module contains zero-downtime restart.
+//!
+//! Running process waits on a Unix socket (`upgrade.socket`). At startup, a new process connects
+//! to it: running process sends its listening socket with SCM_RIGHTS, then drains its clients and
+//! exits. With `upgrade.clients`, clients without command in flight nor state are sent too
+//! instead of being closed. New process accepts clients on inherited socket, so no connection is
+//! refused during restart.
+//!
+#[cfg(test)]
+pub mod tests;
+
+use crate::app::messages::MainLoopEvent;
+use crate::metrics;
+use crate::redis::types::RedisError;
+use log::{error, info, warn};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc::Sender;
+use std::{io, mem, ptr, thread, time};
+
+/// Max descriptors in one message (SCM_MAX_FD is 253 on Linux).
+const MAX_FDS: usize = 250;
+/// Sent by new process to ask sockets.
+const UPGRADE_REQUEST: &[u8] = b"U";
+/// Message carries listening socket.
+const LISTENER: u8 = b'L';
+/// Message carries clients.
+const CLIENTS: u8 = b'C';
+/// Max time to wait sockets from running process.
+const UPGRADE_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+/// Size of control buffer for fds_count descriptors, in u64 to keep cmsghdr aligned.
+fn control_len(fds_count: usize) -> usize {
+    // Safety: CMSG_SPACE only computes a size.
+    let space = unsafe { libc::CMSG_SPACE((fds_count * mem::size_of::<RawFd>()) as u32) };
+
+    (space as usize).div_ceil(mem::size_of::<u64>())
+}
+
+/// Send descriptors in one message of given kind.
+fn send_fds(stream: &UnixStream, fds: &[RawFd], kind: u8) -> io::Result<()> {
+    let data = [kind];
+    let mut iov = libc::iovec {
+        iov_base: data.as_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    let mut control = vec![0u64; control_len(fds.len())];
+
+    // Safety: msghdr is a plain C struct, all pointers set below outlive sendmsg().
+    let sent = unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+
+        if !fds.is_empty() {
+            msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = (control.len() * mem::size_of::<u64>()) as _;
+
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of_val(fds) as u32) as _;
+
+            ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg) as *mut RawFd, fds.len());
+        }
+
+        libc::sendmsg(stream.as_raw_fd(), &msg, 0)
+    };
+
+    if sent < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Receive kind and descriptors of one message. Return None if connection is closed.
+fn recv_fds(stream: &UnixStream) -> io::Result<Option<(u8, Vec<OwnedFd>)>> {
+    let mut data = [0u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    let mut control = vec![0u64; control_len(MAX_FDS)];
+    let mut fds = Vec::new();
+
+    // Safety: msghdr is a plain C struct, all pointers set below outlive recvmsg(). Control
+    // messages are read only in the length returned by kernel.
+    let received = unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = (control.len() * mem::size_of::<u64>()) as _;
+
+        let received = libc::recvmsg(stream.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC);
+
+        if received > 0 {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                    let len = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                    let first = libc::CMSG_DATA(cmsg) as *const RawFd;
+
+                    for i in 0..len / mem::size_of::<RawFd>() {
+                        // Descriptor is new, owned only here
+                        fds.push(OwnedFd::from_raw_fd(ptr::read_unaligned(first.add(i))));
+                    }
+                }
+
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+        }
+
+        received
+    };
+
+    match received {
+        r if r < 0 => Err(io::Error::last_os_error()),
+        0 => Ok(None),
+        _ => Ok(Some((data[0], fds))),
+    }
+}
+
+/// Send listening socket, first message of upgrade.
+pub fn send_listener(stream: &UnixStream, listener: RawFd) -> io::Result<()> {
+    send_fds(stream, &[listener], LISTENER)
+}
+
+/// Send clients, in as many messages as needed.
+pub fn send_clients(stream: &UnixStream, clients: &[RawFd]) -> io::Result<()> {
+    for batch in clients.chunks(MAX_FDS) {
+        send_fds(stream, batch, CLIENTS)?;
+    }
+
+    Ok(())
+}
+
+/// Receive listening socket sent by send_listener().
+pub fn receive_listener(stream: &UnixStream) -> io::Result<TcpListener> {
+    match recv_fds(stream)? {
+        Some((LISTENER, fds)) if fds.len() == 1 => Ok(TcpListener::from(
+            fds.into_iter().next().expect("one descriptor"),
+        )),
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "No listening socket received",
+        )),
+    }
+}
+
+/// Receive clients sent by send_clients(). Return None when running process closes connection.
+pub fn receive_clients(stream: &UnixStream) -> io::Result<Option<Vec<TcpStream>>> {
+    match recv_fds(stream)? {
+        Some((CLIENTS, fds)) => Ok(Some(fds.into_iter().map(TcpStream::from).collect())),
+        Some(_) => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Unexpected upgrade message",
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Ask listening socket to running process. Return None if no process waits on Unix socket.
+/// Returned stream carries clients until running process exits.
+pub fn inherit_listener(path: &str) -> Result<Option<(TcpListener, UnixStream)>, RedisError> {
+    let mut stream = match UnixStream::connect(path) {
+        Ok(s) => s,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            return Ok(None)
+        }
+        Err(e) => return Err(RedisError::from_io_error(e)),
+    };
+
+    info!("Running process found on {}, inherit its sockets", path);
+
+    stream
+        .set_read_timeout(Some(UPGRADE_TIMEOUT))
+        .map_err(RedisError::from_io_error)?;
+    stream
+        .write_all(UPGRADE_REQUEST)
+        .map_err(RedisError::from_io_error)?;
+
+    let listener = receive_listener(&stream).map_err(RedisError::from_io_error)?;
+
+    // Clients come while running process drains
+    stream
+        .set_read_timeout(None)
+        .map_err(RedisError::from_io_error)?;
+
+    Ok(Some((listener, stream)))
+}
+
+/// Receive clients of running process in a new thread and send them to main loop like new
+/// clients.
+pub fn resume_clients(stream: UnixStream, tx: Sender<MainLoopEvent>) {
+    thread::spawn(move || {
+        let mut count = 0;
+
+        loop {
+            let clients = match receive_clients(&stream) {
+                Ok(Some(clients)) => clients,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Can't receive clients of previous process: {}", e);
+                    break;
+                }
+            };
+
+            for client_stream in clients {
+                match client_stream.peer_addr() {
+                    Ok(client_addr) => {
+                        count += 1;
+                        // Client was connected before upgrade, max_clients doesn't apply
+                        metrics::CONNECTED_CLIENTS.increment();
+                        let _ = tx.send(MainLoopEvent::new_client(client_stream, client_addr));
+                    }
+                    Err(e) => warn!("Inherited client is closed: {}", e),
+                }
+            }
+        }
+
+        info!("{} clients inherited from previous process", count);
+    });
+}
+
+/// Wait new process on Unix socket in a new thread.
+pub fn watch_upgrade(path: &str, tx: Sender<MainLoopEvent>) -> Result<(), RedisError> {
+    // Socket of previous process is replaced
+    let _ = std::fs::remove_file(path);
+
+    let listener = UnixListener::bind(path).map_err(RedisError::from_io_error)?;
+
+    info!("Wait upgrade on {}", path);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    error!("Error when establish upgrade connection {:?}.", e);
+                    continue;
+                }
+            };
+
+            let mut request = [0u8; 1];
+            let _ = stream.set_read_timeout(Some(UPGRADE_TIMEOUT));
+
+            match stream.read_exact(&mut request) {
+                Ok(()) if request == UPGRADE_REQUEST => {
+                    info!("New process asks sockets");
+
+                    let _ = tx.send(MainLoopEvent::upgrade(stream));
+                }
+                Ok(()) => warn!("Unknown upgrade request {:?}", request),
+                Err(e) => warn!("Can't read upgrade request: {}", e),
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::upgrade::{receive_clients, receive_listener, send_clients, send_listener};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+
+#[test]
+fn send_listener_and_clients() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = TcpStream::connect(addr).unwrap();
+    // More clients than one message can carry
+    let clients: Vec<TcpStream> = (0..260).map(|_| client.try_clone().unwrap()).collect();
+    let fds: Vec<RawFd> = clients.iter().map(|c| c.as_raw_fd()).collect();
+    let (old_process, new_process) = UnixStream::pair().unwrap();
+
+    send_listener(&old_process, listener.as_raw_fd()).unwrap();
+    send_clients(&old_process, &fds).unwrap();
+    drop(old_process);
+
+    let inherited = receive_listener(&new_process).unwrap();
+
+    assert_eq!(inherited.local_addr().unwrap(), addr);
+
+    let mut received = Vec::new();
+
+    while let Some(mut batch) = receive_clients(&new_process).unwrap() {
+        received.append(&mut batch);
+    }
+
+    assert_eq!(received.len(), 260);
+    assert_eq!(
+        received[259].local_addr().unwrap(),
+        client.local_addr().unwrap()
+    );
+}
+
+#[test]
+fn listener_must_come_first() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (old_process, new_process) = UnixStream::pair().unwrap();
+
+    send_clients(&old_process, &[client.as_raw_fd()]).unwrap();
+
+    assert!(receive_listener(&new_process).is_err());
+}
@@ -19,4 +19,12 @@ impl WorkerEvent {
             shutdown: false,
         }
     }
-}
\ No newline at end of file
+
+    /// Create a message to stop a worker
+    pub fn shutdown() -> Self {
+        Self {
+            client: None,
+            shutdown: true,
+        }
+    }
+}
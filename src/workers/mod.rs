@@ -2,13 +2,14 @@
 //! and read data from redis to write to client
 use std::thread;
 use std::sync::mpsc::{self, Receiver, Sender};
-use log::{debug, error};
+use log::{debug, error, warn};
 use uuid::Uuid;
 use crate::app::messages::{ClientConnectionParameter, MainLoopEvent};
-use crate::redis::stream::network::NetworkStream;
 use crate::redis::stream::RedisStream;
+use crate::redis::types::RedisError;
 
 pub mod messages;
+pub mod push;
 
 /// To send message to worker
 pub type WorkerEventReceiver = Sender<messages::WorkerEvent>;
@@ -16,7 +17,8 @@ pub type WorkerEventReceiver = Sender<messages::WorkerEvent>;
 enum ErrorWorkerLoop {
     Stop,
     GetMessageFailed,
-    ClientError(std::io::Error),
+    /// Client is closed, id of client and error
+    ClientError(String, std::io::Error),
 }
 
 /// Create a worker
@@ -46,8 +48,10 @@ pub fn create_one_worker(name: String, tx_main_loop_message: Sender<MainLoopEven
                     // Exit thread
                     return;
                 }
-                Err(ErrorWorkerLoop::ClientError(e)) => {
-                    // TODO send message to remove client
+                Err(ErrorWorkerLoop::ClientError(id, e)) => {
+                    // Client is dropped (connections closed, slot released), worker goes back to pool
+                    warn!("Close client {}: {}", id, e);
+                    tx_main_loop_message.send(MainLoopEvent::worker_get_client(name.clone(), tx_worker_message.clone())).unwrap();
                 }
             }
         }
@@ -78,12 +82,12 @@ fn run_worker_loop(name: &String, rx_worker_message: &Receiver<messages::WorkerE
 
             let mut client = event.client.unwrap();
 
-            if let Err(e) = copy_data_from_client_to_redis(&mut client.client_stream, &mut client.redis_stream) {
-                return Err(ErrorWorkerLoop::ClientError(e));
+            if let Err(e) = copy_data_from_client_to_redis(&mut client) {
+                return Err(ErrorWorkerLoop::ClientError(client.id.clone(), e));
             }
 
-            if let Err(e) = copy_data_from_redis_to_client(&mut client.client_stream, &mut client.redis_stream) {
-                return Err(ErrorWorkerLoop::ClientError(e));
+            if let Err(e) = copy_data_from_redis_to_client(&mut client) {
+                return Err(ErrorWorkerLoop::ClientError(client.id.clone(), e));
             }
 
             Ok(client)
@@ -95,32 +99,57 @@ fn run_worker_loop(name: &String, rx_worker_message: &Receiver<messages::WorkerE
     }
 }
 
+/// Convert protocol error to io error.
+fn protocol_error(e: RedisError) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Return true if data are sent to Redis.
 #[inline]
-fn copy_data_from_client_to_redis(client_stream: &mut NetworkStream, redis_stream: &mut NetworkStream) -> Result<(), std::io::Error> {
+pub fn copy_data_from_client_to_redis(client: &mut ClientConnectionParameter) -> Result<bool, std::io::Error> {
+    // Data not written last time go first, new data wait while Redis doesn't read
+    client.redis_stream.flush_output()?;
+
     // Copy data from client to redis master
-    match client_stream.get_data(2048) {
-        Ok(data) => {
-            if let Err(e) = redis_stream.write(data.as_ref()) {
-                return Err(e);
-            }
-        }
-        Err(e) => return Err(e)
+    let data = if client.session.wants_data() && !client.redis_stream.has_pending_output() {
+        client.client_stream.get_data(2048)?
+    } else {
+        Vec::new()
     };
 
-    Ok(())
+    // Session is always called, some commands can wait in session buffer
+    let data = client.session.read_from_client(&data).map_err(protocol_error)?;
+
+    if data.is_empty() {
+        return Ok(false);
+    }
+
+    client.redis_stream.write(data.as_ref())?;
+
+    Ok(true)
 }
 
+/// Return true if data are sent to client.
 #[inline]
-fn copy_data_from_redis_to_client(client_stream: &mut NetworkStream, redis_stream: &mut NetworkStream) -> Result<(), std::io::Error> {
+pub fn copy_data_from_redis_to_client(client: &mut ClientConnectionParameter) -> Result<bool, std::io::Error> {
+    // Data not written last time go first, new data wait while client doesn't read
+    client.client_stream.flush_output()?;
+
     // Copy data from redis to client
-    match redis_stream.get_data(2048) {
-        Ok(data) => {
-            if let Err(e) = client_stream.write(data.as_ref()) {
-                return Err(e);
-            }
-        },
-        Err(e) => return Err(e)
+    let data = if client.client_stream.has_pending_output() {
+        Vec::new()
+    } else {
+        client.redis_stream.get_data(2048)?
+    };
+
+    // Session is always called, some replies can be generated by RedConcentrator
+    let data = client.session.read_from_redis(&data).map_err(protocol_error)?;
+
+    if data.is_empty() {
+        return Ok(false);
     }
 
-    Ok(())
-}
\ No newline at end of file
+    client.client_stream.write(data.as_ref())?;
+
+    Ok(true)
+}
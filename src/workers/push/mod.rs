@@ -0,0 +1,192 @@
+//! This module contains routine of push worker.
+//!
+//! Clients in subscribe mode don't follow request/response pattern: Redis sends messages when
+//! they are published. Push worker keeps these clients and streams messages as they arrive.
+//! When a client leaves subscribe mode, it's sent back to main loop.
+//!
+//! Clients waiting reply of a blocking command (BLPOP...) are kept by another push worker
+//! (wait worker) until reply arrives, so they don't cycle through workers.
+//!
+use crate::app::messages::{ClientConnectionParameter, MainLoopEvent};
+use crate::workers::{copy_data_from_client_to_redis, copy_data_from_redis_to_client};
+use log::{debug, error, warn};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// Time to wait when no data are available.
+const IDLE_WAIT: Duration = Duration::from_millis(1);
+
+/// Message to push worker.
+#[derive(Debug)]
+pub struct PushEvent {
+    /// Client enters subscribe mode or waits a blocking command
+    pub client: Option<ClientConnectionParameter>,
+    /// Address of new master
+    pub master_change: Option<String>,
+    /// Close subscribed clients, stop when blocked clients are released
+    pub shutdown: bool,
+}
+
+impl PushEvent {
+    /// Create a message to send a subscribed or blocked client to push worker
+    pub fn send_client(client: ClientConnectionParameter) -> Self {
+        Self {
+            client: Some(client),
+            master_change: None,
+            shutdown: false,
+        }
+    }
+
+    /// Create a message to notify that the master address change
+    pub fn master_change(redis_addr: String) -> Self {
+        Self {
+            client: None,
+            master_change: Some(redis_addr),
+            shutdown: false,
+        }
+    }
+
+    /// Create a message to stop push worker
+    pub fn shutdown() -> Self {
+        Self {
+            client: None,
+            master_change: None,
+            shutdown: true,
+        }
+    }
+}
+
+/// Create push worker.
+pub fn create_push_worker(
+    name: &str,
+    tx_main_loop_message: Sender<MainLoopEvent>,
+) -> Sender<PushEvent> {
+    let (tx_push_message, rx_push_message): (Sender<PushEvent>, Receiver<PushEvent>) =
+        mpsc::channel();
+
+    debug!("create_push_worker(): Start push worker: {}", name);
+
+    let _ = thread::Builder::new()
+        .name(String::from(name))
+        .spawn(move || run_push_loop(rx_push_message, tx_main_loop_message));
+
+    tx_push_message
+}
+
+/// Receive message from main loop.
+/// Return true if push worker must stop when no more client.
+fn manage_push_event(event: PushEvent, clients: &mut Vec<ClientConnectionParameter>) -> bool {
+    if let Some(client) = event.client {
+        debug!(
+            "manage_push_event(): Client {} waits data from Redis",
+            client.id
+        );
+
+        clients.push(client);
+    }
+
+    if let Some(redis_addr) = event.master_change {
+        clients.retain_mut(|client| {
+            if client.redis_addr == redis_addr {
+                return true;
+            }
+
+            match client.reconnect(&redis_addr) {
+                Ok(()) => true,
+                Err(e) => {
+                    error!(
+                        "Can't reconnect client {} to new master: {:?}",
+                        client.id, e
+                    );
+                    false
+                }
+            }
+        });
+    }
+
+    if event.shutdown {
+        // Subscribed clients wait nothing, blocked clients wait their reply
+        clients.retain(|client| client.session.is_blocked());
+    }
+
+    event.shutdown
+}
+
+/// Main loop of push worker.
+fn run_push_loop(
+    rx_push_message: Receiver<PushEvent>,
+    tx_main_loop_message: Sender<MainLoopEvent>,
+) {
+    let mut clients: Vec<ClientConnectionParameter> = Vec::new();
+    let mut shutdown = false;
+
+    loop {
+        if shutdown && clients.is_empty() {
+            debug!("run_push_loop(): Push worker stops");
+            return;
+        }
+
+        // Without client, wait a message. Otherwise, only check if a message is here.
+        let event = if clients.is_empty() {
+            rx_push_message
+                .recv_timeout(Duration::from_secs(1))
+                .map_err(|e| match e {
+                    RecvTimeoutError::Timeout => TryRecvError::Empty,
+                    RecvTimeoutError::Disconnected => TryRecvError::Disconnected,
+                })
+        } else {
+            rx_push_message.try_recv()
+        };
+
+        match event {
+            Ok(event) => shutdown |= manage_push_event(event, &mut clients),
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => {
+                error!("Push worker can't get message from main loop cause his channel is closed");
+                return;
+            }
+        }
+
+        let mut data_moved = false;
+        let mut index = 0;
+
+        while index < clients.len() {
+            let client = &mut clients[index];
+
+            let result = copy_data_from_client_to_redis(client).and_then(|from_client| {
+                copy_data_from_redis_to_client(client).map(|from_redis| from_client || from_redis)
+            });
+
+            match result {
+                Ok(moved) => data_moved |= moved,
+                Err(e) => {
+                    warn!("Close client {}: {:?}", client.id, e);
+                    clients.swap_remove(index);
+                    continue;
+                }
+            }
+
+            if !client.session.is_subscribed() && !client.session.is_blocked() {
+                let client = clients.swap_remove(index);
+
+                debug!("run_push_loop(): Client {} goes back to workers", client.id);
+
+                if tx_main_loop_message
+                    .send(MainLoopEvent::push_release_client(client))
+                    .is_err()
+                {
+                    return;
+                }
+
+                continue;
+            }
+
+            index += 1;
+        }
+
+        if !data_moved {
+            thread::sleep(IDLE_WAIT);
+        }
+    }
+}